@@ -1,21 +1,44 @@
 pub mod lobby;
+pub mod mesh;
 mod replication;
+#[cfg(feature = "shutdown")]
+pub mod shutdown;
 
 use {
     crate::{
         lobby::PlayerId,
-        replication::{get_child_of_serialization_rules, register_replicated},
+        replication::{
+            get_child_of_serialization_rules, register_client_event, register_replicated,
+            register_server_event,
+        },
     },
     bevy::prelude::*,
     bevy_replicon::prelude::*,
+    core::time::Duration,
     rand::{distr::StandardUniform, prelude::*},
     serde::{Deserialize, Serialize},
+    std::{cmp::Ordering, time::SystemTime},
     uuid::Uuid,
 };
 
 /// How many times per second we will replicate entity components.
 pub const TICK_RATE: u16 = 128;
 
+/// Reason with which the game server disconnects the players after the game is completed.
+pub const GAME_COMPLETED_REASON: &str = "Game completed";
+
+/// Mass of the ball of a player, unless changed by a power up.
+pub const BALL_MASS: f32 = 0.04593;
+
+/// Maximum force of a shot, stronger shots are limited to it.
+pub const MAX_SHOT_FORCE: f32 = 10.0;
+
+/// Version of the messages exchanged between the game client and server.
+///
+/// Must be incremented whenever a replicated component or networked event changes,
+/// so that the server can reject incompatible clients instead of misinterpreting their messages.
+pub const PROTOCOL_VERSION: u32 = 9;
+
 /// Sets up replication and basic game systems.
 #[derive(Debug)]
 pub struct MinigolfPlugin;
@@ -37,26 +60,57 @@ impl Plugin for MinigolfPlugin {
 
         app.register_type::<CourseDetails>();
 
-        app.replicate::<Name>();
         app.replicate::<Transform>();
         app.replicate_with(get_child_of_serialization_rules());
 
         register_replicated::<Player>(app);
+        // display names of players are shown in the scoreboard, while names of other entities
+        // are only for debugging the server, so they are not sent to clients
+        app.replicate_group::<(Player, Name)>();
         register_replicated::<PlayerScore>(app);
+        register_replicated::<PinDistance>(app);
         register_replicated::<PowerUp>(app);
         register_replicated::<PlayerPowerUps>(app);
+        register_replicated::<HoleTimer>(app);
+        register_replicated::<CameraHint>(app);
+        register_replicated::<HoleMusic>(app);
+        register_replicated::<Intermission>(app);
+        register_replicated::<GamePhase>(app);
+        register_replicated::<ScoringMode>(app);
+        register_replicated::<ActiveEffects>(app);
+        register_replicated::<RemainingHits>(app);
+        register_replicated::<CurrentTurn>(app);
+        register_replicated::<DisconnectedPlayer>(app);
+        app.replicate_group::<(DisconnectedPlayer, Name)>();
+        register_replicated::<AbandonedBall>(app);
 
         register_replicated::<LevelMesh>(app);
         register_replicated::<PlayableArea>(app);
+        register_replicated::<HazardZone>(app);
+        register_replicated::<ConveyorZone>(app);
 
-        app.add_server_event::<RequestAuthentication>(Channel::Ordered);
-        app.add_client_event::<AuthenticatePlayer>(Channel::Ordered);
-        app.add_client_event::<PlayerInput>(Channel::Ordered);
+        register_server_event::<RequestAuthentication>(app, Channel::Ordered);
+        register_server_event::<PowerUpNotImplemented>(app, Channel::Ordered);
+        register_server_event::<HoleInOne>(app, Channel::Ordered);
+        register_server_event::<HoleResults>(app, Channel::Ordered);
+        register_client_event::<AuthenticatePlayer>(app, Channel::Ordered);
+        register_client_event::<PlayerInput>(app, Channel::Ordered);
+
+        register_client_event::<SendChat>(app, Channel::Ordered);
+        register_server_event::<ChatMessage>(app, Channel::Ordered);
     }
 }
 
 pub type CourseId = String;
 
+/// Checks that the id only contains ASCII letters, digits, `-` and `_`, so that it is safe to use in asset paths.
+pub fn is_valid_course_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 #[derive(Reflect, Serialize, Deserialize, Clone, Debug)]
 pub struct CourseDetails {
     pub id: CourseId,
@@ -78,6 +132,11 @@ impl Player {
             can_move: false,
         }
     }
+
+    /// Gets a short form of the player id for logs and UI, see [PlayerId::short].
+    pub fn display_id(&self) -> String {
+        self.id.short()
+    }
 }
 
 impl From<PlayerId> for Player {
@@ -92,16 +151,32 @@ impl From<PlayerId> for Player {
 #[derive(Component, Reflect, Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct PlayerCredentials {
     pub secret: String,
+    /// Seconds since the Unix epoch at which the credentials were issued by the lobby server.
+    pub issued_at: u64,
+}
+
+impl PlayerCredentials {
+    /// Whether the credentials were issued more than `ttl` ago.
+    pub fn is_expired(&self, ttl: Duration) -> bool {
+        unix_time().saturating_sub(self.issued_at) > ttl.as_secs()
+    }
 }
 
 impl Default for PlayerCredentials {
     fn default() -> Self {
         PlayerCredentials {
             secret: Uuid::new_v4().into(),
+            issued_at: unix_time(),
         }
     }
 }
 
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
 /// Marker component for entities that the player can interact with.
 #[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
 pub struct PlayableArea;
@@ -110,11 +185,25 @@ pub struct PlayableArea;
 #[require(StateScoped::<GameState>(GameState::Playing))]
 pub struct LevelMesh {
     pub asset: String,
+    /// Height of the walls generated along the outline of the [asset](Self::asset),
+    /// which are shown instead of the asset itself, see [mesh::extrude_outline].
+    pub outline_height: Option<f32>,
 }
 
 impl LevelMesh {
     pub fn from_path(path: &str) -> Self {
-        LevelMesh { asset: path.into() }
+        LevelMesh {
+            asset: path.into(),
+            outline_height: None,
+        }
+    }
+
+    /// Walls with the specified height along the outline of the mesh at the path.
+    pub fn outline_of(path: &str, height: f32) -> Self {
+        LevelMesh {
+            asset: path.into(),
+            outline_height: Some(height),
+        }
     }
 }
 
@@ -123,6 +212,11 @@ impl LevelMesh {
 pub enum PlayerInput {
     /// Move in the specified direction with the specified force.
     Move(Vec2),
+    /// Move in the specified direction with the specified force, aiming vertically to putt up slopes.
+    Putt {
+        direction: Vec3,
+        power: f32,
+    },
 
     /// Teleport to the specified position using the [PowerUpType::Teleport] power up.
     Teleport(Vec3),
@@ -131,7 +225,8 @@ pub enum PlayerInput {
     /// Apply hit force at a 45 degree angle for the next hit using the [PowerUpType::ChipShot] power up.
     ChipShot,
 
-    /// Steal a power up from the specified player using the [PowerUpType::StealPowerUp] power up.
+    /// Steal the most recently gained power up from the specified player
+    /// using the [PowerUpType::StealPowerUp] power up.
     StealPowerUp(PlayerId),
 
     StickyBall,
@@ -155,17 +250,53 @@ impl PlayerInput {
         use PlayerInput::*;
 
         match self {
-            Move(_) => true,
+            Move(_) | Putt { .. } => true,
             _ => false,
         }
     }
 
+    /// Whether all values sent with the input are finite, i.e. neither NaN nor infinite.
+    pub fn is_finite(&self) -> bool {
+        use PlayerInput::*;
+
+        match self {
+            Move(vector) | Wind(vector) => vector.is_finite(),
+            Putt { direction, power } => direction.is_finite() && power.is_finite(),
+            Teleport(vector) | Bumper(vector) | BlackHoleBumper(vector) | Tornado(vector) => {
+                vector.is_finite()
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether the directions sent with the input are long enough to be normalized.
+    pub fn has_valid_direction(&self) -> bool {
+        use PlayerInput::*;
+
+        match self {
+            Wind(direction) => direction.try_normalize().is_some(),
+            Putt { direction, .. } => direction.try_normalize().is_some(),
+            _ => true,
+        }
+    }
+
+    /// Gets the force with which the ball is hit, if this is a movement input.
+    pub fn movement(&self) -> Option<Vec3> {
+        use PlayerInput::*;
+
+        match self {
+            Move(movement) => Some(Vec3::new(movement.x, 0.0, movement.y)),
+            Putt { direction, power } => Some(direction.normalize_or_zero() * *power),
+            _ => None,
+        }
+    }
+
     /// Gets the corresponding [PowerUpType].
     pub fn get_power_up_type(&self) -> Option<PowerUpType> {
         use PlayerInput::*;
 
         match self {
-            Move(_) => None,
+            Move(_) | Putt { .. } => None,
             Teleport(_) => Some(PowerUpType::Teleport),
             HoleMagnet => Some(PowerUpType::HoleMagnet),
             ChipShot => Some(PowerUpType::ChipShot),
@@ -187,6 +318,11 @@ impl PlayerInput {
 
 #[derive(Debug, Clone, Event, Serialize, Deserialize, Reflect)]
 pub struct AuthenticatePlayer {
+    /// [PROTOCOL_VERSION] of the client, clients with a different version are disconnected.
+    ///
+    /// Only detects changes that still deserialize, changes to this event itself
+    /// or to the order of registered events fail before the version can be compared.
+    pub protocol_version: u32,
     pub id: PlayerId,
     pub credentials: PlayerCredentials,
 }
@@ -194,9 +330,248 @@ pub struct AuthenticatePlayer {
 #[derive(Debug, Clone, Event, Serialize, Deserialize, Reflect)]
 pub struct RequestAuthentication;
 
+/// Sent to a player that tried to use a power up which is not implemented yet.
+///
+/// The power up is given back to the player.
+#[derive(Debug, Clone, Event, Serialize, Deserialize, Reflect)]
+pub struct PowerUpNotImplemented {
+    pub power_up: PowerUpType,
+}
+
+/// Sent to all players when a player completes a hole with a single stroke.
+#[derive(Debug, Clone, Event, Serialize, Deserialize, Reflect)]
+pub struct HoleInOne {
+    pub player: PlayerId,
+}
+
+/// Sent to all players when a hole is completed, with the results of each player ordered like the standings,
+/// see [ScoringMode::compare].
+#[derive(Debug, Clone, Event, Serialize, Deserialize, Reflect)]
+pub struct HoleResults {
+    pub standings: Vec<HoleResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
+pub struct HoleResult {
+    pub player: PlayerId,
+    /// Strokes taken on the completed hole, including penalties.
+    pub strokes: u32,
+    /// Score of the player after the completed hole with the handicap applied, see [PlayerScore::total].
+    pub total: u32,
+}
+
 #[derive(Component, Reflect, Serialize, Deserialize, Default, Debug)]
 pub struct PlayerScore {
     pub score: u32,
+    /// Strokes subtracted from the score in the standings, set when the game is created.
+    pub handicap: u32,
+    /// Number of holes on which the player sank their ball first, see [ScoringMode::Race].
+    pub points: u32,
+}
+
+impl PlayerScore {
+    pub fn with_handicap(handicap: u32) -> Self {
+        PlayerScore {
+            score: 0,
+            handicap,
+            points: 0,
+        }
+    }
+
+    /// Gets the score used for the standings, with the handicap applied.
+    pub fn total(&self) -> u32 {
+        self.score.saturating_sub(self.handicap)
+    }
+}
+
+/// A player that disconnected during the game, so that the other players can see that they left.
+///
+/// Spawned as a separate entity with the [Name] of the player, since the player entity is despawned.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct DisconnectedPlayer {
+    pub id: PlayerId,
+}
+
+/// Music played while playing the current hole, looped until the hole is completed.
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct HoleMusic {
+    /// Path of the audio asset.
+    pub asset: String,
+}
+
+/// Ball of a disconnected player, left on the hole as an obstacle for the remaining players.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct AbandonedBall;
+
+/// How the players are ranked in the standings.
+#[derive(
+    Component, Reflect, Serialize, Deserialize, Default, Copy, Clone, PartialEq, Eq, Debug,
+)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub enum ScoringMode {
+    /// Players with the fewest strokes are ranked first.
+    #[default]
+    Strokes,
+    /// The first player to sink their ball on a hole gets a point, and the other players get none.
+    /// Players with the most points are ranked first.
+    Race,
+}
+
+impl ScoringMode {
+    /// Compares the scores, so that better scores are ordered first.
+    pub fn compare(&self, score: &PlayerScore, other: &PlayerScore) -> Ordering {
+        match self {
+            ScoringMode::Strokes => score.total().cmp(&other.total()),
+            ScoringMode::Race => other.points.cmp(&score.points),
+        }
+    }
+
+    /// Gets the value of the score shown to players.
+    pub fn displayed_score(&self, score: &PlayerScore) -> u32 {
+        match self {
+            ScoringMode::Strokes => score.total(),
+            ScoringMode::Race => score.points,
+        }
+    }
+}
+
+/// Closest distances to the cup reached by the player, used as a tiebreaker for equal scores.
+#[derive(Component, Reflect, Serialize, Deserialize, Default, Debug)]
+pub struct PinDistance {
+    /// Closest distance to the cup during the current hole.
+    pub hole: Option<f32>,
+    /// Sum of the closest distances of all completed holes, lower is better.
+    pub total: f32,
+}
+
+/// Suggested camera viewpoint for the current hole, relative to the ball of the local player.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct CameraHint {
+    /// Rotation of the camera around the vertical axis, in radians.
+    pub yaw: f32,
+    /// Horizontal distance of the camera from the ball.
+    pub distance: f32,
+    /// Height of the camera above the ball.
+    pub height: f32,
+}
+
+/// Time remaining for players to complete the current hole.
+#[derive(Component, Reflect, Serialize, Deserialize, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct HoleTimer {
+    /// Remaining time in seconds.
+    pub remaining: f32,
+    /// Player that has to complete their hole in time, all players if not set.
+    pub player: Option<PlayerId>,
+}
+
+/// Break between holes, during which the current standings are shown.
+#[derive(Component, Reflect, Serialize, Deserialize, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct Intermission {
+    /// Remaining time in seconds.
+    pub remaining: f32,
+}
+
+/// Area of a hole, such as water, which gives a penalty stroke to balls that enter it.
+///
+/// The area is a unit cube scaled by the transform of the entity.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct HazardZone;
+
+/// Area of a hole which pushes balls in a direction, like a conveyor belt.
+///
+/// The area is a unit cube scaled by the transform of the entity.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct ConveyorZone {
+    /// Direction in which balls are pushed, relative to the rotation of the entity.
+    pub direction: Vec3,
+}
+
+/// Number of hits left before a temporary bumper disappears.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct RemainingHits(pub usize);
+
+/// Player whose turn it is to shoot, when players take turns.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct CurrentTurn(pub Option<PlayerId>);
+
+/// Power ups currently affecting the environment of the hole that the player is playing.
+#[derive(Component, Reflect, Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub struct ActiveEffects(pub Vec<PowerUpType>);
+
+/// Phase of the game on the server, so that clients do not have to infer it.
+#[derive(
+    Component, Reflect, Serialize, Deserialize, Default, Copy, Clone, PartialEq, Eq, Debug,
+)]
+#[require(StateScoped::<GameState>(GameState::Playing))]
+pub enum GamePhase {
+    #[default]
+    WaitingForPlayers,
+    Playing,
+    /// All the players have completed the current hole, including the intermission after it.
+    HoleComplete,
+    CourseComplete,
+    GameComplete,
+}
+
+/// Maximum number of characters in a chat message, longer messages are truncated.
+pub const CHAT_MESSAGE_MAX_LENGTH: usize = 256;
+
+/// Removes control characters and surrounding whitespace, and limits the length of the message.
+///
+/// Returns [None] if nothing is left of the message.
+pub fn sanitize_chat_message(message: &str) -> Option<String> {
+    sanitize_text(message, CHAT_MESSAGE_MAX_LENGTH)
+}
+
+/// Maximum number of characters in the display name of a player, longer names are truncated.
+pub const PLAYER_NAME_MAX_LENGTH: usize = 32;
+
+/// Removes control characters and surrounding whitespace, and limits the length of the name.
+///
+/// Returns [None] if nothing is left of the name.
+pub fn sanitize_player_name(name: &str) -> Option<String> {
+    sanitize_text(name, PLAYER_NAME_MAX_LENGTH)
+}
+
+/// Gets the display name of a player that did not choose one.
+pub fn default_player_name(id: PlayerId) -> String {
+    format!("Player {}", id.short())
+}
+
+fn sanitize_text(text: &str, max_length: usize) -> Option<String> {
+    let text = text
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(max_length)
+        .collect::<String>();
+
+    let text = text.trim();
+    match text.is_empty() {
+        true => None,
+        false => Some(text.to_owned()),
+    }
+}
+
+/// Chat message or emote that the player wants to send to all other players in the game.
+#[derive(Debug, Clone, Event, Serialize, Deserialize, Reflect)]
+pub struct SendChat(pub String);
+
+/// Chat message sent by a player, broadcast by the server to all players in the game.
+#[derive(Debug, Clone, Event, Serialize, Deserialize, Reflect)]
+pub struct ChatMessage {
+    pub player: PlayerId,
+    pub message: String,
 }
 
 const PLAYER_POWER_UP_LIMIT: usize = 3;
@@ -231,6 +606,16 @@ impl PlayerPowerUps {
         }
     }
 
+    /// Removes the most recently gained power up, which is the one taken by [PowerUpType::StealPowerUp].
+    pub fn take_latest(&mut self) -> Option<PowerUpType> {
+        self.power_ups.pop()
+    }
+
+    /// Keeps only the power ups for which the predicate returns true.
+    pub fn retain(&mut self, predicate: impl Fn(PowerUpType) -> bool) {
+        self.power_ups.retain(|&power_up| predicate(power_up));
+    }
+
     pub fn use_power_up(&mut self, power_up: PowerUpType) -> Option<PowerUpType> {
         if let Some(pos) = self.power_ups.iter().position(|x| *x == power_up) {
             Some(self.power_ups.remove(pos))
@@ -248,10 +633,11 @@ impl Default for PlayerPowerUps {
     }
 }
 
-const IMPLEMENTED_POWER_UPS: [PowerUpType; 9] = [
+const IMPLEMENTED_POWER_UPS: [PowerUpType; 10] = [
     PowerUpType::Teleport,
     PowerUpType::HoleMagnet,
     PowerUpType::ChipShot,
+    PowerUpType::StealPowerUp,
     PowerUpType::StickyBall,
     PowerUpType::Bumper,
     PowerUpType::BlackHoleBumper,
@@ -270,7 +656,7 @@ pub enum PowerUpType {
     BallRepellent, // todo
 
     // Targeting specific player
-    StealPowerUp, // todo
+    StealPowerUp,
 
     // Targeting other players
     StickyBall,
@@ -288,9 +674,360 @@ pub enum PowerUpType {
     IceRink,
 }
 
+impl PowerUpType {
+    /// Whether the power up has an effect, power ups that are not implemented are refunded when used.
+    pub fn is_implemented(&self) -> bool {
+        IMPLEMENTED_POWER_UPS.contains(self)
+    }
+
+    /// Gets a short explanation of the effect of the power up for players.
+    pub fn description(&self) -> &'static str {
+        match self {
+            PowerUpType::Teleport => "Move your ball to any point on the hole.",
+            PowerUpType::HoleMagnet => "Your ball is pulled towards the cup when it gets close.",
+            PowerUpType::GhostBall => "Your ball passes through other balls.",
+            PowerUpType::ChipShot => "Your next shot lifts the ball into the air.",
+            PowerUpType::BallRepellent => "Other balls are pushed away from yours.",
+            PowerUpType::StealPowerUp => "Take the latest power up gained by another player.",
+            PowerUpType::StickyBall => "The balls of other players stick to whatever they hit.",
+            PowerUpType::TinyBall => "The balls of other players shrink.",
+            PowerUpType::HugeBall => "The balls of other players grow.",
+            PowerUpType::ZanyBall => "The balls of other players move unpredictably.",
+            PowerUpType::ReversiBall => "The shots of other players go in the opposite direction.",
+            PowerUpType::Bumper => "Place a bumper that bounces balls away.",
+            PowerUpType::BlackHoleBumper => "Place a bumper that pulls balls in.",
+            PowerUpType::Tornado => "Spawn a tornado that throws balls around.",
+            PowerUpType::Wind => "Wind pushes all balls in one direction.",
+            PowerUpType::StickyWalls => "Balls stick to the walls of the hole.",
+            PowerUpType::IceRink => "The hole becomes slippery, so balls roll further.",
+        }
+    }
+}
+
 impl Distribution<PowerUpType> for StandardUniform {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> PowerUpType {
         let index = rng.random_range(0..IMPLEMENTED_POWER_UPS.len());
         IMPLEMENTED_POWER_UPS[index]
     }
 }
+
+/// Relative weight of a [PowerUpType] in [WeightedPowerUps], lower weights are rarer.
+#[derive(Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+pub struct PowerUpWeight {
+    pub power_up: PowerUpType,
+    pub weight: f32,
+}
+
+/// Distribution of implemented power ups, each chosen proportionally to its weight.
+///
+/// Power ups that are not implemented or have no positive finite weight are never chosen.
+/// If no power up can be chosen, or the weights are too large to add up, falls back to [StandardUniform].
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug)]
+pub struct WeightedPowerUps {
+    pub weights: Vec<PowerUpWeight>,
+}
+
+impl WeightedPowerUps {
+    fn valid_weights(&self) -> impl Iterator<Item = &PowerUpWeight> {
+        self.weights.iter().filter(|entry| {
+            entry.weight.is_finite() && entry.weight > 0.0 && entry.power_up.is_implemented()
+        })
+    }
+
+    /// Whether the power up can be chosen.
+    pub fn contains(&self, power_up: PowerUpType) -> bool {
+        self.valid_weights().any(|entry| entry.power_up == power_up)
+    }
+
+    /// Gets the distribution limited to the enabled power ups,
+    /// or [None] if none of the enabled power ups can be chosen.
+    pub fn restrict_to(&self, enabled: &[PowerUpType]) -> Option<WeightedPowerUps> {
+        let weights: Vec<_> = self
+            .valid_weights()
+            .filter(|entry| enabled.contains(&entry.power_up))
+            .copied()
+            .collect();
+
+        match weights.is_empty() {
+            true => None,
+            false => Some(WeightedPowerUps { weights }),
+        }
+    }
+}
+
+impl Default for WeightedPowerUps {
+    fn default() -> Self {
+        WeightedPowerUps {
+            weights: IMPLEMENTED_POWER_UPS
+                .iter()
+                .map(|&power_up| PowerUpWeight {
+                    power_up,
+                    weight: 1.0,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Distribution<PowerUpType> for WeightedPowerUps {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> PowerUpType {
+        let total_weight: f32 = self.valid_weights().map(|entry| entry.weight).sum();
+        if !total_weight.is_finite() || total_weight <= 0.0 {
+            return StandardUniform.sample(rng);
+        }
+
+        let mut remaining = rng.random_range(0.0..total_weight);
+        let mut last = None;
+
+        for entry in self.valid_weights() {
+            if remaining < entry.weight {
+                return entry.power_up;
+            }
+
+            remaining -= entry.weight;
+            last = Some(entry.power_up);
+        }
+
+        // only reachable due to floating point rounding
+        last.unwrap_or_else(|| StandardUniform.sample(rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        rand::{SeedableRng, rngs::StdRng},
+    };
+
+    #[test]
+    fn inputs_with_nan_or_infinity_are_not_finite() {
+        assert!(PlayerInput::Move(Vec2::new(1.0, 2.0)).is_finite());
+        assert!(!PlayerInput::Move(Vec2::new(f32::NAN, 2.0)).is_finite());
+        assert!(!PlayerInput::Teleport(Vec3::INFINITY).is_finite());
+
+        let putt = PlayerInput::Putt {
+            direction: Vec3::X,
+            power: f32::NEG_INFINITY,
+        };
+        assert!(!putt.is_finite());
+        assert!(PlayerInput::HoleMagnet.is_finite());
+    }
+
+    #[test]
+    fn inputs_without_a_direction_are_not_valid() {
+        assert!(PlayerInput::Wind(Vec2::X).has_valid_direction());
+        assert!(!PlayerInput::Wind(Vec2::ZERO).has_valid_direction());
+        assert!(!PlayerInput::Wind(Vec2::splat(1e-30)).has_valid_direction());
+
+        let putt = PlayerInput::Putt {
+            direction: Vec3::ZERO,
+            power: 1.0,
+        };
+        assert!(!putt.has_valid_direction());
+    }
+
+    #[test]
+    fn course_details_round_trip() {
+        let details = CourseDetails {
+            id: "0002".to_owned(),
+            name: "Test 1".to_owned(),
+        };
+
+        let json = serde_json::to_string(&details).unwrap();
+        assert_eq!(json, r#"{"id":"0002","name":"Test 1"}"#);
+
+        let deserialized: CourseDetails = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.id, details.id);
+        assert_eq!(deserialized.name, details.name);
+    }
+
+    #[test]
+    fn credentials_expire_after_ttl() {
+        let issued = PlayerCredentials::default();
+        assert!(!issued.is_expired(Duration::from_secs(60)));
+
+        let stale = PlayerCredentials {
+            issued_at: issued.issued_at - 120,
+            ..issued
+        };
+        assert!(stale.is_expired(Duration::from_secs(60)));
+        assert!(!stale.is_expired(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn handicap_is_subtracted_from_standing() {
+        let handicapped = PlayerScore {
+            score: 12,
+            ..PlayerScore::with_handicap(3)
+        };
+        let scratch = PlayerScore {
+            score: 10,
+            ..default()
+        };
+
+        assert_eq!(handicapped.total(), 9);
+        assert_eq!(ScoringMode::Strokes.displayed_score(&handicapped), 9);
+        assert_eq!(
+            ScoringMode::Strokes.compare(&handicapped, &scratch),
+            Ordering::Less
+        );
+
+        let beginner = PlayerScore {
+            score: 2,
+            ..PlayerScore::with_handicap(5)
+        };
+        assert_eq!(beginner.total(), 0);
+    }
+
+    #[test]
+    fn weighting_skews_sampled_power_ups() {
+        let weights = WeightedPowerUps {
+            weights: vec![
+                PowerUpWeight {
+                    power_up: PowerUpType::Teleport,
+                    weight: 9.0,
+                },
+                PowerUpWeight {
+                    power_up: PowerUpType::HoleMagnet,
+                    weight: 1.0,
+                },
+            ],
+        };
+
+        let mut rng = StdRng::seed_from_u64(1598);
+        let teleports = (0..1000)
+            .filter(|_| weights.sample(&mut rng) == PowerUpType::Teleport)
+            .count();
+
+        assert!((850..950).contains(&teleports), "{teleports} teleports");
+    }
+
+    #[test]
+    fn infinite_weights_are_not_sampled() {
+        let weights = WeightedPowerUps {
+            weights: vec![
+                PowerUpWeight {
+                    power_up: PowerUpType::Teleport,
+                    weight: f32::INFINITY,
+                },
+                PowerUpWeight {
+                    power_up: PowerUpType::HoleMagnet,
+                    weight: 1.0,
+                },
+            ],
+        };
+
+        let mut rng = StdRng::seed_from_u64(1598);
+        assert_eq!(weights.sample(&mut rng), PowerUpType::HoleMagnet);
+
+        // the sum of the weights is infinite, so the uniform distribution is used instead of panicking
+        let overflowing = WeightedPowerUps {
+            weights: vec![
+                PowerUpWeight {
+                    power_up: PowerUpType::Teleport,
+                    weight: f32::MAX,
+                },
+                PowerUpWeight {
+                    power_up: PowerUpType::HoleMagnet,
+                    weight: f32::MAX,
+                },
+            ],
+        };
+        overflowing.sample(&mut rng);
+    }
+
+    #[test]
+    fn implemented_power_ups_match_the_array() {
+        use {
+            PowerUpType::*,
+            bevy::reflect::{TypeInfo, Typed},
+        };
+
+        let unimplemented = [
+            GhostBall,
+            BallRepellent,
+            TinyBall,
+            HugeBall,
+            ZanyBall,
+            ReversiBall,
+            Tornado,
+        ];
+        assert!(
+            IMPLEMENTED_POWER_UPS
+                .iter()
+                .all(PowerUpType::is_implemented)
+        );
+        assert!(!unimplemented.iter().any(PowerUpType::is_implemented));
+
+        // every variant is either in the array or listed above
+        let TypeInfo::Enum(info) = PowerUpType::type_info() else {
+            unreachable!("power up types are an enum");
+        };
+        assert_eq!(
+            info.variant_len(),
+            IMPLEMENTED_POWER_UPS.len() + unimplemented.len()
+        );
+    }
+
+    #[test]
+    fn course_ids_with_path_traversal_are_rejected() {
+        assert!(is_valid_course_id("0001"));
+        assert!(is_valid_course_id("moon-golf_2"));
+
+        assert!(!is_valid_course_id(""));
+        assert!(!is_valid_course_id("../../etc/passwd"));
+        assert!(!is_valid_course_id("..\\0001"));
+        assert!(!is_valid_course_id("0001.scn.ron"));
+    }
+
+    #[test]
+    fn names_are_only_replicated_with_players() {
+        use bevy_replicon::shared::{
+            RepliconSharedPlugin, replication::replication_rules::ReplicationRules,
+        };
+
+        let mut app = App::new();
+        app.add_plugins((
+            bevy::state::app::StatesPlugin,
+            RepliconSharedPlugin::default(),
+            MinigolfPlugin,
+        ));
+
+        let world = app.world_mut();
+        let name = world.register_component::<Name>();
+        let owners = [
+            world.register_component::<Player>(),
+            world.register_component::<DisconnectedPlayer>(),
+        ];
+
+        let rules = world.resource::<ReplicationRules>();
+        let name_rules = rules
+            .iter()
+            .filter(|rule| rule.components.iter().any(|&(id, _)| id == name))
+            .collect::<Vec<_>>();
+
+        assert_eq!(name_rules.len(), owners.len());
+        for rule in name_rules {
+            assert!(rule.components.iter().any(|(id, _)| owners.contains(id)));
+        }
+    }
+
+    #[test]
+    fn every_power_up_has_a_description() {
+        use bevy::reflect::{DynamicEnum, FromReflect, TypeInfo, Typed};
+
+        let TypeInfo::Enum(info) = PowerUpType::type_info() else {
+            unreachable!("power up types are an enum");
+        };
+
+        for variant in info.iter() {
+            let power_up =
+                PowerUpType::from_reflect(&DynamicEnum::new(variant.name(), ())).unwrap();
+            assert!(
+                !power_up.description().trim().is_empty(),
+                "{power_up:?} has no description"
+            );
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use {
     crate::{
-        CourseId, PlayerCredentials,
+        CourseId, PlayerCredentials, PowerUpType, default_player_name, is_valid_course_id,
         lobby::{LobbyId, PlayerId},
     },
     serde::{Deserialize, Serialize},
+    std::fmt::{Display, Formatter},
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -25,6 +26,57 @@ pub struct CreateGameRequest {
     pub lobby_id: LobbyId,
     pub players: Vec<(PlayerId, PlayerCredentials)>,
     pub courses: Vec<CourseId>,
+    /// Strokes subtracted from the score of players, players without a handicap are not included.
+    #[serde(default)]
+    pub handicaps: Vec<(PlayerId, u32)>,
+    /// Display names of the players, players that did not choose a name are not included.
+    #[serde(default)]
+    pub names: Vec<(PlayerId, String)>,
+    /// Power ups that can be spawned and used in the game, overriding the configuration of the game server.
+    #[serde(default)]
+    pub enabled_power_ups: Option<Vec<PowerUpType>>,
+}
+
+impl CreateGameRequest {
+    /// Checks that there is at least one player in the game, that each player is included once,
+    /// and that all course ids are valid.
+    pub fn validate(&self) -> Result<(), ProtocolError> {
+        if self.players.is_empty() {
+            return Err(ProtocolError::Invalid("no players".to_owned()));
+        }
+
+        let has_duplicates = self.players.iter().enumerate().any(|(index, (id, _))| {
+            self.players[..index]
+                .iter()
+                .any(|(other_id, _)| other_id == id)
+        });
+
+        if has_duplicates {
+            return Err(ProtocolError::Invalid("duplicate players".to_owned()));
+        }
+
+        if let Some(id) = self.courses.iter().find(|id| !is_valid_course_id(id)) {
+            return Err(ProtocolError::Invalid(format!("invalid course id {id:?}")));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the display name of the player, or a default name if the player did not choose one.
+    pub fn name(&self, player: PlayerId) -> String {
+        self.names
+            .iter()
+            .find(|(id, _)| *id == player)
+            .map_or_else(|| default_player_name(player), |(_, name)| name.clone())
+    }
+
+    /// Gets the handicap of the player, 0 if the player does not have one.
+    pub fn handicap(&self, player: PlayerId) -> u32 {
+        self.handicaps
+            .iter()
+            .find(|(id, _)| *id == player)
+            .map_or(0, |(_, handicap)| *handicap)
+    }
 }
 
 impl Into<String> for ClientPacket {
@@ -45,8 +97,63 @@ impl<'a> From<&'a [u8]> for ClientPacket {
     }
 }
 
-impl<'a> From<&'a [u8]> for ServerPacket {
-    fn from(value: &'a [u8]) -> Self {
-        serde_json::from_slice::<ServerPacket>(value).unwrap()
+impl ServerPacket {
+    pub fn parse(value: &[u8]) -> Result<Self, ProtocolError> {
+        serde_json::from_slice::<ServerPacket>(value).map_err(ProtocolError::Malformed)
+    }
+}
+
+/// Error in the communication between the game server and the lobby server.
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// The packet could not be deserialized.
+    Malformed(serde_json::Error),
+    /// The packet is valid, but not expected in the current state.
+    Unexpected(ServerPacket),
+    /// The packet is expected, but its contents are not valid.
+    Invalid(String),
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::Malformed(err) => write!(f, "malformed packet: {err}"),
+            ProtocolError::Unexpected(packet) => write!(f, "unexpected packet {packet:?}"),
+            ProtocolError::Invalid(reason) => write!(f, "invalid packet: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_names_are_sent_and_default_to_short_id() {
+        let (named, unnamed) = (PlayerId::new(), PlayerId::new());
+        let credentials = PlayerCredentials {
+            secret: "secret".to_owned(),
+            issued_at: 0,
+        };
+        let request = CreateGameRequest {
+            lobby_id: 1,
+            players: vec![(named, credentials.clone()), (unnamed, credentials)],
+            courses: vec!["0001".to_owned()],
+            handicaps: vec![],
+            names: vec![(named, "Ada".to_owned())],
+            enabled_power_ups: None,
+        };
+
+        let mut json = serde_json::to_value(&request).unwrap();
+        let request: CreateGameRequest = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(request.name(named), "Ada");
+        assert_eq!(request.name(unnamed), default_player_name(unnamed));
+        assert_eq!(request.name(unnamed), format!("Player {}", unnamed.short()));
+
+        json.as_object_mut().unwrap().remove("names");
+        let request: CreateGameRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(request.name(named), default_player_name(named));
     }
 }
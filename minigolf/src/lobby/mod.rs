@@ -16,7 +16,31 @@ impl UniqueId {
     pub fn new() -> Self {
         UniqueId { id: Uuid::new_v4() }
     }
+
+    /// Gets the first characters of the id, enough to tell apart the players of a game.
+    pub fn short(&self) -> String {
+        self.id.simple().to_string()[..8].to_owned()
+    }
 }
 
 pub type PlayerId = UniqueId;
 pub type LobbyId = u64;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_id_is_deterministic() {
+        let id = |value| UniqueId {
+            id: Uuid::from_u128(value),
+        };
+
+        let first = id(0x67e5504410b1426f9247bb680e5fe0c8);
+        assert_eq!(first.short(), "67e55044");
+        assert_eq!(
+            first.short(),
+            id(0x67e5504410b1426f9247bb680e5fe0c8).short()
+        );
+    }
+}
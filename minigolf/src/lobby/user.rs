@@ -16,6 +16,14 @@ pub enum ClientPacket {
     JoinLobby(LobbyId),
     LeaveLobby,
     StartGame,
+    /// Send a chat message to all members of the current lobby.
+    Chat(String),
+    /// Set the handicap of a player in the current lobby, only allowed for the owner of the lobby.
+    SetHandicap(PlayerId, u32),
+    /// Set the display name of the player, shown to the other players in games.
+    SetName(String),
+    /// Mark the player as ready or not ready to start the game in the current lobby.
+    SetReady(bool),
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
@@ -27,6 +35,12 @@ pub enum ServerPacket {
     PlayerJoined(PlayerInLobby),
     PlayerLeft(PlayerInLobby),
     GameStarted(String),
+    /// A chat message sent by the player to the current lobby.
+    Chat(PlayerId, String),
+    /// The owner of the current lobby set the handicap of a player.
+    HandicapSet(PlayerId, u32),
+    /// The game was started, so the player is no longer ready.
+    ReadyCleared,
 }
 
 #[derive(Serialize, Deserialize, Reflect, PartialEq, Copy, Clone, Debug)]
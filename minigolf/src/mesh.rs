@@ -0,0 +1,99 @@
+use {
+    bevy::{
+        asset::RenderAssetUsages,
+        prelude::*,
+        render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+    },
+    std::collections::HashMap,
+};
+
+/// Creates a mesh of walls with the given height along the outline of a triangle mesh.
+///
+/// The outline consists of the edges which belong to a single triangle.
+/// Vertices are compared by position, since meshes duplicate vertices along seams of normals or UVs.
+pub fn extrude_outline(mesh: &Mesh, height: f32) -> Option<Mesh> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+
+    let indices = match mesh.indices() {
+        Some(indices) => indices.iter().collect::<Vec<_>>(),
+        None => (0..positions.len()).collect(),
+    };
+
+    // adding zero turns -0.0 into 0.0, so that both have the same bits
+    let position_key = |index: usize| positions[index].map(|value| (value + 0.0).to_bits());
+
+    let mut edges = HashMap::<([u32; 3], [u32; 3]), (usize, usize, usize)>::new();
+    for triangle in indices.chunks_exact(3) {
+        for (start, end) in [
+            (triangle[0], triangle[1]),
+            (triangle[1], triangle[2]),
+            (triangle[2], triangle[0]),
+        ] {
+            let (start_key, end_key) = (position_key(start), position_key(end));
+            if start_key == end_key {
+                continue;
+            }
+
+            let key = (start_key.min(end_key), start_key.max(end_key));
+            edges.entry(key).or_insert((start, end, 0)).2 += 1;
+        }
+    }
+
+    let mut wall_positions = Vec::<[f32; 3]>::new();
+    let mut wall_indices = Vec::<u32>::new();
+    for (start, end, _) in edges.into_values().filter(|(_, _, count)| *count == 1) {
+        let bottom_start = Vec3::from(positions[start]);
+        let bottom_end = Vec3::from(positions[end]);
+        let top_start = bottom_start + Vec3::Y * height;
+        let top_end = bottom_end + Vec3::Y * height;
+
+        let first = wall_positions.len() as u32;
+        wall_positions.extend([bottom_start, bottom_end, top_end, top_start].map(Vec3::to_array));
+        wall_indices.extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+    }
+
+    if wall_indices.is_empty() {
+        return None;
+    }
+
+    let walls = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, wall_positions)
+    .with_inserted_indices(Indices::U32(wall_indices));
+
+    Some(walls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outline_ignores_duplicated_vertices() {
+        // a square of two triangles which do not share vertices, like along a UV seam
+        let positions = vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 0.0, 1.0],
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 1.0],
+            [-0.0, 0.0, 1.0],
+        ];
+        let square = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+
+        let walls = extrude_outline(&square, 0.1).unwrap();
+
+        // one wall of two triangles for each side of the square
+        assert_eq!(walls.indices().unwrap().len(), 4 * 6);
+    }
+}
@@ -26,6 +26,28 @@ pub(crate) fn register_replicated<
     app.replicate::<TComponent>();
 }
 
+/// Registers an event sent from clients to the server over the specified channel.
+pub(crate) fn register_client_event<
+    TEvent: Event + GetTypeRegistration + Serialize + DeserializeOwned,
+>(
+    app: &mut App,
+    channel: Channel,
+) {
+    app.register_type::<TEvent>();
+    app.add_client_event::<TEvent>(channel);
+}
+
+/// Registers an event sent from the server to clients over the specified channel.
+pub(crate) fn register_server_event<
+    TEvent: Event + GetTypeRegistration + Serialize + DeserializeOwned,
+>(
+    app: &mut App,
+    channel: Channel,
+) {
+    app.register_type::<TEvent>();
+    app.add_server_event::<TEvent>(channel);
+}
+
 pub(crate) fn get_child_of_serialization_rules() -> RuleFns<ChildOf> {
     RuleFns::new(serialize_child_of, deserialize_child_of)
 }
@@ -45,3 +67,54 @@ fn deserialize_child_of(ctx: &mut WriteCtx, message: &mut Bytes) -> Result<Child
     ChildOf::map_entities(&mut component, ctx);
     Ok(component)
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        bevy::state::app::StatesPlugin,
+        bevy_replicon::{shared::backend::replicon_channels::RepliconChannels, test_app::*},
+        serde::Deserialize,
+    };
+
+    #[derive(Event, Reflect, Serialize, Deserialize, PartialEq, Debug)]
+    struct Ping(u32);
+
+    #[test]
+    fn registered_client_events_round_trip_over_channel() {
+        let mut server_app = App::new();
+        let mut client_app = App::new();
+        for app in [&mut server_app, &mut client_app] {
+            app.add_plugins((
+                MinimalPlugins,
+                StatesPlugin,
+                RepliconPlugins.set(ServerPlugin {
+                    tick_policy: TickPolicy::EveryFrame,
+                    ..default()
+                }),
+            ));
+            register_client_event::<Ping>(app, Channel::Unordered);
+            app.finish();
+        }
+
+        let channels = server_app.world().resource::<RepliconChannels>();
+        assert!(matches!(
+            channels.client_channels().last(),
+            Some(Channel::Unordered)
+        ));
+
+        server_app.connect_client(&mut client_app);
+        client_app.world_mut().send_event(Ping(42));
+        client_app.update();
+        server_app.exchange_with_client(&mut client_app);
+        server_app.update();
+
+        let events = server_app.world().resource::<Events<FromClient<Ping>>>();
+        let received = events
+            .get_cursor()
+            .read(events)
+            .map(|from_client| &from_client.event)
+            .collect::<Vec<_>>();
+        assert_eq!(received, [&Ping(42)]);
+    }
+}
@@ -0,0 +1,109 @@
+use {
+    aeronet::io::{Session, connection::Disconnect},
+    bevy::prelude::*,
+    core::time::Duration,
+    std::sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Disconnects all sessions on SIGINT/SIGTERM, and exits once the disconnects had time to be sent.
+///
+/// Replaces bevy's `TerminalCtrlCHandlerPlugin`, since only one signal handler can be set.
+#[derive(Debug)]
+pub struct ShutdownPlugin;
+
+impl Plugin for ShutdownPlugin {
+    fn build(&self, app: &mut App) {
+        if let Err(err) = ctrlc::set_handler(|| SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst)) {
+            warn!("Could not set shutdown signal handler: {err}");
+        }
+
+        app.add_systems(Update, (disconnect_sessions, exit_after_flush).chain());
+    }
+}
+
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// How long to keep running after disconnecting sessions, so that the disconnect reason reaches them.
+const FLUSH_DURATION: Duration = Duration::from_millis(500);
+
+#[derive(Resource, Debug)]
+struct ShuttingDown {
+    timer: Timer,
+}
+
+fn disconnect_sessions(
+    sessions: Query<Entity, (With<Session>, With<ChildOf>)>,
+    shutting_down: Option<Res<ShuttingDown>>,
+    mut commands: Commands,
+) {
+    if shutting_down.is_some() || !SHUTDOWN_REQUESTED.load(Ordering::SeqCst) {
+        return;
+    }
+
+    info!(
+        "Shutting down, disconnecting {} sessions",
+        sessions.iter().count()
+    );
+
+    for session in &sessions {
+        commands.trigger_targets(Disconnect::new("server shutting down"), session);
+    }
+
+    commands.insert_resource(ShuttingDown {
+        timer: Timer::new(FLUSH_DURATION, TimerMode::Once),
+    });
+}
+
+fn exit_after_flush(
+    shutting_down: Option<ResMut<ShuttingDown>>,
+    time: Res<Time>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(mut shutting_down) = shutting_down else {
+        return;
+    };
+
+    if shutting_down.timer.tick(time.delta()).just_finished() {
+        info!("Shut down");
+        exit.write(AppExit::Success);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, bevy::ecs::system::RunSystemOnce, std::time::Instant};
+
+    #[derive(Resource, Default)]
+    struct DisconnectedSessions(Vec<Entity>);
+
+    #[test]
+    fn sessions_are_disconnected_once_shutdown_is_requested() {
+        let mut world = World::new();
+        world.init_resource::<DisconnectedSessions>();
+        world.add_observer(
+            |trigger: Trigger<Disconnect>, mut disconnected: ResMut<DisconnectedSessions>| {
+                disconnected.0.push(trigger.target());
+            },
+        );
+
+        let server = world.spawn_empty().id();
+        let session = world
+            .spawn((Session::new(Instant::now(), 1200), ChildOf(server)))
+            .id();
+        // a client session of this app, which is not a child of a server
+        world.spawn(Session::new(Instant::now(), 1200));
+
+        world.run_system_once(disconnect_sessions).unwrap();
+        assert!(world.resource::<DisconnectedSessions>().0.is_empty());
+        assert!(world.get_resource::<ShuttingDown>().is_none());
+
+        SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+        world.run_system_once(disconnect_sessions).unwrap();
+        assert_eq!(world.resource::<DisconnectedSessions>().0, vec![session]);
+        assert!(world.get_resource::<ShuttingDown>().is_some());
+
+        // sessions are only disconnected once
+        world.run_system_once(disconnect_sessions).unwrap();
+        assert_eq!(world.resource::<DisconnectedSessions>().0, vec![session]);
+    }
+}
@@ -1,12 +1,15 @@
 use {
-    crate::{LocalPlayer, input::InputTarget},
+    crate::{
+        LocalPlayer,
+        input::{InputTarget, MouseSensitivity},
+    },
     bevy::{
         app::App,
         input::{mouse::MouseMotion, mouse::MouseWheel},
         math::Vec3,
         prelude::*,
     },
-    minigolf::GameState,
+    minigolf::{CameraHint, GameState},
     std::f32::consts::PI,
 };
 
@@ -15,6 +18,11 @@ pub(crate) struct CameraInputPlugin;
 impl Plugin for CameraInputPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<TargetTransform>();
+        app.register_type::<PinchState>();
+
+        app.init_resource::<PinchState>();
+
+        app.add_observer(apply_camera_hint);
 
         app.configure_sets(Update, CameraInputSet.run_if(in_state(GameState::Playing)));
 
@@ -23,6 +31,7 @@ impl Plugin for CameraInputPlugin {
             (
                 follow_player_with_camera,
                 move_camera_based_on_scroll,
+                move_camera_based_on_pinch,
                 interpolate_position,
                 accumulate_mouse_movement.run_if(in_state(InputTarget::Camera)),
             )
@@ -68,16 +77,32 @@ fn interpolate_position(mut transforms: Query<(&mut Transform, &TargetTransform)
 fn accumulate_mouse_movement(
     mut mouse_motion_events: EventReader<MouseMotion>,
     mut inputs: Query<&mut TargetTransform, With<Camera3d>>,
+    sensitivity: Res<MouseSensitivity>,
 ) {
     for ev in mouse_motion_events.read() {
         let Ok(mut target) = inputs.single_mut() else {
             continue;
         };
 
-        target.rotation *= Quat::from_euler(EulerRot::XYZ, 0.0, ev.delta.x / 100.0 * PI, 0.0);
+        let angle = ev.delta.x * sensitivity.camera / 100.0 * PI;
+        target.rotation *= Quat::from_euler(EulerRot::XYZ, 0.0, angle, 0.0);
     }
 }
 
+fn apply_camera_hint(
+    trigger: Trigger<OnAdd, CameraHint>,
+    hints: Query<&CameraHint>,
+    mut camera: Query<&mut TargetTransform, With<Camera3d>>,
+) {
+    let (Ok(hint), Ok(mut camera)) = (hints.get(trigger.target()), camera.single_mut()) else {
+        return;
+    };
+
+    camera.distance = hint.distance;
+    camera.height = hint.height;
+    camera.rotation = Quat::from_euler(EulerRot::XYZ, 0.0, hint.yaw, 0.0);
+}
+
 fn follow_player_with_camera(
     player: Query<&Transform, With<LocalPlayer>>,
     mut camera: Query<&mut TargetTransform, With<Camera3d>>,
@@ -105,3 +130,91 @@ fn move_camera_based_on_scroll(
         camera_transform.height += 0.05 * mouse_wheel.y.signum();
     }
 }
+
+/// Distance between two touches in the previous frame, while the player is pinching.
+#[derive(Resource, Reflect, Debug, Default)]
+struct PinchState {
+    distance: Option<f32>,
+}
+
+/// Gets how much to zoom when the distance between touches changes, positive values zoom out.
+///
+/// Pinching by 100 pixels zooms as much as one step of the mouse wheel.
+fn pinch_zoom(previous_distance: f32, distance: f32) -> f32 {
+    (previous_distance - distance) / 100.0
+}
+
+fn move_camera_based_on_pinch(
+    mut camera: Query<&mut TargetTransform, With<Camera3d>>,
+    touches: Res<Touches>,
+    mut state: ResMut<PinchState>,
+) {
+    let positions = touches
+        .iter()
+        .map(|touch| touch.position())
+        .collect::<Vec<_>>();
+    let &[first, second] = positions.as_slice() else {
+        state.distance = None;
+        return;
+    };
+
+    let distance = first.distance(second);
+    let Some(previous_distance) = state.distance.replace(distance) else {
+        return;
+    };
+
+    let Ok(mut camera_transform) = camera.single_mut() else {
+        return;
+    };
+
+    let zoom = pinch_zoom(previous_distance, distance);
+    camera_transform.distance += 0.1 * zoom;
+    camera_transform.height += 0.05 * zoom;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreading_touches_zooms_in() {
+        assert_eq!(pinch_zoom(200.0, 300.0), -1.0);
+        assert_eq!(pinch_zoom(300.0, 200.0), 1.0);
+        assert_eq!(pinch_zoom(150.0, 150.0), 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_hint_is_applied_when_hole_starts() {
+        let mut world = World::new();
+        world.add_observer(apply_camera_hint);
+        let camera = world
+            .spawn((
+                Camera3d::default(),
+                TargetTransform::new(Transform::IDENTITY),
+            ))
+            .id();
+
+        world.spawn(CameraHint {
+            yaw: PI / 2.0,
+            distance: 5.0,
+            height: 3.0,
+        });
+        world.flush();
+
+        let target = world.get::<TargetTransform>(camera).unwrap();
+        assert_eq!(target.distance, 5.0);
+        assert_eq!(target.height, 3.0);
+        assert!(
+            target
+                .rotation
+                .angle_between(Quat::from_rotation_y(PI / 2.0))
+                .abs()
+                < 1e-5
+        );
+    }
+}
@@ -2,7 +2,11 @@ use {
     crate::{LocalPlayer, input::camera::CameraInputPlugin},
     bevy::{
         app::App,
-        input::{common_conditions::input_just_released, mouse::MouseMotion, touch::TouchPhase},
+        input::{
+            common_conditions::{input_just_pressed, input_just_released},
+            mouse::MouseMotion,
+            touch::TouchPhase,
+        },
         picking::pointer::PointerInteraction,
         prelude::*,
     },
@@ -33,6 +37,9 @@ impl Plugin for MinigolfInputPlugin {
         app.register_required_components::<PlayableArea, Pickable>();
 
         app.register_type::<AccumulatedInputs>();
+        app.register_type::<MouseSensitivity>();
+
+        app.init_resource::<MouseSensitivity>();
 
         app.configure_sets(
             Update,
@@ -40,17 +47,26 @@ impl Plugin for MinigolfInputPlugin {
         );
 
         app.add_systems(OnEnter(GameState::Playing), setup);
+        app.add_systems(OnEnter(MenuState::Open), (reset_inputs, reset_input_target));
+        app.add_systems(OnExit(GameState::Playing), close_menu);
 
         app.add_systems(Update, check_whether_can_move.in_set(ValidateInputSet));
+        app.add_systems(
+            Update,
+            (toggle_menu, cancel_power_up_target)
+                .chain()
+                .run_if(in_state(GameState::Playing).and(input_just_pressed(KeyCode::Escape))),
+        );
 
         app.init_state::<InputState>();
         app.init_state::<InputTarget>();
+        app.init_state::<MenuState>();
         app.init_resource::<TouchState>();
 
         app.configure_sets(
             Update,
             InputSet
-                .run_if(in_state(GameState::Playing).and(in_state(InputState::CanMove)))
+                .run_if(in_state(GameState::Playing).and(can_take_shot))
                 .after(ValidateInputSet),
         );
 
@@ -58,6 +74,7 @@ impl Plugin for MinigolfInputPlugin {
             Update,
             (
                 accumulate_mouse_movement.run_if(in_state(InputTarget::Movement)),
+                adjust_vertical_aim.run_if(in_state(InputTarget::Movement)),
                 reset_inputs.run_if(input_just_released(MouseButton::Right)),
                 handle_touch,
                 draw_accumulated_inputs,
@@ -123,12 +140,47 @@ pub(crate) enum InputTarget {
     Wind,
 }
 
+impl InputTarget {
+    /// Whether a power up is being placed.
+    fn is_power_up(&self) -> bool {
+        match self {
+            InputTarget::Teleport
+            | InputTarget::Bumper
+            | InputTarget::BlackHoleBumper
+            | InputTarget::Tornado
+            | InputTarget::Wind => true,
+
+            InputTarget::None | InputTarget::Camera | InputTarget::Movement => false,
+        }
+    }
+}
+
+/// Whether the in-game menu is open, during which the player cannot take shots.
+///
+/// The game continues on the server while the menu is open.
+#[derive(States, Reflect, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum MenuState {
+    #[default]
+    Closed,
+    Open,
+}
+
+/// Whether the player can take a shot, which is not possible while the in-game menu is open.
+fn can_take_shot(input_state: Res<State<InputState>>, menu_state: Res<State<MenuState>>) -> bool {
+    *input_state.get() == InputState::CanMove && *menu_state.get() == MenuState::Closed
+}
+
 fn on_pointer_down(
     trigger: Trigger<Pointer<Pressed>>,
     players: Query<Entity, With<LocalPlayer>>,
     input_state: Res<State<InputState>>,
+    menu_state: Res<State<MenuState>>,
     mut input_target: ResMut<NextState<InputTarget>>,
 ) {
+    if *menu_state.get() == MenuState::Open {
+        return;
+    }
+
     // After upgrading to 0.16, this is triggered multiple times
     // At least it is triggered in a consistent order, with the first one being the top-most entity
     if let NextState::Pending(_) = input_target.as_ref() {
@@ -147,11 +199,12 @@ fn on_pointer_down(
 fn on_pointer_up(
     _trigger: Trigger<Pointer<Released>>,
     input_state: Res<State<InputState>>,
+    menu_state: Res<State<MenuState>>,
     mut writer: EventWriter<PlayerInput>,
     mut inputs: Query<&mut AccumulatedInputs, With<LocalPlayer>>,
     mut input_target: ResMut<NextState<InputTarget>>,
 ) {
-    if *input_state.get() != InputState::CanMove {
+    if !can_take_shot(input_state, menu_state) {
         input_target.set(InputTarget::None);
         return;
     }
@@ -167,10 +220,45 @@ fn on_pointer_up(
         return;
     }
 
-    writer.write(PlayerInput::Move(input.input));
+    writer.write(shot_input(input.input, input.vertical_angle));
 
     input_target.set(InputTarget::None);
     input.input = Vec2::ZERO;
+    input.vertical_angle = 0.0;
+}
+
+/// Leaves the targeting mode of a power up without placing it.
+///
+/// Power ups are only used once they are placed, so nothing has to be refunded.
+fn cancel_power_up_target(
+    input_target: Res<State<InputTarget>>,
+    mut next_input_target: ResMut<NextState<InputTarget>>,
+) {
+    if input_target.is_power_up() {
+        info!("Cancelled targeting {:?}", input_target.get());
+        next_input_target.set(InputTarget::None);
+    }
+}
+
+/// Opens the in-game menu, unless the key is used to cancel placing a power up.
+fn toggle_menu(
+    input_target: Res<State<InputTarget>>,
+    menu_state: Res<State<MenuState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+) {
+    match menu_state.get() {
+        MenuState::Open => next_menu_state.set(MenuState::Closed),
+        MenuState::Closed if !input_target.is_power_up() => next_menu_state.set(MenuState::Open),
+        MenuState::Closed => {}
+    }
+}
+
+fn close_menu(mut menu_state: ResMut<NextState<MenuState>>) {
+    menu_state.set(MenuState::Closed);
+}
+
+fn reset_input_target(mut input_target: ResMut<NextState<InputTarget>>) {
+    input_target.set(InputTarget::None);
 }
 
 #[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
@@ -197,25 +285,99 @@ pub(crate) struct InputSet;
 
 #[derive(Component, Reflect, Deref, DerefMut, Default, Debug)]
 pub(crate) struct AccumulatedInputs {
+    #[deref]
     input: Vec2,
+    /// Angle in degrees above or below the ground at which the shot is aimed, for putting up or down slopes.
+    vertical_angle: f32,
+}
+
+/// Maximum angle in degrees at which a shot can be aimed above or below the ground.
+///
+/// The server limits the angle further, unless the [PowerUpType::ChipShot](minigolf::PowerUpType::ChipShot)
+/// power up is used.
+const MAX_VERTICAL_AIM: f32 = 45.0;
+
+/// Degrees per second by which the vertical aim changes while a key is held.
+const VERTICAL_AIM_SPEED: f32 = 30.0;
+
+/// Gets the input for a shot aimed with the accumulated inputs.
+///
+/// Shots aimed along the ground are sent as [PlayerInput::Move],
+/// so that they are understood the same way as before vertical aim was added.
+fn shot_input(input: Vec2, vertical_angle: f32) -> PlayerInput {
+    if vertical_angle == 0.0 {
+        return PlayerInput::Move(input);
+    }
+
+    let power = input.length();
+    let vertical = power * vertical_angle.to_radians().tan();
+
+    PlayerInput::Putt {
+        direction: Vec3::new(input.x, vertical, input.y),
+        power,
+    }
+}
+
+/// Multipliers of mouse movement, adjustable by the player.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub(crate) struct MouseSensitivity {
+    /// Sensitivity when aiming a shot.
+    pub(crate) aim: f32,
+    /// Sensitivity when rotating the camera.
+    pub(crate) camera: f32,
+}
+
+impl Default for MouseSensitivity {
+    fn default() -> Self {
+        MouseSensitivity {
+            aim: 1.0,
+            camera: 1.0,
+        }
+    }
 }
 
 fn accumulate_mouse_movement(
     mut mouse_motion_events: EventReader<MouseMotion>,
     mut inputs: Query<&mut AccumulatedInputs, With<LocalPlayer>>,
+    sensitivity: Res<MouseSensitivity>,
 ) {
     for ev in mouse_motion_events.read() {
         let Ok(mut input) = inputs.single_mut() else {
             continue;
         };
 
-        input.input.y -= ev.delta.x / 400.0;
-        input.input.x += ev.delta.y / 400.0;
+        let delta = ev.delta * sensitivity.aim / 400.0;
+        input.input.y -= delta.x;
+        input.input.x += delta.y;
 
         input.input = input.input.clamp_length_max(1.0);
     }
 }
 
+/// Raises or lowers the aim of the shot while the arrow keys are held.
+fn adjust_vertical_aim(
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut inputs: Query<&mut AccumulatedInputs, With<LocalPlayer>>,
+) {
+    let up = keys.pressed(KeyCode::ArrowUp);
+    let down = keys.pressed(KeyCode::ArrowDown);
+    let direction = match (up, down) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => return,
+    };
+
+    let Ok(mut input) = inputs.single_mut() else {
+        return;
+    };
+
+    input.vertical_angle = (input.vertical_angle
+        + direction * VERTICAL_AIM_SPEED * time.delta_secs())
+    .clamp(-MAX_VERTICAL_AIM, MAX_VERTICAL_AIM);
+}
+
 fn reset_inputs(mut inputs: Query<&mut AccumulatedInputs, With<LocalPlayer>>) {
     let Ok(mut input) = inputs.single_mut() else {
         error!("Multiple entities with accumulated inputs/local player marker ");
@@ -223,18 +385,22 @@ fn reset_inputs(mut inputs: Query<&mut AccumulatedInputs, With<LocalPlayer>>) {
     };
 
     input.input = Vec2::ZERO;
+    input.vertical_angle = 0.0;
 }
 
 #[derive(Resource, Reflect, Debug, Default)]
 struct TouchState {
     start: Option<Vec2>,
     last: Option<Vec2>,
+    /// Whether a second finger touched the screen, in which case the touches are used to zoom the camera.
+    pinching: bool,
 }
 
 fn handle_touch(
     mut touch_inputs: EventReader<TouchInput>,
     mut inputs: Query<&mut AccumulatedInputs, With<LocalPlayer>>,
     mut state: ResMut<TouchState>,
+    touches: Res<Touches>,
     mut writer: EventWriter<PlayerInput>,
 ) {
     for touch in touch_inputs.read() {
@@ -242,6 +408,23 @@ fn handle_touch(
             continue;
         };
 
+        // aiming is cancelled once a second finger touches the screen, until all fingers are lifted
+        if touches.iter().count() > 1 {
+            state.pinching = true;
+        }
+
+        if state.pinching {
+            input.input = Vec2::ZERO;
+            state.start = None;
+            state.last = None;
+
+            if touches.iter().count() == 0 {
+                state.pinching = false;
+            }
+
+            continue;
+        }
+
         match touch.phase {
             TouchPhase::Started => {
                 state.start = Some(touch.position);
@@ -267,9 +450,10 @@ fn handle_touch(
                     continue;
                 }
 
-                writer.write(PlayerInput::Move(input.input));
+                writer.write(shot_input(input.input, input.vertical_angle));
 
                 input.input = Vec2::ZERO;
+                input.vertical_angle = 0.0;
                 state.start = None;
                 state.last = None;
             }
@@ -301,6 +485,7 @@ fn draw_accumulated_inputs(
     let mut end = player_transform.translation.clone();
     end.x += input.x * 2.0;
     end.z += input.y * 2.0;
+    end.y += input.length() * 2.0 * input.vertical_angle.to_radians().tan();
 
     gizmos.arrow(
         player_transform.translation,
@@ -413,3 +598,89 @@ fn draw_mesh_picking_target(pointers: Query<&PointerInteraction>, mut gizmos: Gi
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shots_along_the_ground_are_moves() {
+        let input = Vec2::new(0.3, 0.4);
+        assert_eq!(shot_input(input, 0.0), PlayerInput::Move(input));
+    }
+
+    #[test]
+    fn shots_aimed_upwards_are_putts() {
+        let PlayerInput::Putt { direction, power } = shot_input(Vec2::new(0.3, 0.4), 45.0) else {
+            panic!("expected a putt");
+        };
+
+        assert!((power - 0.5).abs() < 1e-5);
+        assert!((direction - Vec3::new(0.3, 0.5, 0.4)).length() < 1e-5);
+    }
+
+    #[test]
+    fn aim_input_scales_with_mouse_sensitivity() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let accumulated = |aim: f32| {
+            let mut world = World::new();
+            world.init_resource::<Events<MouseMotion>>();
+            world.insert_resource(MouseSensitivity { aim, camera: 1.0 });
+            let player = world
+                .spawn((LocalPlayer, AccumulatedInputs::default()))
+                .id();
+
+            world.send_event(MouseMotion {
+                delta: Vec2::new(40.0, 20.0),
+            });
+            world.run_system_once(accumulate_mouse_movement).unwrap();
+
+            world.get::<AccumulatedInputs>(player).unwrap().input
+        };
+
+        let default = accumulated(1.0);
+        assert!((default - Vec2::new(0.05, -0.1)).length() < 1e-5);
+        assert!((accumulated(2.0) - default * 2.0).length() < 1e-5);
+    }
+
+    #[test]
+    fn shots_are_blocked_while_menu_is_open() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut world = World::new();
+        world.insert_resource(State::new(InputState::CanMove));
+        world.insert_resource(State::new(MenuState::Open));
+        assert!(!world.run_system_once(can_take_shot).unwrap());
+
+        world.insert_resource(State::new(MenuState::Closed));
+        assert!(world.run_system_once(can_take_shot).unwrap());
+
+        world.insert_resource(State::new(InputState::CannotMove));
+        assert!(!world.run_system_once(can_take_shot).unwrap());
+    }
+
+    #[test]
+    fn cancelling_power_up_target_returns_to_none() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut world = World::new();
+        world.insert_resource(State::new(InputTarget::Bumper));
+        world.init_resource::<NextState<InputTarget>>();
+
+        world.run_system_once(cancel_power_up_target).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<InputTarget>>(),
+            NextState::Pending(InputTarget::None)
+        ));
+
+        // aiming the camera is not a power up target
+        world.insert_resource(State::new(InputTarget::Camera));
+        world.insert_resource(NextState::<InputTarget>::Unchanged);
+        world.run_system_once(cancel_power_up_target).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<InputTarget>>(),
+            NextState::Unchanged
+        ));
+    }
+}
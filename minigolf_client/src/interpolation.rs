@@ -0,0 +1,181 @@
+use {
+    crate::LocalPlayer,
+    bevy::prelude::*,
+    minigolf::{GameState, Player},
+    std::collections::VecDeque,
+};
+
+/// Smooths the movement of remote players' balls between replicated snapshots.
+///
+/// Remote balls are rendered slightly in the past, so that there are usually two snapshots to interpolate between.
+pub(crate) struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Interpolation>();
+
+        app.init_resource::<Interpolation>();
+
+        app.add_systems(
+            Update,
+            (start_interpolation, interpolate_remote_players)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Settings for interpolating remote players' movement.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub(crate) struct Interpolation {
+    pub(crate) enabled: bool,
+    /// How far in the past remote players are rendered, in seconds.
+    pub(crate) delay: f32,
+    /// How long to keep moving a ball past the last snapshot, in seconds.
+    pub(crate) max_extrapolation: f32,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation {
+            enabled: true,
+            delay: 0.1,
+            max_extrapolation: 0.1,
+        }
+    }
+}
+
+/// Replicated transforms of a remote player, along with the time they were received.
+#[derive(Component, Debug)]
+pub(crate) struct InterpolationBuffer {
+    snapshots: VecDeque<(f32, Transform)>,
+    /// Last interpolated transform, to detect when the replicated transform overwrites it.
+    applied: Transform,
+}
+
+impl InterpolationBuffer {
+    fn new(time: f32, transform: Transform) -> Self {
+        InterpolationBuffer {
+            snapshots: VecDeque::from([(time, transform)]),
+            applied: transform,
+        }
+    }
+
+    fn push(&mut self, time: f32, transform: Transform, render_time: f32) {
+        // the previous transform was held until now, for example while the ball was at rest
+        if let Some((last_time, _)) = self.snapshots.back_mut() {
+            *last_time = last_time.max(render_time);
+        }
+
+        self.snapshots.push_back((time, transform));
+    }
+
+    /// Gets the transform at the specified time, removing snapshots that are no longer needed.
+    fn sample(&mut self, time: f32, max_extrapolation: f32) -> Transform {
+        while self.snapshots.len() > 2 && self.snapshots[1].0 <= time {
+            self.snapshots.pop_front();
+        }
+
+        match (self.snapshots.front(), self.snapshots.get(1)) {
+            (Some(&(_, only)), None) => only,
+            (Some(&(start_time, start)), Some(&(end_time, end))) => {
+                let duration = end_time - start_time;
+                if duration <= 0.0 {
+                    return end;
+                }
+
+                // past the last snapshot the movement is extrapolated, up to the limit
+                let max_factor = 1.0 + max_extrapolation / duration;
+                let factor = ((time - start_time) / duration).clamp(0.0, max_factor);
+
+                Transform {
+                    translation: start.translation.lerp(end.translation, factor),
+                    rotation: start.rotation.slerp(end.rotation, factor.min(1.0)),
+                    scale: end.scale,
+                }
+            }
+            (None, _) => self.applied,
+        }
+    }
+}
+
+fn start_interpolation(
+    players: Query<
+        (Entity, &Transform),
+        (
+            With<Player>,
+            Without<LocalPlayer>,
+            Without<InterpolationBuffer>,
+        ),
+    >,
+    buffers: Query<(Entity, Has<LocalPlayer>), With<InterpolationBuffer>>,
+    settings: Res<Interpolation>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, is_local) in buffers.iter() {
+        if is_local || !settings.enabled {
+            commands.entity(entity).remove::<InterpolationBuffer>();
+        }
+    }
+
+    if !settings.enabled {
+        return;
+    }
+
+    for (entity, transform) in players.iter() {
+        commands
+            .entity(entity)
+            .insert(InterpolationBuffer::new(time.elapsed_secs(), *transform));
+    }
+}
+
+fn interpolate_remote_players(
+    mut players: Query<(&mut Transform, &mut InterpolationBuffer), Without<LocalPlayer>>,
+    settings: Res<Interpolation>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+    let render_time = now - settings.delay;
+
+    for (mut transform, mut buffer) in players.iter_mut() {
+        if *transform != buffer.applied {
+            buffer.push(now, *transform, render_time);
+        }
+
+        let interpolated = buffer.sample(render_time, settings.max_extrapolation);
+        *transform = interpolated;
+        buffer.applied = interpolated;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_are_interpolated_and_extrapolated_up_to_limit() {
+        let mut buffer = InterpolationBuffer::new(0.0, Transform::from_xyz(0.0, 0.0, 0.0));
+        buffer.push(1.0, Transform::from_xyz(1.0, 0.0, 0.0), 0.0);
+
+        assert_eq!(
+            buffer.sample(0.5, 0.2).translation,
+            Vec3::new(0.5, 0.0, 0.0)
+        );
+        assert_eq!(buffer.sample(-1.0, 0.2).translation, Vec3::ZERO);
+
+        // past the last snapshot the ball keeps moving only up to the extrapolation limit
+        assert_eq!(
+            buffer.sample(3.0, 0.2).translation,
+            Vec3::new(1.2, 0.0, 0.0)
+        );
+
+        buffer.push(2.0, Transform::from_xyz(1.0, 0.0, 2.0), 1.5);
+        assert_eq!(
+            buffer.sample(1.75, 0.2).translation,
+            Vec3::new(1.0, 0.0, 1.0)
+        );
+        assert_eq!(buffer.snapshots.len(), 2);
+    }
+}
@@ -1,11 +1,19 @@
 mod input;
+mod interpolation;
+mod music;
 mod network;
+mod prediction;
+mod trail;
 mod ui;
 
 use {
     crate::{
         input::{AccumulatedInputs, MinigolfInputPlugin, camera::TargetTransform},
+        interpolation::InterpolationPlugin,
+        music::HoleMusicPlugin,
         network::{Authentication, ClientNetworkPlugin},
+        prediction::PredictionPlugin,
+        trail::ShotTrailPlugin,
         ui::{ClientUiPlugin, ServerState},
     },
     aeronet::io::{Session, connection::Disconnected},
@@ -16,7 +24,10 @@ use {
         window::PrimaryWindow,
     },
     bevy_replicon::prelude::*,
-    minigolf::{GameState, LevelMesh, MinigolfPlugin, Player, PowerUp},
+    minigolf::{
+        AbandonedBall, ConveyorZone, GameState, HazardZone, LevelMesh, MinigolfPlugin, Player,
+        PowerUp, RemainingHits, mesh::extrude_outline,
+    },
     web_sys::{HtmlCanvasElement, wasm_bindgen::JsCast},
 };
 
@@ -29,14 +40,30 @@ fn main() -> AppExit {
             ClientNetworkPlugin,
             MinigolfPlugin,
             MinigolfInputPlugin,
+            PredictionPlugin,
+            InterpolationPlugin,
+            ShotTrailPlugin,
+            HoleMusicPlugin,
         ))
         .register_required_components::<Children, InheritedVisibility>()
         .add_systems(Startup, (set_window_title, setup_level))
         .add_observer(on_connected)
         .add_observer(on_player_added)
+        .add_observer(on_abandoned_ball_added)
         .add_observer(on_level_mesh_added)
         .add_observer(on_power_up_added)
+        .add_observer(on_hazard_added)
+        .add_observer(on_conveyor_added)
         .add_observer(on_disconnected)
+        .add_systems(
+            Update,
+            (
+                show_remaining_hits,
+                highlight_local_player,
+                draw_conveyors,
+                generate_outline_meshes,
+            ),
+        )
         .add_systems(OnExit(ServerState::GameServer), despawn_replicated)
         .run()
 }
@@ -98,15 +125,99 @@ fn on_level_mesh_added(
     let level_mesh = query.get(entity).unwrap();
     let mesh_handle: Handle<Mesh> = server.load(level_mesh.clone().asset);
 
-    commands.entity(entity).insert((
-        Mesh3d(mesh_handle.clone()),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::WHITE,
-            metallic: 0.5,
-            perceptual_roughness: 0.5,
-            ..default()
-        })),
-    ));
+    let mut entity = commands.entity(entity);
+    entity.insert(MeshMaterial3d(materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        metallic: 0.5,
+        perceptual_roughness: 0.5,
+        ..default()
+    })));
+
+    match level_mesh.outline_height {
+        Some(height) => entity.insert(OutlineMesh {
+            source: mesh_handle,
+            height,
+        }),
+        None => entity.insert(Mesh3d(mesh_handle)),
+    };
+}
+
+/// Level mesh generated along the outline of another mesh once it is loaded,
+/// see [LevelMesh::outline_height].
+#[derive(Component, Debug)]
+struct OutlineMesh {
+    source: Handle<Mesh>,
+    height: f32,
+}
+
+fn generate_outline_meshes(
+    outlines: Query<(Entity, &OutlineMesh)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    for (entity, outline) in outlines.iter() {
+        let Some(source) = meshes.get(&outline.source) else {
+            continue;
+        };
+
+        let mesh = extrude_outline(source, outline.height);
+        commands.entity(entity).remove::<OutlineMesh>();
+
+        let Some(mesh) = mesh else {
+            warn!("Could not generate outline mesh for {entity}");
+            continue;
+        };
+
+        commands.entity(entity).insert(Mesh3d(meshes.add(mesh)));
+    }
+}
+
+/// Colors temporary bumpers from white to red as they run out of hits.
+fn show_remaining_hits(
+    bumpers: Query<
+        (&RemainingHits, &MeshMaterial3d<StandardMaterial>),
+        Or<(
+            Changed<RemainingHits>,
+            Added<MeshMaterial3d<StandardMaterial>>,
+        )>,
+    >,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (hits, material) in bumpers.iter() {
+        let Some(material) = materials.get_mut(material.id()) else {
+            continue;
+        };
+
+        let intensity = match hits.0 {
+            0 | 1 => 0.0,
+            2 => 0.5,
+            _ => 1.0,
+        };
+        material.base_color = Color::srgb(1.0, intensity, intensity);
+    }
+}
+
+/// Makes the ball of the local player glow while it can be hit.
+fn highlight_local_player(
+    players: Query<
+        (&Player, &MeshMaterial3d<StandardMaterial>),
+        (
+            With<LocalPlayer>,
+            Or<(Changed<Player>, Added<MeshMaterial3d<StandardMaterial>>)>,
+        ),
+    >,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (player, material) in players.iter() {
+        let Some(material) = materials.get_mut(material.id()) else {
+            continue;
+        };
+
+        material.emissive = match player.can_move {
+            true => LinearRgba::rgb(0.2, 0.6, 0.2),
+            false => LinearRgba::BLACK,
+        };
+    }
 }
 
 fn on_power_up_added(
@@ -136,6 +247,54 @@ fn on_power_up_added(
     ));
 }
 
+fn on_hazard_added(
+    trigger: Trigger<OnAdd, HazardZone>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    let entity = trigger.target();
+
+    commands.entity(entity).insert((
+        Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.1, 0.3, 0.8, 0.6),
+            alpha_mode: Blend,
+            perceptual_roughness: 0.1,
+            ..default()
+        })),
+    ));
+}
+
+fn on_conveyor_added(
+    trigger: Trigger<OnAdd, ConveyorZone>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    let entity = trigger.target();
+
+    commands.entity(entity).insert((
+        Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.8, 0.6, 0.1, 0.3),
+            alpha_mode: Blend,
+            ..default()
+        })),
+    ));
+}
+
+/// Shows the direction in which conveyors push the balls.
+fn draw_conveyors(conveyors: Query<(&ConveyorZone, &GlobalTransform)>, mut gizmos: Gizmos) {
+    for (conveyor, transform) in conveyors.iter() {
+        let direction = transform.rotation() * conveyor.direction.normalize_or_zero();
+        let start = transform.translation() - direction * 0.1;
+        let end = transform.translation() + direction * 0.1;
+
+        gizmos.arrow(start, end, Color::srgb(0.8, 0.6, 0.1));
+    }
+}
+
 fn on_connected(_trigger: Trigger<OnAdd, Session>, mut game_state: ResMut<NextState<GameState>>) {
     game_state.set(GameState::Playing);
 }
@@ -181,6 +340,25 @@ fn on_player_added(
     }
 }
 
+fn on_abandoned_ball_added(
+    trigger: Trigger<OnAdd, AbandonedBall>,
+    server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    let player_mesh_handle: Handle<Mesh> = server.load("Player.glb#Mesh0/Primitive0");
+
+    commands.entity(trigger.target()).insert((
+        Mesh3d(player_mesh_handle),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgb(0.5, 0.5, 0.5),
+            metallic: 0.5,
+            perceptual_roughness: 0.5,
+            ..default()
+        })),
+    ));
+}
+
 /// Just to be safe that all entities from the server are removed
 fn despawn_replicated(replicated: Query<Entity, With<Replicated>>, mut commands: Commands) {
     for entity in replicated.iter() {
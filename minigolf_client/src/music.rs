@@ -0,0 +1,31 @@
+use {bevy::prelude::*, minigolf::HoleMusic};
+
+/// Plays the music of the current hole.
+pub(crate) struct HoleMusicPlugin;
+
+impl Plugin for HoleMusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(play_hole_music);
+    }
+}
+
+/// Loops the music on the replicated entity, so that it stops when the server despawns it after the hole.
+///
+/// If the asset cannot be loaded, the error is logged by the asset server and the hole is played without music.
+fn play_hole_music(
+    trigger: Trigger<OnAdd, HoleMusic>,
+    music: Query<&HoleMusic>,
+    server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let entity = trigger.target();
+    let Ok(music) = music.get(entity) else {
+        return;
+    };
+
+    info!("Playing hole music {}", music.asset);
+    commands.entity(entity).insert((
+        AudioPlayer::new(server.load(&music.asset)),
+        PlaybackSettings::LOOP,
+    ));
+}
@@ -1,5 +1,8 @@
 use {
-    crate::ui::{ServerState, lobby::LobbyUi, lobby_server::LobbyServerSession},
+    crate::ui::{
+        ServerState, disconnected::DisconnectReason, lobby::LobbyUi, lobby_select::LobbiesUi,
+        lobby_server::LobbyServerSession,
+    },
     aeronet::io::{Session, SessionEndpoint, connection::Disconnected},
     aeronet_replicon::client::{AeronetRepliconClient, AeronetRepliconClientPlugin},
     aeronet_websocket::client::{WebSocketClient, WebSocketClientPlugin},
@@ -10,7 +13,8 @@ use {
     bevy::prelude::*,
     bevy_replicon::prelude::*,
     minigolf::{
-        AuthenticatePlayer, PlayerCredentials, RequestAuthentication,
+        AuthenticatePlayer, GAME_COMPLETED_REASON, PROTOCOL_VERSION, PlayerCredentials,
+        RequestAuthentication,
         lobby::{PlayerId, user::ServerPacket},
     },
 };
@@ -113,7 +117,10 @@ fn on_disconnected(
     trigger: Trigger<Disconnected>,
     names: Query<&Name>,
     game_servers: Query<(), With<AeronetRepliconClient>>,
-    mut state: ResMut<NextState<ServerState>>,
+    lobby_servers: Query<(), With<LobbyServerSession>>,
+    state: Res<State<ServerState>>,
+    mut next_state: ResMut<NextState<ServerState>>,
+    mut commands: Commands,
 ) {
     let session = trigger.target();
     let name = names
@@ -124,17 +131,32 @@ fn on_disconnected(
         Disconnected::ByUser(reason) => {
             info!("{name} disconnected by user: {reason}");
         }
+        Disconnected::ByPeer(reason)
+            if reason == GAME_COMPLETED_REASON && game_servers.contains(session) =>
+        {
+            info!("{name} completed the game");
+        }
         Disconnected::ByPeer(reason) => {
             info!("{name} disconnected by peer: {reason}");
+            commands.insert_resource(DisconnectReason {
+                server: name.to_string(),
+                reason: reason.clone(),
+            });
         }
         Disconnected::ByError(err) => {
             info!("{name} disconnected due to error: {err:?}");
         }
     };
 
+    // the lobby server session is kept open while playing, so the lobby membership is still intact
     if let Ok(_) = game_servers.get(session) {
         info!("Disconnected from game server, falling back to current lobby");
-        state.set(ServerState::Lobby);
+        next_state.set(ServerState::Lobby);
+    } else if let Ok(_) = lobby_servers.get(session) {
+        if *state.get() != ServerState::LobbyServer {
+            info!("Disconnected from lobby server, falling back to lobby server selection");
+            next_state.set(ServerState::LobbyServer);
+        }
     }
 }
 
@@ -142,6 +164,7 @@ fn handle_lobby_server_packets(
     mut sessions: Query<&mut Session, With<LobbyServerSession>>,
     mut server_state: ResMut<NextState<ServerState>>,
     mut lobby_ui: ResMut<LobbyUi>,
+    mut lobbies_ui: ResMut<LobbiesUi>,
     mut commands: Commands,
 ) {
     let Ok(mut lobby_session) = sessions.single_mut() else {
@@ -163,7 +186,9 @@ fn handle_lobby_server_packets(
                 commands.insert_resource::<LobbyUi>(LobbyUi::new_lobby(lobby_id.to_string()));
             }
 
-            ServerPacket::AvailableLobbies(_) => {}
+            ServerPacket::AvailableLobbies(lobbies) => {
+                lobbies_ui.set_available_lobbies(lobbies);
+            }
 
             ServerPacket::LobbyJoined(lobby_id, player_ids) => {
                 server_state.set(ServerState::Lobby);
@@ -196,6 +221,18 @@ fn handle_lobby_server_packets(
             ServerPacket::PlayerLeft(player) => {
                 lobby_ui.remove_player(player.player_id);
             }
+
+            ServerPacket::Chat(player_id, message) => {
+                lobby_ui.add_chat_message(player_id, message);
+            }
+
+            ServerPacket::HandicapSet(player_id, handicap) => {
+                lobby_ui.set_handicap(player_id, handicap);
+            }
+
+            ServerPacket::ReadyCleared => {
+                lobby_ui.clear_ready();
+            }
         }
     }
 }
@@ -225,8 +262,113 @@ fn on_authentication_requested(
 
         info!("Sending {:?}", auth);
         writer.write(AuthenticatePlayer {
+            protocol_version: PROTOCOL_VERSION,
             id: auth.id,
             credentials: auth.credentials,
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        aeronet::io::{bytes::Bytes, packet::RecvPacket},
+        bevy::ecs::system::RunSystemOnce,
+        std::time::Instant,
+    };
+
+    fn lobby_server_session(packets: impl IntoIterator<Item = ServerPacket>) -> impl Bundle {
+        let mut session = Session::new(Instant::now(), 1200);
+        for packet in packets {
+            let packet: String = packet.into();
+            session.recv.push(RecvPacket {
+                recv_at: Instant::now(),
+                payload: Bytes::from(packet),
+            });
+        }
+
+        (LobbyServerSession, session)
+    }
+
+    fn lobby_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<NextState<ServerState>>();
+        world.init_resource::<LobbyUi>();
+        world.init_resource::<LobbiesUi>();
+        world
+    }
+
+    #[test]
+    fn available_lobbies_are_shown() {
+        let mut world = lobby_world();
+        world.spawn(lobby_server_session([ServerPacket::AvailableLobbies(
+            vec![1, 2],
+        )]));
+
+        world.run_system_once(handle_lobby_server_packets).unwrap();
+
+        let lobbies_ui = world.resource::<LobbiesUi>();
+        assert_eq!(lobbies_ui.available_lobbies(), &[1, 2]);
+    }
+
+    #[test]
+    fn latest_available_lobbies_replace_previous_ones() {
+        let mut world = lobby_world();
+        world.spawn(lobby_server_session([
+            ServerPacket::AvailableLobbies(vec![1, 2]),
+            ServerPacket::AvailableLobbies(vec![3]),
+        ]));
+
+        world.run_system_once(handle_lobby_server_packets).unwrap();
+        assert_eq!(world.resource::<LobbiesUi>().available_lobbies(), &[3]);
+    }
+
+    #[test]
+    fn lobby_session_persists_after_game_is_completed() {
+        let mut world = World::new();
+        world.insert_resource(State::new(ServerState::GameServer));
+        world.init_resource::<NextState<ServerState>>();
+        world.add_observer(on_disconnected);
+
+        let lobby_server = world
+            .spawn((Name::new("Lobby server"), LobbyServerSession))
+            .id();
+        let game_server = world
+            .spawn((Name::new("Game server"), AeronetRepliconClient))
+            .id();
+
+        let reason = GAME_COMPLETED_REASON.to_owned();
+        world.trigger_targets(Disconnected::ByPeer(reason), game_server);
+        world.flush();
+
+        assert!(matches!(
+            *world.resource::<NextState<ServerState>>(),
+            NextState::Pending(ServerState::Lobby)
+        ));
+        assert!(!world.contains_resource::<DisconnectReason>());
+        assert!(world.get::<LobbyServerSession>(lobby_server).is_some());
+    }
+
+    #[test]
+    fn leaving_game_returns_to_lobby() {
+        let mut world = World::new();
+        world.insert_resource(State::new(ServerState::GameServer));
+        world.init_resource::<NextState<ServerState>>();
+        world.add_observer(on_disconnected);
+
+        let game_server = world
+            .spawn((Name::new("Game server"), AeronetRepliconClient))
+            .id();
+
+        let reason = "Left the game".to_owned();
+        world.trigger_targets(Disconnected::ByUser(reason), game_server);
+        world.flush();
+
+        assert!(matches!(
+            *world.resource::<NextState<ServerState>>(),
+            NextState::Pending(ServerState::Lobby)
+        ));
+        assert!(!world.contains_resource::<DisconnectReason>());
+    }
+}
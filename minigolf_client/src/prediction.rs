@@ -0,0 +1,189 @@
+use {
+    crate::LocalPlayer,
+    bevy::prelude::*,
+    minigolf::{BALL_MASS, GameState, MAX_SHOT_FORCE, PlayerInput},
+};
+
+/// Predicts the local ball's trajectory after hitting it, until the server's movement is replicated.
+///
+/// Hides the round trip to the server, otherwise the ball only starts moving once the hit is applied.
+pub(crate) struct PredictionPlugin;
+
+impl Plugin for PredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BallPrediction>();
+        app.register_type::<PredictedMovement>();
+
+        app.init_resource::<BallPrediction>();
+
+        app.add_systems(
+            Update,
+            (start_prediction, predict_movement)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Settings for predicting the local ball's movement.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub(crate) struct BallPrediction {
+    pub(crate) enabled: bool,
+    /// Mass of the ball on the server, used to convert the hit impulse into velocity.
+    pub(crate) ball_mass: f32,
+    /// Linear damping of the ball on the server.
+    pub(crate) damping: f32,
+    /// Approximate deceleration caused by friction with the floor.
+    pub(crate) deceleration: f32,
+    /// How long to wait for the server to move the ball, before assuming the hit was rejected.
+    pub(crate) max_duration: f32,
+}
+
+impl Default for BallPrediction {
+    fn default() -> Self {
+        BallPrediction {
+            enabled: true,
+            ball_mass: BALL_MASS,
+            damping: 0.5,
+            deceleration: 1.0,
+            max_duration: 0.5,
+        }
+    }
+}
+
+/// Movement of the local ball that was predicted but not yet confirmed by the server.
+#[derive(Component, Reflect, Debug)]
+pub(crate) struct PredictedMovement {
+    /// Position of the ball when it was hit, which is where the server still has it.
+    origin: Vec3,
+    velocity: Vec3,
+    elapsed: f32,
+    /// Last predicted position, to detect when the replicated transform overwrites it.
+    predicted: Vec3,
+}
+
+fn start_prediction(
+    mut reader: EventReader<PlayerInput>,
+    player: Query<(Entity, &Transform), (With<LocalPlayer>, Without<PredictedMovement>)>,
+    settings: Res<BallPrediction>,
+    mut commands: Commands,
+) {
+    for input in reader.read() {
+        if !settings.enabled {
+            continue;
+        }
+
+        let Some(movement) = input.movement() else {
+            continue;
+        };
+
+        let Ok((entity, transform)) = player.single() else {
+            continue;
+        };
+
+        // only the horizontal component is predicted, since the vertical movement depends on the course
+        let mut impulse = movement.clamp_length_max(MAX_SHOT_FORCE);
+        impulse.y = 0.0;
+
+        commands.entity(entity).insert(PredictedMovement {
+            origin: transform.translation,
+            velocity: impulse / settings.ball_mass,
+            elapsed: 0.0,
+            predicted: transform.translation,
+        });
+    }
+}
+
+fn predict_movement(
+    mut player: Query<(Entity, &mut Transform, &mut PredictedMovement), With<LocalPlayer>>,
+    settings: Res<BallPrediction>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let Ok((entity, mut transform, mut prediction)) = player.single_mut() else {
+        return;
+    };
+
+    if transform.translation != prediction.predicted {
+        debug!(
+            "Server moved the ball, predicted position was off by {}",
+            transform.translation.distance(prediction.predicted)
+        );
+        commands.entity(entity).remove::<PredictedMovement>();
+        return;
+    }
+
+    let delta = time.delta_secs();
+    prediction.elapsed += delta;
+
+    if prediction.elapsed > settings.max_duration {
+        warn!("Server did not move the ball, reverting predicted movement");
+        transform.translation = prediction.origin;
+        commands.entity(entity).remove::<PredictedMovement>();
+        return;
+    }
+
+    let velocity = prediction.velocity / (1.0 + settings.damping * delta);
+    let speed = (velocity.length() - settings.deceleration * delta).max(0.0);
+    prediction.velocity = velocity.normalize_or_zero() * speed;
+
+    transform.translation += prediction.velocity * delta;
+    prediction.predicted = transform.translation;
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, bevy::ecs::system::RunSystemOnce, core::time::Duration};
+
+    fn predicted_ball(world: &mut World) -> Entity {
+        world.init_resource::<Time>();
+        world.init_resource::<BallPrediction>();
+        world.init_resource::<Events<PlayerInput>>();
+
+        let ball = world.spawn((LocalPlayer, Transform::default())).id();
+        world.send_event(PlayerInput::Move(Vec2::X));
+        world.run_system_once(start_prediction).unwrap();
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(100));
+        world.run_system_once(predict_movement).unwrap();
+        assert!(world.get::<Transform>(ball).unwrap().translation.x > 0.0);
+
+        ball
+    }
+
+    #[test]
+    fn replicated_transform_replaces_prediction() {
+        let mut world = World::new();
+        let ball = predicted_ball(&mut world);
+
+        let replicated = Vec3::new(0.01, 0.0, 0.0);
+        world.get_mut::<Transform>(ball).unwrap().translation = replicated;
+        world.run_system_once(predict_movement).unwrap();
+
+        assert!(world.get::<PredictedMovement>(ball).is_none());
+        assert_eq!(
+            world.get::<Transform>(ball).unwrap().translation,
+            replicated
+        );
+    }
+
+    #[test]
+    fn ball_snaps_back_when_server_does_not_move_it() {
+        let mut world = World::new();
+        let ball = predicted_ball(&mut world);
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world.run_system_once(predict_movement).unwrap();
+
+        assert!(world.get::<PredictedMovement>(ball).is_none());
+        assert_eq!(
+            world.get::<Transform>(ball).unwrap().translation,
+            Vec3::ZERO
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use {
+    bevy::{color::palettes::basic::WHITE, prelude::*},
+    minigolf::{GameState, Player},
+    std::collections::VecDeque,
+};
+
+/// Draws a fading trail of where each ball travelled during its last shot.
+pub(crate) struct ShotTrailPlugin;
+
+impl Plugin for ShotTrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(add_shot_trail);
+
+        app.add_systems(
+            Update,
+            (record_shot_trails, draw_shot_trails)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Maximum number of positions kept for a single shot.
+const TRAIL_CAPACITY: usize = 256;
+/// Minimum distance the ball has to move before a new position is recorded.
+const TRAIL_MIN_DISTANCE: f32 = 0.01;
+/// Seconds after the ball stops until the trail is no longer visible.
+const TRAIL_FADE_DURATION: f32 = 5.0;
+
+/// Positions of a ball during its last shot.
+#[derive(Component, Debug)]
+pub(crate) struct ShotTrail {
+    positions: VecDeque<Vec3>,
+    /// Whether the ball is currently moving after a shot.
+    recording: bool,
+    /// Time when the last shot ended, used to fade out the trail.
+    stopped_at: Option<f32>,
+}
+
+impl Default for ShotTrail {
+    fn default() -> Self {
+        ShotTrail {
+            positions: VecDeque::with_capacity(TRAIL_CAPACITY),
+            recording: false,
+            stopped_at: None,
+        }
+    }
+}
+
+impl ShotTrail {
+    fn start(&mut self, position: Vec3) {
+        self.positions.clear();
+        self.positions.push_back(position);
+        self.recording = true;
+        self.stopped_at = None;
+    }
+
+    fn stop(&mut self, time: f32) {
+        self.recording = false;
+        self.stopped_at = Some(time);
+    }
+
+    /// Records the position, dropping the oldest one once the trail is full.
+    fn push(&mut self, position: Vec3) {
+        if let Some(last) = self.positions.back() {
+            if last.distance(position) < TRAIL_MIN_DISTANCE {
+                return;
+            }
+        }
+
+        if self.positions.len() == TRAIL_CAPACITY {
+            self.positions.pop_front();
+        }
+
+        self.positions.push_back(position);
+    }
+
+    /// Gets how visible the trail is at the specified time, from 0 to 1.
+    fn opacity(&self, time: f32) -> f32 {
+        match self.stopped_at {
+            Some(stopped_at) => (1.0 - (time - stopped_at) / TRAIL_FADE_DURATION).clamp(0.0, 1.0),
+            None => 1.0,
+        }
+    }
+}
+
+fn add_shot_trail(trigger: Trigger<OnAdd, Player>, mut commands: Commands) {
+    commands
+        .entity(trigger.target())
+        .insert(ShotTrail::default());
+}
+
+fn record_shot_trails(mut players: Query<(&Player, &Transform, &mut ShotTrail)>, time: Res<Time>) {
+    for (player, transform, mut trail) in players.iter_mut() {
+        match (trail.recording, player.can_move) {
+            // the server only allows moving again once the ball has stopped
+            (false, false) => trail.start(transform.translation),
+            (true, false) => trail.push(transform.translation),
+            (true, true) => {
+                trail.push(transform.translation);
+                trail.stop(time.elapsed_secs());
+            }
+            (false, true) => {}
+        }
+    }
+}
+
+fn draw_shot_trails(players: Query<&ShotTrail>, time: Res<Time>, mut gizmos: Gizmos) {
+    let now = time.elapsed_secs();
+
+    for trail in players.iter() {
+        let opacity = trail.opacity(now);
+        if opacity <= 0.0 || trail.positions.len() < 2 {
+            continue;
+        }
+
+        // older positions are drawn more transparent
+        let count = trail.positions.len() as f32;
+        gizmos.linestrip_gradient(trail.positions.iter().enumerate().map(|(index, position)| {
+            let alpha = opacity * (index + 1) as f32 / count;
+            (*position, Color::from(WHITE.with_alpha(alpha)))
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trail_skips_close_positions_and_drops_oldest() {
+        let mut trail = ShotTrail::default();
+        trail.start(Vec3::ZERO);
+
+        trail.push(Vec3::splat(TRAIL_MIN_DISTANCE / 10.0));
+        assert_eq!(trail.positions.len(), 1);
+
+        for index in 1..=TRAIL_CAPACITY {
+            trail.push(Vec3::X * index as f32);
+        }
+        assert_eq!(trail.positions.len(), TRAIL_CAPACITY);
+        assert_eq!(trail.positions.front(), Some(&Vec3::X));
+        assert_eq!(
+            trail.positions.back(),
+            Some(&(Vec3::X * TRAIL_CAPACITY as f32))
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use {
+    crate::ui::ServerState,
+    aeronet::io::connection::Disconnect,
+    aeronet_replicon::client::AeronetRepliconClient,
+    bevy::prelude::*,
+    bevy_egui::{EguiContexts, egui},
+    bevy_replicon::prelude::*,
+};
+
+/// UI for warning the player when their connection to the game server is poor,
+/// and for leaving the game.
+pub(crate) struct ConnectionQualityUiPlugin;
+
+impl Plugin for ConnectionQualityUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (connection_quality_ui, leave_game_ui).run_if(in_state(ServerState::GameServer)),
+        );
+    }
+}
+
+/// Round trip time in seconds above which the connection is considered laggy.
+const RTT_WARNING_THRESHOLD: f64 = 0.2;
+
+/// Fraction of lost packets above which the connection is considered unstable.
+const PACKET_LOSS_WARNING_THRESHOLD: f64 = 0.05;
+
+fn connection_warning(rtt: f64, packet_loss: f64) -> Option<&'static str> {
+    match (
+        rtt > RTT_WARNING_THRESHOLD,
+        packet_loss > PACKET_LOSS_WARNING_THRESHOLD,
+    ) {
+        (true, true) => Some("High latency and packet loss"),
+        (true, false) => Some("High latency"),
+        (false, true) => Some("Packet loss"),
+        (false, false) => None,
+    }
+}
+
+fn connection_quality_ui(mut context: EguiContexts, replicon_client: Res<RepliconClient>) {
+    if !replicon_client.is_connected() {
+        return;
+    }
+
+    let stats = replicon_client.stats();
+    let Some(warning) = connection_warning(stats.rtt, stats.packet_loss) else {
+        return;
+    };
+
+    egui::Area::new(egui::Id::new("Connection quality"))
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+        .show(context.ctx_mut(), |ui| {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!(
+                    "{warning} (RTT {:.0}ms, packet loss {:.1}%)",
+                    stats.rtt * 1000.0,
+                    stats.packet_loss * 100.0
+                ),
+            );
+        });
+}
+
+/// Disconnects from the game server, which returns the player to the current lobby.
+fn leave_game_ui(
+    mut context: EguiContexts,
+    game_servers: Query<Entity, With<AeronetRepliconClient>>,
+    mut commands: Commands,
+) {
+    egui::Area::new(egui::Id::new("Leave game"))
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(10.0, -10.0))
+        .show(context.ctx_mut(), |ui| {
+            if !ui.button("Leave game").clicked() {
+                return;
+            }
+
+            leave_game(&game_servers, &mut commands);
+        });
+}
+
+pub(crate) fn leave_game(
+    game_servers: &Query<Entity, With<AeronetRepliconClient>>,
+    commands: &mut Commands,
+) {
+    info!("Leaving game");
+    for session in game_servers.iter() {
+        commands.trigger_targets(Disconnect::new("Left the game"), session);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, bevy::ecs::system::RunSystemOnce};
+
+    #[test]
+    fn poor_connections_are_warned_about() {
+        assert_eq!(connection_warning(0.05, 0.0), None);
+        assert_eq!(connection_warning(0.3, 0.0), Some("High latency"));
+        assert_eq!(connection_warning(0.05, 0.1), Some("Packet loss"));
+        assert_eq!(
+            connection_warning(0.3, 0.1),
+            Some("High latency and packet loss")
+        );
+        assert_eq!(
+            connection_warning(RTT_WARNING_THRESHOLD, PACKET_LOSS_WARNING_THRESHOLD),
+            None
+        );
+    }
+
+    #[derive(Resource, Default)]
+    struct DisconnectedSessions(Vec<Entity>);
+
+    #[test]
+    fn leaving_disconnects_only_from_game_server() {
+        let mut world = World::new();
+        world.init_resource::<DisconnectedSessions>();
+        world.add_observer(
+            |trigger: Trigger<Disconnect>, mut sessions: ResMut<DisconnectedSessions>| {
+                sessions.0.push(trigger.target());
+            },
+        );
+
+        let game_server = world.spawn(AeronetRepliconClient).id();
+        world.spawn_empty();
+
+        world
+            .run_system_once(
+                |game_servers: Query<Entity, With<AeronetRepliconClient>>,
+                 mut commands: Commands| {
+                    leave_game(&game_servers, &mut commands);
+                },
+            )
+            .unwrap();
+
+        assert_eq!(world.resource::<DisconnectedSessions>().0, [game_server]);
+    }
+}
@@ -0,0 +1,39 @@
+use {
+    bevy::prelude::*,
+    bevy_egui::{EguiContexts, egui},
+};
+
+/// UI for showing why a server disconnected the client
+pub(crate) struct DisconnectedUiPlugin;
+
+impl Plugin for DisconnectedUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DisconnectReason>();
+
+        app.add_systems(
+            Update,
+            disconnected_ui.run_if(resource_exists::<DisconnectReason>),
+        );
+    }
+}
+
+/// The reason given by the server when it disconnected the client.
+#[derive(Resource, Reflect, Debug)]
+pub(crate) struct DisconnectReason {
+    pub(crate) server: String,
+    pub(crate) reason: String,
+}
+
+fn disconnected_ui(
+    mut context: EguiContexts,
+    reason: Res<DisconnectReason>,
+    mut commands: Commands,
+) {
+    egui::Window::new("Disconnected").show(context.ctx_mut(), |ui| {
+        ui.label(format!("{} disconnected: {}", reason.server, reason.reason));
+
+        if ui.button("Ok").clicked() {
+            commands.remove_resource::<DisconnectReason>();
+        }
+    });
+}
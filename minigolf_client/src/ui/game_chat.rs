@@ -0,0 +1,116 @@
+use {
+    crate::ui::ServerState,
+    bevy::prelude::*,
+    bevy_egui::{EguiContexts, egui},
+    minigolf::{
+        CHAT_MESSAGE_MAX_LENGTH, ChatMessage, HoleInOne, Player, SendChat, default_player_name,
+        lobby::PlayerId,
+    },
+};
+
+/// UI for chatting and sending emotes while playing a game
+pub(crate) struct GameChatUiPlugin;
+
+impl Plugin for GameChatUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameChat>();
+
+        app.add_systems(OnExit(ServerState::GameServer), clear_chat)
+            .configure_sets(
+                Update,
+                GameChatUiSet.run_if(in_state(ServerState::GameServer)),
+            )
+            .add_systems(
+                Update,
+                (receive_chat_messages, game_chat_ui)
+                    .chain()
+                    .in_set(GameChatUiSet),
+            );
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+struct GameChatUiSet;
+
+/// Quick messages that can be sent with a single click.
+const EMOTES: [&str; 4] = ["Nice shot!", "Oops", "So close!", "GG"];
+
+#[derive(Resource, Default, Debug)]
+struct GameChat {
+    messages: Vec<(PlayerId, String)>,
+    input: String,
+}
+
+fn clear_chat(mut chat: ResMut<GameChat>) {
+    *chat = GameChat::default();
+}
+
+fn receive_chat_messages(
+    mut reader: EventReader<ChatMessage>,
+    mut hole_in_one_reader: EventReader<HoleInOne>,
+    mut chat: ResMut<GameChat>,
+) {
+    for ChatMessage { player, message } in reader.read() {
+        chat.messages.push((*player, message.clone()));
+    }
+
+    for HoleInOne { player } in hole_in_one_reader.read() {
+        chat.messages.push((*player, "Hole in one!".to_owned()));
+    }
+}
+
+fn game_chat_ui(
+    mut context: EguiContexts,
+    mut chat: ResMut<GameChat>,
+    mut writer: EventWriter<SendChat>,
+    players: Query<(&Player, &Name)>,
+) {
+    let chat = &mut *chat;
+
+    egui::Window::new("Chat")
+        .default_open(false)
+        .show(context.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(150.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for (player, message) in &chat.messages {
+                        let name = players
+                            .iter()
+                            .find(|(other, _)| other.id == *player)
+                            .map_or_else(
+                                || default_player_name(*player),
+                                |(_, name)| name.to_string(),
+                            );
+                        ui.label(format!("{name}: {message}"));
+                    }
+                });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                for emote in EMOTES {
+                    if ui.button(emote).clicked() {
+                        writer.write(SendChat(emote.to_owned()));
+                    }
+                }
+            });
+
+            let enter_pressed = ui.input(|state| state.key_pressed(egui::Key::Enter));
+
+            let mut send = false;
+            ui.horizontal(|ui| {
+                let input_resp = ui.add(
+                    egui::TextEdit::singleline(&mut chat.input).char_limit(CHAT_MESSAGE_MAX_LENGTH),
+                );
+                send |= input_resp.lost_focus() && enter_pressed;
+                send |= ui.button("Send").clicked();
+            });
+
+            if !send || chat.input.trim().is_empty() {
+                return;
+            }
+
+            let message = std::mem::take(&mut chat.input);
+            writer.write(SendChat(message));
+        });
+}
@@ -3,7 +3,11 @@ use {
     aeronet::io::{Session, bytes::Bytes},
     bevy::prelude::*,
     bevy_egui::{EguiContexts, egui},
-    minigolf::lobby::{PlayerId, user::ClientPacket},
+    minigolf::{
+        CHAT_MESSAGE_MAX_LENGTH, PLAYER_NAME_MAX_LENGTH, default_player_name,
+        lobby::{PlayerId, user::ClientPacket},
+        sanitize_player_name,
+    },
 };
 
 /// UI for managing the current lobby
@@ -14,7 +18,7 @@ impl Plugin for LobbyUiPlugin {
         app.init_resource::<LobbyUi>();
 
         app.configure_sets(Update, LobbyUiSet.run_if(in_state(ServerState::Lobby)))
-            .add_systems(Update, lobby_ui.in_set(LobbyUiSet));
+            .add_systems(Update, (lobby_ui, chat_ui).in_set(LobbyUiSet));
     }
 }
 
@@ -25,7 +29,14 @@ struct LobbyUiSet;
 pub(crate) struct LobbyUi {
     lobby_id: String,
     player_ids: Vec<PlayerId>,
+    /// Whether the lobby was created by this player, only the owner can set handicaps.
+    is_owner: bool,
+    handicaps: Vec<(PlayerId, u32)>,
     course_count: usize,
+    chat_messages: Vec<(PlayerId, String)>,
+    chat_input: String,
+    name_input: String,
+    ready: bool,
 }
 
 impl Default for LobbyUi {
@@ -33,7 +44,13 @@ impl Default for LobbyUi {
         LobbyUi {
             lobby_id: String::new(),
             player_ids: Vec::new(),
+            is_owner: false,
+            handicaps: Vec::new(),
             course_count: 1,
+            chat_messages: Vec::new(),
+            chat_input: String::new(),
+            name_input: String::new(),
+            ready: false,
         }
     }
 }
@@ -42,6 +59,7 @@ impl LobbyUi {
     pub(crate) fn new_lobby(lobby_id: String) -> Self {
         LobbyUi {
             lobby_id,
+            is_owner: true,
             ..default()
         }
     }
@@ -62,12 +80,33 @@ impl LobbyUi {
     pub(crate) fn remove_player(&mut self, player: PlayerId) {
         info!("Player left current lobby {:?}", player);
         self.player_ids.retain(|p| *p != player);
+        self.handicaps.retain(|(id, _)| *id != player);
+    }
+
+    fn handicap(&self, player: PlayerId) -> u32 {
+        self.handicaps
+            .iter()
+            .find(|(id, _)| *id == player)
+            .map_or(0, |(_, handicap)| *handicap)
+    }
+
+    pub(crate) fn set_handicap(&mut self, player: PlayerId, handicap: u32) {
+        self.handicaps.retain(|(id, _)| *id != player);
+        self.handicaps.push((player, handicap));
+    }
+
+    pub(crate) fn clear_ready(&mut self) {
+        self.ready = false;
+    }
+
+    pub(crate) fn add_chat_message(&mut self, player: PlayerId, message: String) {
+        self.chat_messages.push((player, message));
     }
 }
 
 fn lobby_ui(
     mut context: EguiContexts,
-    lobby_ui: ResMut<LobbyUi>,
+    mut lobby_ui: ResMut<LobbyUi>,
     mut lobby_session: Query<&mut Session, With<LobbyServerSession>>,
     mut state: ResMut<NextState<ServerState>>,
 ) {
@@ -78,10 +117,49 @@ fn lobby_ui(
         ui.separator();
 
         ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut lobby_ui.name_input)
+                    .char_limit(PLAYER_NAME_MAX_LENGTH)
+                    .hint_text("Name"),
+            );
+
+            if !ui.button("Set name").clicked() {
+                return;
+            }
+
+            let Some(name) = sanitize_player_name(&lobby_ui.name_input) else {
+                return;
+            };
+
+            let Ok(mut session) = lobby_session.single_mut() else {
+                warn!("Not connected to the lobby server");
+                return;
+            };
+
+            let request: String = ClientPacket::SetName(name).into();
+            session.send.push(Bytes::from(request));
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut lobby_ui.ready, "Ready").changed() {
+                let Ok(mut session) = lobby_session.single_mut() else {
+                    warn!("Not connected to the lobby server");
+                    return;
+                };
+
+                let request: String = ClientPacket::SetReady(lobby_ui.ready).into();
+                session.send.push(Bytes::from(request));
+            }
+
             if ui.button("Start game").clicked() {
                 info!("Starting game");
 
-                let mut session = lobby_session.single_mut().unwrap();
+                let Ok(mut session) = lobby_session.single_mut() else {
+                    warn!("Not connected to the lobby server");
+                    return;
+                };
+
                 let request: String = ClientPacket::StartGame.into();
                 session.send.push(Bytes::from(request));
             }
@@ -89,7 +167,11 @@ fn lobby_ui(
             if ui.button("Leave lobby").clicked() {
                 info!("Leaving lobby");
 
-                let mut session = lobby_session.single_mut().unwrap();
+                let Ok(mut session) = lobby_session.single_mut() else {
+                    warn!("Not connected to the lobby server");
+                    return;
+                };
+
                 let request: String = ClientPacket::LeaveLobby.into();
                 session.send.push(Bytes::from(request));
                 state.set(ServerState::Lobbies);
@@ -98,8 +180,77 @@ fn lobby_ui(
         ui.separator();
 
         ui.label("Players");
-        for player in &lobby_ui.player_ids {
-            ui.label(format!("{player:?}"));
+        let mut changed_handicap = None;
+        for &player in &lobby_ui.player_ids {
+            ui.horizontal(|ui| {
+                ui.label(default_player_name(player));
+
+                if lobby_ui.is_owner {
+                    let mut handicap = lobby_ui.handicap(player);
+                    let drag = egui::DragValue::new(&mut handicap).prefix("Handicap: ");
+                    if ui.add(drag).changed() {
+                        changed_handicap = Some((player, handicap));
+                    }
+                } else {
+                    ui.label(format!("Handicap: {}", lobby_ui.handicap(player)));
+                }
+            });
+        }
+
+        if let Some((player, handicap)) = changed_handicap {
+            lobby_ui.set_handicap(player, handicap);
+
+            let Ok(mut session) = lobby_session.single_mut() else {
+                warn!("Not connected to the lobby server");
+                return;
+            };
+
+            let request: String = ClientPacket::SetHandicap(player, handicap).into();
+            session.send.push(Bytes::from(request));
+        }
+    });
+}
+
+fn chat_ui(
+    mut context: EguiContexts,
+    mut lobby_ui: ResMut<LobbyUi>,
+    mut lobby_session: Query<&mut Session, With<LobbyServerSession>>,
+) {
+    let lobby_ui = &mut *lobby_ui;
+
+    egui::Window::new("Chat").show(context.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for (player, message) in &lobby_ui.chat_messages {
+                    ui.label(format!("{}: {message}", default_player_name(*player)));
+                }
+            });
+        ui.separator();
+
+        let enter_pressed = ui.input(|state| state.key_pressed(egui::Key::Enter));
+
+        let mut send = false;
+        ui.horizontal(|ui| {
+            let input_resp = ui.add(
+                egui::TextEdit::singleline(&mut lobby_ui.chat_input)
+                    .char_limit(CHAT_MESSAGE_MAX_LENGTH),
+            );
+            send |= input_resp.lost_focus() && enter_pressed;
+            send |= ui.button("Send").clicked();
+        });
+
+        if !send || lobby_ui.chat_input.trim().is_empty() {
+            return;
         }
+
+        let Ok(mut session) = lobby_session.single_mut() else {
+            return;
+        };
+
+        let message = std::mem::take(&mut lobby_ui.chat_input);
+        let request: String = ClientPacket::Chat(message).into();
+        session.send.push(Bytes::from(request));
     });
 }
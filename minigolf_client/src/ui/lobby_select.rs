@@ -3,6 +3,7 @@ use {
     aeronet::io::{Session, bytes::Bytes},
     bevy::prelude::*,
     bevy_egui::{EguiContexts, egui},
+    core::time::Duration,
     minigolf::lobby::{LobbyId, user::ClientPacket},
 };
 
@@ -15,16 +16,72 @@ impl Plugin for LobbySelectUiPlugin {
 
         app.configure_sets(Update, LobbiesUiSet.run_if(in_state(ServerState::Lobbies)));
 
-        app.add_systems(Update, lobbies_ui.in_set(LobbiesUiSet));
+        app.add_systems(OnEnter(ServerState::Lobbies), request_lobbies);
+        app.add_systems(Update, (refresh_lobbies, lobbies_ui).in_set(LobbiesUiSet));
     }
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 struct LobbiesUiSet;
 
-#[derive(Resource, Reflect, Debug, Default)]
-struct LobbiesUi {
+/// How often the list of available lobbies is requested from the lobby server.
+const LOBBY_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Resource, Reflect, Debug)]
+pub(crate) struct LobbiesUi {
     lobby_id: String,
+    lobbies: Vec<LobbyId>,
+    refresh_timer: Timer,
+}
+
+impl Default for LobbiesUi {
+    fn default() -> Self {
+        LobbiesUi {
+            lobby_id: String::new(),
+            lobbies: Vec::new(),
+            refresh_timer: Timer::new(LOBBY_REFRESH_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+impl LobbiesUi {
+    pub(crate) fn set_available_lobbies(&mut self, lobbies: Vec<LobbyId>) {
+        self.lobbies = lobbies;
+    }
+
+    pub(crate) fn available_lobbies(&self) -> &[LobbyId] {
+        &self.lobbies
+    }
+}
+
+fn request_lobbies(
+    mut lobbies_ui: ResMut<LobbiesUi>,
+    mut lobby_session: Query<&mut Session, With<LobbyServerSession>>,
+) {
+    lobbies_ui.refresh_timer.reset();
+
+    if let Ok(mut session) = lobby_session.single_mut() {
+        list_lobbies(&mut session);
+    }
+}
+
+fn refresh_lobbies(
+    mut lobbies_ui: ResMut<LobbiesUi>,
+    mut lobby_session: Query<&mut Session, With<LobbyServerSession>>,
+    time: Res<Time>,
+) {
+    if !lobbies_ui.refresh_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if let Ok(mut session) = lobby_session.single_mut() {
+        list_lobbies(&mut session);
+    }
+}
+
+fn list_lobbies(session: &mut Session) {
+    let request: String = ClientPacket::ListLobbies.into();
+    session.send.push(Bytes::from(request));
 }
 
 fn lobbies_ui(
@@ -44,16 +101,51 @@ fn lobbies_ui(
 
                 info!("Joining lobby {}", lobbies_ui.lobby_id);
 
-                let mut session = lobby_session.single_mut().unwrap();
+                let Ok(mut session) = lobby_session.single_mut() else {
+                    warn!("Not connected to the lobby server");
+                    return;
+                };
+
                 let request: String = ClientPacket::JoinLobby(id).into();
                 session.send.push(Bytes::from(request));
             }
         });
+        ui.separator();
+
+        ui.label("Available lobbies:");
+        for lobby_id in lobbies_ui.available_lobbies() {
+            ui.horizontal(|ui| {
+                ui.label(lobby_id.to_string());
+
+                if ui.button("Join").clicked() {
+                    info!("Joining lobby {lobby_id}");
+
+                    let Ok(mut session) = lobby_session.single_mut() else {
+                        warn!("Not connected to the lobby server");
+                        return;
+                    };
+
+                    let request: String = ClientPacket::JoinLobby(*lobby_id).into();
+                    session.send.push(Bytes::from(request));
+                }
+            });
+        }
+
+        if lobbies_ui.available_lobbies().is_empty() {
+            ui.label("No lobbies available");
+        }
+
+        ui.separator();
+
         ui.horizontal(|ui| {
             if ui.button("Create lobby").clicked() {
                 info!("Creating lobby");
 
-                let mut session = lobby_session.single_mut().unwrap();
+                let Ok(mut session) = lobby_session.single_mut() else {
+                    warn!("Not connected to the lobby server");
+                    return;
+                };
+
                 let request: String = ClientPacket::CreateLobby.into();
                 session.send.push(Bytes::from(request));
             }
@@ -0,0 +1,51 @@
+use {
+    crate::{
+        input::{MenuState, MouseSensitivity},
+        ui::{ServerState, connection::leave_game, settings::sensitivity_settings},
+    },
+    aeronet_replicon::client::AeronetRepliconClient,
+    bevy::prelude::*,
+    bevy_egui::{EguiContexts, egui},
+};
+
+/// In-game menu opened with escape, which does not pause the game since it is simulated by the server.
+pub(crate) struct MenuUiPlugin;
+
+impl Plugin for MenuUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            menu_ui.run_if(in_state(ServerState::GameServer).and(in_state(MenuState::Open))),
+        );
+    }
+}
+
+fn menu_ui(
+    mut context: EguiContexts,
+    mut sensitivity: ResMut<MouseSensitivity>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    game_servers: Query<Entity, With<AeronetRepliconClient>>,
+    mut commands: Commands,
+) {
+    egui::Window::new("Menu")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .resizable(false)
+        .show(context.ctx_mut(), |ui| {
+            ui.label("The game continues while the menu is open");
+            ui.separator();
+
+            sensitivity_settings(ui, &mut sensitivity);
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Resume").clicked() {
+                    menu_state.set(MenuState::Closed);
+                }
+
+                if ui.button("Leave game").clicked() {
+                    leave_game(&game_servers, &mut commands);
+                }
+            });
+        });
+}
@@ -1,14 +1,21 @@
+mod connection;
 #[cfg(feature = "dev")]
 mod dev;
+pub(crate) mod disconnected;
+mod game_chat;
 pub(crate) mod lobby;
-mod lobby_select;
+pub(crate) mod lobby_select;
 pub(crate) mod lobby_server;
+mod menu;
 mod power_ups;
+mod settings;
 
 use {
     crate::ui::{
-        lobby::LobbyUiPlugin, lobby_select::LobbySelectUiPlugin, lobby_server::LobbyServerUiPlugin,
-        power_ups::PowerUpUiPlugin,
+        connection::ConnectionQualityUiPlugin, disconnected::DisconnectedUiPlugin,
+        game_chat::GameChatUiPlugin, lobby::LobbyUiPlugin, lobby_select::LobbySelectUiPlugin,
+        lobby_server::LobbyServerUiPlugin, menu::MenuUiPlugin, power_ups::PowerUpUiPlugin,
+        settings::SettingsUiPlugin,
     },
     bevy::prelude::*,
     bevy_egui::EguiPlugin,
@@ -33,6 +40,11 @@ impl Plugin for ClientUiPlugin {
             LobbySelectUiPlugin,
             LobbyUiPlugin,
             PowerUpUiPlugin,
+            DisconnectedUiPlugin,
+            GameChatUiPlugin,
+            ConnectionQualityUiPlugin,
+            SettingsUiPlugin,
+            MenuUiPlugin,
         ));
 
         app.init_state::<ServerState>();
@@ -1,8 +1,12 @@
 use {
-    crate::{LocalPlayer, input::InputTarget, ui::ServerState},
+    crate::{LocalPlayer, input::InputTarget, network::Authentication, ui::ServerState},
     bevy::prelude::*,
     bevy_egui::{EguiContexts, egui},
-    minigolf::{Player, PlayerInput, PlayerPowerUps, PlayerScore, PowerUpType::*},
+    minigolf::{
+        ActiveEffects, CurrentTurn, DisconnectedPlayer, GamePhase, HoleResult, HoleResults,
+        HoleTimer, Intermission, PinDistance, Player, PlayerInput, PlayerPowerUps, PlayerScore,
+        PowerUpNotImplemented, PowerUpType, PowerUpType::*, ScoringMode, default_player_name,
+    },
 };
 
 /// UI for displaying and interacting with power ups
@@ -10,32 +14,252 @@ pub(crate) struct PowerUpUiPlugin;
 
 impl Plugin for PowerUpUiPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<HoleResultsPopup>();
+        app.init_resource::<RefundNotice>();
+
+        app.add_systems(OnExit(ServerState::GameServer), close_hole_results);
+
         app.configure_sets(
             Update,
             PowerUpUiSet.run_if(in_state(ServerState::GameServer)),
         )
-        .add_systems(Update, (power_up_ui, score_board).in_set(PowerUpUiSet));
+        .add_systems(
+            Update,
+            (
+                power_up_ui,
+                shot_indicator,
+                score_board,
+                standings,
+                (receive_hole_results, hole_results).chain(),
+                (power_up_not_implemented, refund_notice).chain(),
+            )
+                .in_set(PowerUpUiSet),
+        );
     }
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 struct PowerUpUiSet;
 
-fn score_board(mut context: EguiContexts, scores: Query<(&Player, &PlayerScore)>) {
+/// Shows whether the local player can take a shot.
+fn shot_indicator(mut context: EguiContexts, player: Query<&Player, With<LocalPlayer>>) {
+    let Ok(player) = player.single() else {
+        return;
+    };
+
+    let (text, color) = shot_indicator_label(player);
+
+    egui::Area::new(egui::Id::new("Shot indicator"))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .show(context.ctx_mut(), |ui| {
+            ui.label(egui::RichText::new(text).heading().color(color));
+        });
+}
+
+fn shot_indicator_label(player: &Player) -> (&'static str, egui::Color32) {
+    match player.can_move {
+        true => ("Take your shot", egui::Color32::GREEN),
+        false => ("Cannot shoot right now", egui::Color32::GRAY),
+    }
+}
+
+fn score_board(
+    mut context: EguiContexts,
+    scores: Query<(&Player, &Name, &PlayerScore)>,
+    disconnected: Query<&Name, With<DisconnectedPlayer>>,
+    timer: Query<&HoleTimer>,
+    phase: Query<&GamePhase>,
+    turn: Query<&CurrentTurn>,
+    effects: Query<(&Player, &ActiveEffects)>,
+    scoring_mode: Query<&ScoringMode>,
+    authentication: Option<Res<Authentication>>,
+) {
+    let scoring_mode = scoring_mode.single().copied().unwrap_or_default();
+    let local_id = authentication.map(|authentication| authentication.id);
+
     egui::Window::new("Scoreboard").show(context.ctx_mut(), |ui| {
+        if let Ok(phase) = phase.single() {
+            ui.label(phase_description(phase));
+            ui.separator();
+        }
+
+        if let Ok(CurrentTurn(Some(player))) = turn.single() {
+            if local_id == Some(*player) {
+                ui.label("Your turn");
+            } else {
+                let name = scores
+                    .iter()
+                    .find(|(other, ..)| other.id == *player)
+                    .map_or_else(
+                        || default_player_name(*player),
+                        |(_, name, _)| name.to_string(),
+                    );
+                ui.label(format!("Turn of {name}"));
+            }
+            ui.separator();
+        }
+
+        // players progressing independently each have their own timer
+        let timer = timer
+            .iter()
+            .find(|timer| timer.player.is_none() || timer.player == local_id);
+        if let Some(timer) = timer {
+            ui.label(format!("Time remaining: {:.0}s", timer.remaining.ceil()));
+            ui.separator();
+        }
+
+        let effects = effects
+            .iter()
+            .find(|(player, _)| Some(player.id) == local_id)
+            .map(|(_, effects)| effects);
+        if let Some(ActiveEffects(effects)) = effects {
+            if !effects.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label("Active effects:");
+                    for effect in effects {
+                        ui.label(format!("{effect:?}"))
+                            .on_hover_text(effect.description());
+                    }
+                });
+                ui.separator();
+            }
+        }
+
         ui.vertical(|ui| {
-            for (player, score) in scores {
+            for (_, name, score) in scores {
                 ui.horizontal(|ui| {
-                    ui.label(format!("Player \"{:?}\": {:?}", player.id, score.score));
+                    let displayed_score = scoring_mode.displayed_score(score);
+                    ui.label(format!("{name}: {displayed_score}"));
                 });
             }
+
+            for name in &disconnected {
+                ui.label(egui::RichText::new(format!("{name} left")).color(egui::Color32::GRAY));
+            }
+        })
+    });
+}
+
+fn phase_description(phase: &GamePhase) -> &'static str {
+    match phase {
+        GamePhase::WaitingForPlayers => "Waiting for players",
+        GamePhase::Playing => "Playing",
+        GamePhase::HoleComplete => "Hole complete",
+        GamePhase::CourseComplete => "Course complete",
+        GamePhase::GameComplete => "Game complete",
+    }
+}
+
+fn standings(
+    mut context: EguiContexts,
+    scores: Query<(&Name, &PlayerScore, &PinDistance)>,
+    intermission: Query<&Intermission>,
+    phase: Query<&GamePhase>,
+    scoring_mode: Query<&ScoringMode>,
+) {
+    let intermission = intermission.single().ok();
+    let is_course_over = matches!(
+        phase.single(),
+        Ok(GamePhase::CourseComplete | GamePhase::GameComplete)
+    );
+
+    if intermission.is_none() && !is_course_over {
+        return;
+    }
+
+    let scoring_mode = scoring_mode.single().copied().unwrap_or_default();
+
+    // ties are broken by whoever got closest to the cup
+    let mut scores = scores.iter().collect::<Vec<_>>();
+    scores.sort_by(|(_, score, distance), (_, other_score, other_distance)| {
+        scoring_mode
+            .compare(score, other_score)
+            .then(distance.total.total_cmp(&other_distance.total))
+    });
+
+    egui::Window::new("Standings").show(context.ctx_mut(), |ui| {
+        if let Some(intermission) = intermission {
+            ui.label(format!(
+                "Next hole in {:.0}s",
+                intermission.remaining.ceil()
+            ));
+            ui.separator();
+        }
+
+        ui.vertical(|ui| {
+            for (place, (name, score, distance)) in scores.iter().enumerate() {
+                ui.label(format!(
+                    "{}. {name}: {:?} ({:.2}m from the pin)",
+                    place + 1,
+                    scoring_mode.displayed_score(score),
+                    distance.total
+                ));
+            }
         })
     });
 }
 
+/// Seconds for which the results of a completed hole are shown.
+const HOLE_RESULTS_DURATION: f32 = 5.0;
+
+/// Results of the last completed hole, shown briefly before the next hole starts.
+#[derive(Resource, Default, Debug)]
+struct HoleResultsPopup {
+    standings: Vec<HoleResult>,
+    remaining: f32,
+}
+
+fn receive_hole_results(mut reader: EventReader<HoleResults>, mut popup: ResMut<HoleResultsPopup>) {
+    for HoleResults { standings } in reader.read() {
+        popup.standings = standings.clone();
+        popup.remaining = HOLE_RESULTS_DURATION;
+    }
+}
+
+fn hole_results(
+    mut context: EguiContexts,
+    mut popup: ResMut<HoleResultsPopup>,
+    players: Query<(&Player, &Name)>,
+    time: Res<Time>,
+) {
+    if popup.remaining <= 0.0 {
+        return;
+    }
+
+    popup.remaining -= time.delta_secs();
+
+    egui::Window::new("Hole results")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .resizable(false)
+        .show(context.ctx_mut(), |ui| {
+            for (place, result) in popup.standings.iter().enumerate() {
+                let name = players
+                    .iter()
+                    .find(|(player, _)| player.id == result.player)
+                    .map_or_else(
+                        || default_player_name(result.player),
+                        |(_, name)| name.to_string(),
+                    );
+
+                ui.label(format!(
+                    "{}. {name}: {} strokes ({} total)",
+                    place + 1,
+                    result.strokes,
+                    result.total
+                ));
+            }
+        });
+}
+
+fn close_hole_results(mut popup: ResMut<HoleResultsPopup>) {
+    *popup = HoleResultsPopup::default();
+}
+
 fn power_up_ui(
     mut context: EguiContexts,
     player: Query<&PlayerPowerUps, With<LocalPlayer>>,
+    other_players: Query<(&Player, &Name), Without<LocalPlayer>>,
     mut writer: EventWriter<PlayerInput>,
     mut input_target: ResMut<NextState<InputTarget>>,
 ) {
@@ -45,9 +269,26 @@ fn power_up_ui(
 
     egui::Window::new("Power ups").show(context.ctx_mut(), |ui| {
         ui.vertical(|ui| {
-            for power_up_type in power_ups.get_power_ups() {
+            for power_up_type in power_ups
+                .get_power_ups()
+                .iter()
+                .filter(|power_up| power_up.is_implemented())
+            {
                 ui.horizontal(|ui| {
-                    ui.label(format!("{:?}", power_up_type));
+                    ui.label(format!("{:?}", power_up_type))
+                        .on_hover_text(power_up_type.description());
+
+                    if *power_up_type == StealPowerUp {
+                        ui.menu_button("Use", |ui| {
+                            for (other_player, name) in &other_players {
+                                if ui.button(name.as_str()).clicked() {
+                                    writer.write(PlayerInput::StealPowerUp(other_player.id));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                        return;
+                    }
 
                     if ui.button("Use").clicked() {
                         info!("Use power up {:?}", power_up_type);
@@ -97,3 +338,69 @@ fn power_up_ui(
         })
     });
 }
+
+/// Seconds for which a refunded power up is shown.
+const REFUND_NOTICE_DURATION: f32 = 3.0;
+
+/// Last power up that was refunded because it is not implemented yet.
+#[derive(Resource, Default, Debug)]
+struct RefundNotice {
+    power_up: Option<PowerUpType>,
+    remaining: f32,
+}
+
+fn power_up_not_implemented(
+    mut reader: EventReader<PowerUpNotImplemented>,
+    mut notice: ResMut<RefundNotice>,
+) {
+    for event in reader.read() {
+        warn!(
+            "Power up {:?} is not implemented yet, it was returned",
+            event.power_up
+        );
+
+        notice.power_up = Some(event.power_up);
+        notice.remaining = REFUND_NOTICE_DURATION;
+    }
+}
+
+fn refund_notice(mut context: EguiContexts, mut notice: ResMut<RefundNotice>, time: Res<Time>) {
+    let Some(power_up) = notice.power_up else {
+        return;
+    };
+
+    notice.remaining -= time.delta_secs();
+    if notice.remaining <= 0.0 {
+        notice.power_up = None;
+        return;
+    }
+
+    egui::Area::new(egui::Id::new("Refund notice"))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 50.0))
+        .show(context.ctx_mut(), |ui| {
+            let text = format!("{power_up:?} is not implemented yet, it was returned");
+            ui.label(egui::RichText::new(text).color(egui::Color32::YELLOW));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shot_indicator_reflects_whether_player_can_move() {
+        let mut player = Player::new();
+
+        player.can_move = true;
+        assert_eq!(
+            shot_indicator_label(&player),
+            ("Take your shot", egui::Color32::GREEN)
+        );
+
+        player.can_move = false;
+        assert_eq!(
+            shot_indicator_label(&player),
+            ("Cannot shoot right now", egui::Color32::GRAY)
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use {
+    crate::input::MouseSensitivity,
+    bevy::prelude::*,
+    bevy_egui::{EguiContexts, egui},
+};
+
+/// UI for adjusting client settings.
+pub(crate) struct SettingsUiPlugin;
+
+impl Plugin for SettingsUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, settings_ui);
+    }
+}
+
+fn settings_ui(mut context: EguiContexts, mut sensitivity: ResMut<MouseSensitivity>) {
+    egui::Window::new("Settings")
+        .default_open(false)
+        .show(context.ctx_mut(), |ui| {
+            sensitivity_settings(ui, &mut sensitivity)
+        });
+}
+
+/// Adds the controls for adjusting the mouse sensitivity.
+pub(crate) fn sensitivity_settings(ui: &mut egui::Ui, sensitivity: &mut MouseSensitivity) {
+    ui.add(egui::Slider::new(&mut sensitivity.aim, 0.1..=5.0).text("Aim sensitivity"));
+    ui.add(egui::Slider::new(&mut sensitivity.camera, 0.1..=5.0).text("Camera sensitivity"));
+}
@@ -1,5 +1,5 @@
 use {
-    crate::Args,
+    crate::{Args, DisplayName, Handicap, LobbyIndex},
     aeronet::io::{Session, bytes::Bytes, connection::LocalAddr, server::Server},
     aeronet_websocket::server::{ServerConfig, WebSocketServer},
     bevy::prelude::*,
@@ -7,7 +7,7 @@ use {
         lobby::{
             LobbyId,
             game::{ClientPacket, CreateGameRequest, ServerPacket},
-            user::LobbyMember,
+            user::{LobbyMember, ServerPacket as UserPacket},
         },
         {Player, PlayerCredentials},
     },
@@ -175,21 +175,52 @@ impl From<&LobbyMember> for StartGame {
 fn on_start_game(
     trigger: Trigger<StartGame>,
     mut servers: Query<&mut Session, With<GameServer>>,
-    lobby_players: Query<(&LobbyMember, &Player, &PlayerCredentials)>,
+    mut lobby_players: Query<
+        (
+            &Player,
+            &mut PlayerCredentials,
+            &mut Session,
+            Option<&Handicap>,
+            Option<&DisplayName>,
+        ),
+        Without<GameServer>,
+    >,
+    index: Res<LobbyIndex>,
 ) {
     let lobby_id = trigger.lobby_id;
 
+    // credentials expire after some time on the game server, so issue new ones for each game
+    let mut members = lobby_players.iter_many_mut(index.members(lobby_id));
+    while let Some((player, mut credentials, mut session, ..)) = members.fetch_next() {
+        *credentials = PlayerCredentials::default();
+
+        let message: String = UserPacket::Hello(player.id, credentials.clone()).into();
+        session.send.push(Bytes::from_owner(message));
+    }
+
     for mut server in &mut servers {
         let players = lobby_players
-            .iter()
-            .filter(|(member, _, _)| member.lobby_id == lobby_id)
-            .map(|(_, player, credentials)| (player.id, credentials.clone()))
+            .iter_many(index.members(lobby_id))
+            .map(|(player, credentials, ..)| (player.id, credentials.clone()))
+            .collect();
+
+        let handicaps = lobby_players
+            .iter_many(index.members(lobby_id))
+            .filter_map(|(player, _, _, handicap, _)| handicap.map(|h| (player.id, h.0)))
+            .collect();
+
+        let names = lobby_players
+            .iter_many(index.members(lobby_id))
+            .filter_map(|(player, .., name)| name.map(|n| (player.id, n.0.clone())))
             .collect();
 
         let request = CreateGameRequest {
             lobby_id,
             players,
             courses: vec!["0002".to_owned(), "0002".to_owned()],
+            handicaps,
+            names,
+            enabled_power_ups: None,
         };
 
         let message: String = ServerPacket::CreateGame(request).into();
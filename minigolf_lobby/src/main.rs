@@ -4,29 +4,37 @@ mod user;
 use {
     crate::{game::GameServerPlugin, user::UserPlugin},
     aeronet_websocket::server::WebSocketServerPlugin,
-    bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*},
+    bevy::{app::ScheduleRunnerPlugin, ecs::entity::EntityHashSet, log::LogPlugin, prelude::*},
     core::time::Duration,
     minigolf::{
         Player,
-        lobby::user::{LobbyMember, PlayerInLobby},
+        lobby::{
+            LobbyId,
+            user::{LobbyMember, PlayerInLobby},
+        },
+        shutdown::ShutdownPlugin,
+    },
+    std::{
+        collections::HashMap,
+        net::{IpAddr, Ipv6Addr, SocketAddr},
     },
-    std::net::{IpAddr, Ipv6Addr, SocketAddr},
 };
 
 const TICK_RATE: f64 = 32.0;
 
 fn main() -> AppExit {
+    let args = <Args as clap::Parser>::parse();
+
     App::new()
-        .init_resource::<Args>()
         .add_plugins(LogPlugin::default())
-        .add_plugins(
-            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
-                1.0 / TICK_RATE,
-            ))),
-        )
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(args.tick_duration())))
         .add_plugins(WebSocketServerPlugin)
-        .add_plugins((GameServerPlugin, UserPlugin))
-        .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+        .add_plugins((GameServerPlugin, UserPlugin, ShutdownPlugin))
+        .insert_resource(Time::<Fixed>::from_hz(args.tick_rate))
+        .insert_resource(args)
+        .init_resource::<LobbyIndex>()
+        .add_observer(on_lobby_member_inserted)
+        .add_observer(on_lobby_member_replaced)
         .add_observer(on_lobby_member_removed)
         .run()
 }
@@ -44,11 +52,28 @@ struct Args {
     /// Address to listen on for game servers
     #[arg(long, default_value_t = GAME_ADDRESS)]
     game_address: SocketAddr,
+
+    /// Number of updates per second
+    #[arg(long, default_value_t = TICK_RATE, value_parser = parse_tick_rate)]
+    tick_rate: f64,
+
+    /// Number of ready players at which a lobby starts the game without waiting for the owner
+    #[arg(long)]
+    auto_start_players: Option<usize>,
+}
+
+impl Args {
+    /// Gets the time between updates.
+    fn tick_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.tick_rate)
+    }
 }
 
-impl FromWorld for Args {
-    fn from_world(_: &mut World) -> Self {
-        <Self as clap::Parser>::parse()
+fn parse_tick_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        Ok(rate) => Err(format!("{rate} is not a positive number")),
+        Err(error) => Err(error.to_string()),
     }
 }
 
@@ -63,6 +88,105 @@ impl Lobby {
     }
 }
 
+/// Strokes subtracted from the score of a lobby member, set by the owner of the lobby.
+#[derive(Debug, Component, Reflect)]
+struct Handicap(u32);
+
+/// Marks a lobby member that is ready to start the game.
+#[derive(Debug, Component, Reflect)]
+struct Ready;
+
+/// Name chosen by the player, shown to the other players in games.
+#[derive(Debug, Component, Reflect)]
+struct DisplayName(String);
+
+/// Lobby and member entities grouped by [LobbyId], kept in sync with [LobbyMember] by observers.
+#[derive(Resource, Default, Debug)]
+struct LobbyIndex {
+    lobbies: HashMap<LobbyId, LobbyEntities>,
+}
+
+#[derive(Default, Debug)]
+struct LobbyEntities {
+    lobby: Option<Entity>,
+    members: EntityHashSet,
+}
+
+impl LobbyIndex {
+    /// Gets the entity with the [Lobby] component for the specified lobby.
+    fn lobby(&self, id: LobbyId) -> Option<Entity> {
+        self.lobbies.get(&id).and_then(|entities| entities.lobby)
+    }
+
+    /// Gets all member entities (excluding the [Lobby] entity) of the specified lobby.
+    fn members(&self, id: LobbyId) -> impl Iterator<Item = Entity> + '_ {
+        self.lobbies
+            .get(&id)
+            .into_iter()
+            .flat_map(|entities| entities.members.iter().copied())
+    }
+
+    /// Gets the ids of all lobbies that currently exist.
+    fn lobby_ids(&self) -> impl Iterator<Item = LobbyId> + '_ {
+        self.lobbies
+            .iter()
+            .filter(|(_, entities)| entities.lobby.is_some())
+            .map(|(id, _)| *id)
+    }
+
+    fn insert(&mut self, id: LobbyId, entity: Entity, is_lobby: bool) {
+        let entities = self.lobbies.entry(id).or_default();
+
+        if is_lobby {
+            entities.lobby = Some(entity);
+        } else {
+            entities.members.insert(entity);
+        }
+    }
+
+    fn remove(&mut self, id: LobbyId, entity: Entity) {
+        let Some(entities) = self.lobbies.get_mut(&id) else {
+            return;
+        };
+
+        if entities.lobby == Some(entity) {
+            entities.lobby = None;
+        }
+
+        entities.members.remove(&entity);
+
+        if entities.lobby.is_none() && entities.members.is_empty() {
+            self.lobbies.remove(&id);
+        }
+    }
+}
+
+fn on_lobby_member_inserted(
+    trigger: Trigger<OnInsert, LobbyMember>,
+    members: Query<(&LobbyMember, Has<Lobby>)>,
+    mut index: ResMut<LobbyIndex>,
+) {
+    let entity = trigger.target();
+    let Ok((member, is_lobby)) = members.get(entity) else {
+        return;
+    };
+
+    index.insert(member.lobby_id, entity, is_lobby);
+}
+
+fn on_lobby_member_replaced(
+    trigger: Trigger<OnReplace, LobbyMember>,
+    members: Query<&LobbyMember>,
+    mut index: ResMut<LobbyIndex>,
+) {
+    let entity = trigger.target();
+    let Ok(member) = members.get(entity) else {
+        return;
+    };
+
+    index.remove(member.lobby_id, entity);
+}
+
 #[derive(Event, Reflect, Deref, DerefMut, Debug)]
 struct PlayerJoinedLobby(PlayerInLobby);
 
@@ -71,13 +195,13 @@ struct PlayerDisconnected(PlayerInLobby);
 
 fn on_lobby_member_removed(
     trigger: Trigger<OnRemove, LobbyMember>,
-    members: Query<(Entity, &LobbyMember), Without<Lobby>>,
-    lobby: Query<(Entity, &LobbyMember), With<Lobby>>,
+    members: Query<&LobbyMember, Without<Lobby>>,
     players: Query<&Player>,
+    index: Res<LobbyIndex>,
     mut commands: Commands,
 ) {
     let entity = trigger.target();
-    let Ok((_, lobby_member)) = members.get(entity) else {
+    let Ok(lobby_member) = members.get(entity) else {
         return;
     };
 
@@ -88,20 +212,12 @@ fn on_lobby_member_removed(
         commands.trigger(PlayerDisconnected(PlayerInLobby::new(id, player.id)));
     }
 
-    let Some(lobby_entity) = lobby
-        .iter()
-        .filter(|(_, l)| l.lobby_id == id)
-        .map(|(entity, _)| entity)
-        .next()
-    else {
+    let Some(lobby_entity) = index.lobby(id) else {
         info!("No lobby found with id {:?}", id);
         return;
     };
 
-    let member_count = members
-        .iter()
-        .filter(|(e, m)| *e != entity && m.lobby_id == id)
-        .count();
+    let member_count = index.members(id).filter(|e| *e != entity).count();
 
     info!("{:?} members remaining in lobby {:?}", member_count, id);
 
@@ -110,3 +226,41 @@ fn on_lobby_member_removed(
         commands.entity(lobby_entity).despawn();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn members_moving_between_lobbies_are_reindexed() {
+        let mut world = World::new();
+        world.init_resource::<LobbyIndex>();
+        world.add_observer(on_lobby_member_inserted);
+        world.add_observer(on_lobby_member_replaced);
+
+        let member = world.spawn(LobbyMember::from(1)).id();
+        let lobby = world.spawn((Lobby::new(member), LobbyMember::from(1))).id();
+
+        let index = world.resource::<LobbyIndex>();
+        assert_eq!(index.lobby(1), Some(lobby));
+        assert_eq!(index.members(1).collect::<Vec<_>>(), vec![member]);
+
+        world.entity_mut(member).insert(LobbyMember::from(2));
+
+        let index = world.resource::<LobbyIndex>();
+        assert_eq!(index.members(1).count(), 0);
+        assert_eq!(index.members(2).collect::<Vec<_>>(), vec![member]);
+        assert_eq!(index.lobby(2), None);
+        assert_eq!(index.lobby_ids().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn tick_rate_must_be_positive() {
+        assert_eq!(parse_tick_rate("32"), Ok(32.0));
+        assert!(parse_tick_rate("0").is_err());
+        assert!(parse_tick_rate("-1").is_err());
+        assert!(parse_tick_rate("NaN").is_err());
+        assert!(parse_tick_rate("inf").is_err());
+        assert!(parse_tick_rate("fast").is_err());
+    }
+}
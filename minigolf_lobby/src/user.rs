@@ -1,14 +1,24 @@
 use {
     crate::{
-        Args, Lobby, PlayerDisconnected, PlayerJoinedLobby,
+        Args, DisplayName, Handicap, Lobby, LobbyIndex, PlayerDisconnected, PlayerJoinedLobby,
+        Ready,
         game::{GameStarted, StartGame},
     },
-    aeronet::io::{Session, bytes::Bytes, connection::LocalAddr, server::Server},
+    aeronet::io::{
+        Session,
+        bytes::Bytes,
+        connection::{Disconnected, LocalAddr},
+        server::Server,
+    },
     aeronet_websocket::server::{ServerConfig, WebSocketServer},
     bevy::{ecs::component::ComponentInfo, prelude::*},
     minigolf::{
         Player, PlayerCredentials,
-        lobby::user::{ClientPacket, LobbyMember, PlayerInLobby, ServerPacket},
+        lobby::{
+            LobbyId, PlayerId,
+            user::{ClientPacket, LobbyMember, PlayerInLobby, ServerPacket},
+        },
+        sanitize_chat_message, sanitize_player_name,
     },
     std::ops::RangeFull,
 };
@@ -22,10 +32,15 @@ impl Plugin for UserPlugin {
 
         app.add_observer(on_opened);
         app.add_observer(on_connected);
+        app.add_observer(on_disconnected);
         app.add_observer(on_lobby_id_added);
 
         app.add_observer(on_player_joined_lobby);
         app.add_observer(on_player_disconnected);
+        app.add_observer(on_chat_message_sent);
+        app.add_observer(on_handicap_set);
+        app.add_observer(on_player_ready);
+        app.add_observer(clear_ready);
 
         app.add_systems(Update, (handle_messages, game_started));
     }
@@ -97,7 +112,9 @@ fn handle_messages(
     mut sessions: Query<(Entity, &mut Session), With<UserSession>>,
     known_players: Query<(&Player, &PlayerCredentials)>,
     members: Query<&LobbyMember>,
-    lobby_players: Query<(&Player, &LobbyMember)>,
+    players: Query<&Player>,
+    lobbies: Query<&Lobby>,
+    index: Res<LobbyIndex>,
     mut commands: Commands,
 ) {
     for (user_session, mut session) in &mut sessions {
@@ -140,29 +157,31 @@ fn handle_messages(
                     session.send.push(Bytes::from_owner(message));
 
                     commands.entity(lobby).insert(lobby_member);
-                    commands.entity(user_session).insert(lobby_member);
+                    commands
+                        .entity(user_session)
+                        .remove::<(Ready, Handicap)>()
+                        .insert(lobby_member);
                 }
 
                 ClientPacket::JoinLobby(id) => {
-                    let current_members = lobby_players
-                        .iter()
-                        .filter(|(_, l)| l.lobby_id == id)
-                        .map(|(p, _)| p.id)
+                    let current_members = players
+                        .iter_many(index.members(id))
+                        .map(|p| p.id)
                         .collect::<Vec<_>>();
 
                     let message: String = ServerPacket::LobbyJoined(id, current_members).into();
                     session.send.push(Bytes::from_owner(message));
 
                     let (player, _) = known_players.get(user_session).unwrap();
-                    commands.entity(user_session).insert(LobbyMember::from(id));
+                    commands
+                        .entity(user_session)
+                        .remove::<(Ready, Handicap)>()
+                        .insert(LobbyMember::from(id));
                     commands.trigger(PlayerJoinedLobby(PlayerInLobby::new(id, player.id)));
                 }
 
                 ClientPacket::ListLobbies => {
-                    let ids = members
-                        .iter()
-                        .map(|member| member.lobby_id)
-                        .collect::<Vec<_>>();
+                    let ids = index.lobby_ids().collect::<Vec<_>>();
                     let response: String = ServerPacket::AvailableLobbies(ids).into();
                     session.send.push(Bytes::from_owner(response));
                 }
@@ -173,13 +192,155 @@ fn handle_messages(
                 }
 
                 ClientPacket::LeaveLobby => {
-                    commands.entity(user_session).remove::<LobbyMember>();
+                    commands
+                        .entity(user_session)
+                        .remove::<(LobbyMember, Ready, Handicap)>();
+                }
+
+                ClientPacket::Chat(message) => {
+                    let (Ok(member), Ok((player, _))) =
+                        (members.get(user_session), known_players.get(user_session))
+                    else {
+                        warn!("{user_session} sent a chat message while not in a lobby");
+                        continue;
+                    };
+
+                    let Some(message) = sanitize_chat_message(&message) else {
+                        continue;
+                    };
+
+                    commands.trigger(ChatMessageSent {
+                        lobby_id: member.lobby_id,
+                        player_id: player.id,
+                        message,
+                    });
+                }
+
+                ClientPacket::SetHandicap(player_id, handicap) => {
+                    let Ok(member) = members.get(user_session) else {
+                        warn!("{user_session} set a handicap while not in a lobby");
+                        continue;
+                    };
+
+                    let is_owner = index
+                        .lobby(member.lobby_id)
+                        .and_then(|lobby| lobbies.get(lobby).ok())
+                        .is_some_and(|lobby| lobby.owner == user_session);
+
+                    if !is_owner {
+                        warn!("{user_session} set a handicap without owning the lobby");
+                        continue;
+                    }
+
+                    let Some(target) = index
+                        .members(member.lobby_id)
+                        .find(|entity| players.get(*entity).is_ok_and(|p| p.id == player_id))
+                    else {
+                        warn!("Player {player_id:?} is not in lobby {}", member.lobby_id);
+                        continue;
+                    };
+
+                    commands.entity(target).insert(Handicap(handicap));
+                    commands.trigger(HandicapSet {
+                        lobby_id: member.lobby_id,
+                        owner: user_session,
+                        player_id,
+                        handicap,
+                    });
+                }
+
+                ClientPacket::SetName(name) => {
+                    let Some(name) = sanitize_player_name(&name) else {
+                        warn!("{user_session} set an empty name");
+                        continue;
+                    };
+
+                    info!("{user_session} set their name to {name:?}");
+                    commands.entity(user_session).insert(DisplayName(name));
+                }
+
+                ClientPacket::SetReady(ready) => {
+                    if members.get(user_session).is_err() {
+                        warn!("{user_session} set their readiness while not in a lobby");
+                        continue;
+                    }
+
+                    if ready {
+                        commands.entity(user_session).insert(Ready);
+                    } else {
+                        commands.entity(user_session).remove::<Ready>();
+                    }
                 }
             };
         }
     }
 }
 
+/// Starts the game once the configured number of lobby members are ready.
+fn on_player_ready(
+    trigger: Trigger<OnAdd, Ready>,
+    members: Query<&LobbyMember>,
+    ready: Query<Entity, With<Ready>>,
+    index: Res<LobbyIndex>,
+    args: Res<Args>,
+    mut commands: Commands,
+) {
+    let Some(auto_start_players) = args.auto_start_players else {
+        return;
+    };
+
+    let Ok(member) = members.get(trigger.target()) else {
+        return;
+    };
+
+    let ready_members = ready.iter_many(index.members(member.lobby_id)).count();
+    if ready_members < auto_start_players {
+        return;
+    }
+
+    info!(
+        "{ready_members} players are ready in lobby {:?}, starting game",
+        member.lobby_id
+    );
+
+    commands.trigger(StartGame::from(member));
+}
+
+/// Clears the readiness of all lobby members once the game is started, however it was started.
+fn clear_ready(
+    trigger: Trigger<StartGame>,
+    mut sessions: Query<(Entity, &mut Session), (With<UserSession>, With<Ready>)>,
+    index: Res<LobbyIndex>,
+    mut commands: Commands,
+) {
+    let mut members = sessions.iter_many_mut(index.members(trigger.lobby_id));
+    while let Some((member, mut session)) = members.fetch_next() {
+        commands.entity(member).remove::<Ready>();
+
+        let response: String = ServerPacket::ReadyCleared.into();
+        session.send.push(Bytes::from_owner(response));
+    }
+}
+
+/// Leaves the lobby explicitly, so that the lobby is cleaned up the same way as with [ClientPacket::LeaveLobby].
+fn on_disconnected(
+    trigger: Trigger<Disconnected>,
+    members: Query<&LobbyMember, With<UserSession>>,
+    mut commands: Commands,
+) {
+    let session = trigger.target();
+    let Ok(member) = members.get(session) else {
+        return;
+    };
+
+    info!(
+        "User {session} disconnected from lobby {:?}: {:?}",
+        member.lobby_id,
+        trigger.event()
+    );
+    commands.entity(session).try_remove::<LobbyMember>();
+}
+
 fn on_lobby_id_added(
     trigger: Trigger<OnAdd, LobbyMember>,
     world: &World,
@@ -216,14 +377,12 @@ fn on_lobby_id_added(
 
 fn game_started(
     mut game_started_reader: EventReader<GameStarted>,
-    mut members: Query<(&LobbyMember, &mut Session), With<UserSession>>,
+    mut sessions: Query<&mut Session, With<UserSession>>,
+    index: Res<LobbyIndex>,
 ) {
     for game_started in &mut game_started_reader.read() {
-        for (id, mut session) in &mut members {
-            if id.lobby_id != game_started.lobby_id {
-                continue;
-            }
-
+        let mut members = sessions.iter_many_mut(index.members(game_started.lobby_id));
+        while let Some(mut session) = members.fetch_next() {
             let message: String = ServerPacket::GameStarted(game_started.server.clone()).into();
             session.send.push(Bytes::from_owner(message));
         }
@@ -232,14 +391,12 @@ fn game_started(
 
 fn on_player_joined_lobby(
     trigger: Trigger<PlayerJoinedLobby>,
-    mut sessions: Query<(&LobbyMember, &mut Session), With<UserSession>>,
+    mut sessions: Query<&mut Session, With<UserSession>>,
+    index: Res<LobbyIndex>,
 ) {
     let player = trigger.event();
-    for (member, mut session) in &mut sessions {
-        if member.lobby_id != player.lobby_id {
-            continue;
-        }
-
+    let mut members = sessions.iter_many_mut(index.members(player.lobby_id));
+    while let Some(mut session) = members.fetch_next() {
         let response: String = ServerPacket::PlayerJoined(player.0).into();
         session.send.push(Bytes::from_owner(response));
     }
@@ -247,15 +404,154 @@ fn on_player_joined_lobby(
 
 fn on_player_disconnected(
     trigger: Trigger<PlayerDisconnected>,
-    mut sessions: Query<(&LobbyMember, &mut Session), With<UserSession>>,
+    mut sessions: Query<&mut Session, With<UserSession>>,
+    index: Res<LobbyIndex>,
 ) {
     let player = trigger.event();
-    for (member, mut session) in &mut sessions {
-        if member.lobby_id != player.lobby_id {
+    let mut members = sessions.iter_many_mut(index.members(player.lobby_id));
+    while let Some(mut session) = members.fetch_next() {
+        let response: String = ServerPacket::PlayerLeft(player.0).into();
+        session.send.push(Bytes::from_owner(response));
+    }
+}
+
+#[derive(Event, Debug)]
+struct ChatMessageSent {
+    lobby_id: LobbyId,
+    player_id: PlayerId,
+    message: String,
+}
+
+fn on_chat_message_sent(
+    trigger: Trigger<ChatMessageSent>,
+    mut sessions: Query<&mut Session, With<UserSession>>,
+    index: Res<LobbyIndex>,
+) {
+    let chat = trigger.event();
+    let mut members = sessions.iter_many_mut(index.members(chat.lobby_id));
+    while let Some(mut session) = members.fetch_next() {
+        let response: String = ServerPacket::Chat(chat.player_id, chat.message.clone()).into();
+        session.send.push(Bytes::from_owner(response));
+    }
+}
+
+#[derive(Event, Debug)]
+struct HandicapSet {
+    lobby_id: LobbyId,
+    owner: Entity,
+    player_id: PlayerId,
+    handicap: u32,
+}
+
+/// Shows the handicap to the other members, the owner already knows it.
+fn on_handicap_set(
+    trigger: Trigger<HandicapSet>,
+    mut sessions: Query<(Entity, &mut Session), With<UserSession>>,
+    index: Res<LobbyIndex>,
+) {
+    let handicap = trigger.event();
+    let mut members = sessions.iter_many_mut(index.members(handicap.lobby_id));
+    while let Some((member, mut session)) = members.fetch_next() {
+        if member == handicap.owner {
             continue;
         }
 
-        let response: String = ServerPacket::PlayerLeft(player.0).into();
+        let response: String =
+            ServerPacket::HandicapSet(handicap.player_id, handicap.handicap).into();
         session.send.push(Bytes::from_owner(response));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            GAME_ADDRESS, TICK_RATE, USER_ADDRESS, on_lobby_member_inserted,
+            on_lobby_member_removed, on_lobby_member_replaced,
+        },
+        std::time::Instant,
+    };
+
+    #[derive(Resource, Default)]
+    struct StartedGames(usize);
+
+    #[test]
+    fn game_starts_once_enough_members_are_ready() {
+        let mut world = World::new();
+        world.insert_resource(Args {
+            user_address: USER_ADDRESS,
+            game_address: GAME_ADDRESS,
+            tick_rate: TICK_RATE,
+            auto_start_players: Some(2),
+        });
+        world.init_resource::<LobbyIndex>();
+        world.init_resource::<StartedGames>();
+        world.add_observer(on_lobby_member_inserted);
+        world.add_observer(on_player_ready);
+        world.add_observer(|_: Trigger<StartGame>, mut started: ResMut<StartedGames>| {
+            started.0 += 1;
+        });
+
+        let first = world.spawn(LobbyMember::from(1)).id();
+        let second = world.spawn(LobbyMember::from(1)).id();
+        world.spawn((LobbyMember::from(2), Ready));
+
+        world.entity_mut(first).insert(Ready);
+        world.flush();
+        assert_eq!(world.resource::<StartedGames>().0, 0);
+
+        world.entity_mut(second).insert(Ready);
+        world.flush();
+        assert_eq!(world.resource::<StartedGames>().0, 1);
+    }
+
+    #[test]
+    fn disconnected_members_leave_their_lobby() {
+        let mut world = World::new();
+        world.init_resource::<LobbyIndex>();
+        world.add_observer(on_lobby_member_inserted);
+        world.add_observer(on_lobby_member_replaced);
+        world.add_observer(on_lobby_member_removed);
+        world.add_observer(on_disconnected);
+
+        let member = world.spawn((UserSession, LobbyMember::from(1))).id();
+        let lobby = world.spawn((Lobby::new(member), LobbyMember::from(1))).id();
+
+        world.trigger_targets(Disconnected::ByPeer("connection lost".to_owned()), member);
+        world.flush();
+
+        assert!(world.get::<LobbyMember>(member).is_none());
+        assert!(world.get_entity(lobby).is_err());
+        assert_eq!(world.resource::<LobbyIndex>().lobby_ids().count(), 0);
+    }
+
+    #[test]
+    fn chat_messages_are_relayed_to_lobby_members() {
+        let mut world = World::new();
+        world.init_resource::<LobbyIndex>();
+        world.add_observer(on_lobby_member_inserted);
+        world.add_observer(on_chat_message_sent);
+
+        let session = || (UserSession, Session::new(Instant::now(), 1200));
+        let sender = world.spawn((session(), LobbyMember::from(1))).id();
+        let member = world.spawn((session(), LobbyMember::from(1))).id();
+        let other_lobby = world.spawn((session(), LobbyMember::from(2))).id();
+        world.flush();
+
+        let player_id = Player::new().id;
+        world.trigger(ChatMessageSent {
+            lobby_id: 1,
+            player_id,
+            message: "Hello".to_owned(),
+        });
+        world.flush();
+
+        let expected: String = ServerPacket::Chat(player_id, "Hello".to_owned()).into();
+        for entity in [sender, member] {
+            let session = world.get::<Session>(entity).unwrap();
+            assert_eq!(session.send, vec![Bytes::from(expected.clone())]);
+        }
+        assert!(world.get::<Session>(other_lobby).unwrap().send.is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use {
+    crate::{PlayerSession, ServerState},
+    bevy::prelude::*,
+    bevy_replicon::prelude::*,
+    core::time::Duration,
+    minigolf::{ChatMessage, Player, SendChat, sanitize_chat_message},
+};
+
+/// Relays chat messages and emotes between players in a game.
+pub(crate) struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_required_components::<PlayerSession, ChatRateLimit>();
+
+        app.add_systems(
+            Update,
+            relay_chat_messages.run_if(in_state(ServerState::Playing)),
+        );
+    }
+}
+
+/// Minimum time between two chat messages from the same player.
+const CHAT_MESSAGE_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Component, Default, Debug)]
+struct ChatRateLimit {
+    last_message: Option<Duration>,
+}
+
+fn relay_chat_messages(
+    mut reader: EventReader<FromClient<SendChat>>,
+    mut sessions: Query<(&PlayerSession, &mut ChatRateLimit)>,
+    players: Query<&Player>,
+    time: Res<Time<Real>>,
+    mut writer: EventWriter<ToClients<ChatMessage>>,
+) {
+    for &FromClient {
+        client_entity,
+        event: ref chat,
+    } in reader.read()
+    {
+        let Ok((session, mut rate_limit)) = sessions.get_mut(client_entity) else {
+            warn!("Received chat message from {client_entity} without a session");
+            continue;
+        };
+
+        let now = time.elapsed();
+        if let Some(last_message) = rate_limit.last_message {
+            if now - last_message < CHAT_MESSAGE_INTERVAL {
+                warn!(
+                    "Dropping chat message from {client_entity}, sent too soon after the last one"
+                );
+                continue;
+            }
+        }
+
+        let Some(message) = sanitize_chat_message(&chat.0) else {
+            continue;
+        };
+
+        let Ok(player) = players.get(session.player) else {
+            continue;
+        };
+
+        rate_limit.last_message = Some(now);
+        writer.write(ToClients {
+            mode: SendMode::Broadcast,
+            event: ChatMessage {
+                player: player.id,
+                message,
+            },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, bevy::ecs::system::RunSystemOnce};
+
+    #[test]
+    fn chat_messages_are_broadcast_to_all_players() {
+        let mut world = World::new();
+        world.init_resource::<Time<Real>>();
+        world.init_resource::<Events<FromClient<SendChat>>>();
+        world.init_resource::<Events<ToClients<ChatMessage>>>();
+
+        let player = Player::new();
+        let player_id = player.id;
+        let player = world.spawn(player).id();
+        let session = world
+            .spawn((PlayerSession { player }, ChatRateLimit::default()))
+            .id();
+
+        for message in ["  Nice shot!  ", "sent too soon"] {
+            world.send_event(FromClient {
+                client_entity: session,
+                event: SendChat(message.to_owned()),
+            });
+        }
+        world.run_system_once(relay_chat_messages).unwrap();
+
+        let events = world.resource::<Events<ToClients<ChatMessage>>>();
+        let sent = events.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(sent.len(), 1);
+        assert!(matches!(sent[0].mode, SendMode::Broadcast));
+        assert_eq!(sent[0].event.player, player_id);
+        assert_eq!(sent[0].event.message, "Nice shot!");
+    }
+}
@@ -1,9 +1,11 @@
 use {
     crate::{
-        Configuration, CourseState, GameState, HoleState, ServerState, config::ServerPlugin,
-        course::setup::CourseConfiguration,
+        Configuration, CourseState, GameState, HoleState, ServerState,
+        config::ServerPlugin,
+        course::{PhysicsConfig, setup::CourseConfiguration},
     },
     bevy::{
+        app::TerminalCtrlCHandlerPlugin,
         asset::{ReflectAsset, UntypedAssetId},
         ecs::system::RunSystemOnce,
         math::{DQuat, DVec3},
@@ -29,7 +31,11 @@ use {
 
 impl Plugin for ServerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(DefaultPlugins);
+        app.add_plugins(
+            DefaultPlugins
+                .build()
+                .disable::<TerminalCtrlCHandlerPlugin>(),
+        );
 
         app.add_plugins(EguiPlugin {
             enable_multipass_for_primary_context: false,
@@ -449,6 +455,7 @@ fn save_configuration(world: &mut World) {
     let scene = DynamicSceneBuilder::from_world(world)
         .deny_all_resources()
         .allow_resource::<Configuration>()
+        .allow_resource::<PhysicsConfig>()
         .extract_resources()
         .build();
 
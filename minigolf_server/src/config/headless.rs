@@ -1,7 +1,7 @@
 use {
     crate::config::ServerPlugin,
     bevy::{
-        app::ScheduleRunnerPlugin,
+        app::{ScheduleRunnerPlugin, TerminalCtrlCHandlerPlugin},
         prelude::*,
         render::{RenderPlugin, settings::WgpuSettings},
         winit::WinitPlugin,
@@ -22,7 +22,8 @@ impl Plugin for ServerPlugin {
                     .into(),
                     ..default()
                 })
-                .disable::<WinitPlugin>(),
+                .disable::<WinitPlugin>()
+                .disable::<TerminalCtrlCHandlerPlugin>(),
         )
         .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
             1.0 / f64::from(TICK_RATE),
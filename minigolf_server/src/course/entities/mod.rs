@@ -1,11 +1,15 @@
 use {
-    crate::{Configuration, GameLayer, PlayingSystems, ServerState},
+    crate::{
+        Configuration, GameLayer, LastPlayerPosition, PlayingSystems, ServerState,
+        course::HoleStrokes,
+    },
     avian3d::{
         math::{Scalar, Vector},
         prelude::*,
     },
     bevy::{app::App, ecs::entity::EntityHashSet, prelude::*},
-    minigolf::Player,
+    minigolf::{HazardZone, Player, PlayerScore, RemainingHits},
+    std::collections::HashMap,
 };
 
 pub(crate) struct CourseEntitiesPlugin;
@@ -15,13 +19,27 @@ impl Plugin for CourseEntitiesPlugin {
         app.register_type::<Bumper>();
         app.register_type::<JumpPad>();
         app.register_type::<BallMagnet>();
+        app.register_type::<Hazard>();
+        app.register_type::<MovingPlatform>();
+        app.register_type::<Conveyor>();
+
+        app.init_resource::<CollisionCooldowns>();
 
         app.add_systems(OnEnter(ServerState::Playing), setup);
 
         app.add_systems(Update, add_required_ball_magnet_components); // todo
+        app.add_systems(
+            FixedUpdate,
+            move_platforms.run_if(in_state(ServerState::Playing)),
+        );
+        app.add_systems(FixedUpdate, apply_conveyors.in_set(PlayingSystems));
         app.add_systems(
             Update,
-            (despawn_bumpers, apply_ball_magnet).in_set(PlayingSystems),
+            (replicate_bumper_hits, despawn_bumpers, apply_ball_magnet).in_set(PlayingSystems),
+        );
+        app.add_systems(
+            FixedUpdate,
+            expire_collision_cooldowns.in_set(PlayingSystems),
         );
     }
 }
@@ -38,9 +56,67 @@ fn setup(mut commands: Commands) {
             StateScoped(ServerState::Playing),
             Observer::new(apply_jump_pad_impulse),
         ),
+        (
+            Name::new("Hazard collision observer"),
+            StateScoped(ServerState::Playing),
+            Observer::new(apply_hazard_penalty),
+        ),
     ]);
 }
 
+/// Effect applied when entities collide, the cooldown of each effect is tracked separately.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum CollisionEffect {
+    Bumper,
+    JumpPad,
+    Hazard,
+    /// Sticky walls or a sticky ball stopping the ball.
+    Sticky,
+}
+
+/// Time of the last handled collision between pairs of entities for each effect,
+/// see [Configuration::collision_cooldown].
+///
+/// Collisions are handled during the physics step, so the time is always taken from [Time<Fixed>].
+#[derive(Resource, Default, Debug)]
+pub(crate) struct CollisionCooldowns {
+    last_handled: HashMap<(CollisionEffect, Entity, Entity), f32>,
+}
+
+impl CollisionCooldowns {
+    /// Checks whether the effect of a collision between the entities should be applied,
+    /// and if so records it.
+    pub(crate) fn try_handle(
+        &mut self,
+        effect: CollisionEffect,
+        entity: Entity,
+        other: Entity,
+        now: f32,
+        cooldown: f32,
+    ) -> bool {
+        let key = (effect, entity.min(other), entity.max(other));
+
+        match self.last_handled.get(&key) {
+            Some(&last) if now - last < cooldown => false,
+            _ => {
+                self.last_handled.insert(key, now);
+                true
+            }
+        }
+    }
+}
+
+fn expire_collision_cooldowns(
+    mut cooldowns: ResMut<CollisionCooldowns>,
+    config: Res<Configuration>,
+    time: Res<Time<Fixed>>,
+) {
+    let now = time.elapsed_secs();
+    cooldowns
+        .last_handled
+        .retain(|_, last| now - *last < config.collision_cooldown);
+}
+
 /// Component for identifying bumper entities.
 #[derive(Component, Reflect, Debug)]
 #[require(
@@ -60,14 +136,21 @@ impl Bumper {
     pub(crate) fn with_hits(hits: usize) -> Self {
         Self { hits: Some(hits) }
     }
+
+    /// Whether the bumper is part of the hole, instead of being placed by a player.
+    pub(crate) fn is_permanent(&self) -> bool {
+        self.hits.is_none()
+    }
 }
 
 fn apply_bumper_impulse(
     trigger: Trigger<OnCollisionStart>,
     mut bumpers: Query<(&Position, &mut Bumper)>,
     players: Query<&Position, With<Player>>,
+    mut cooldowns: ResMut<CollisionCooldowns>,
     mut commands: Commands,
     config: Res<Configuration>,
+    time: Res<Time<Fixed>>,
 ) {
     let bumper_entity = trigger.target();
     let Ok((bumper_position, mut bumper)) = bumpers.get_mut(bumper_entity) else {
@@ -79,6 +162,17 @@ fn apply_bumper_impulse(
         return;
     };
 
+    let now = time.elapsed_secs();
+    if !cooldowns.try_handle(
+        CollisionEffect::Bumper,
+        bumper_entity,
+        other_entity,
+        now,
+        config.collision_cooldown,
+    ) {
+        return;
+    }
+
     // todo: probably should handle collisions from above differently
     let direction = (player_position.0 - bumper_position.0).normalize();
 
@@ -96,6 +190,17 @@ fn apply_bumper_impulse(
     }
 }
 
+fn replicate_bumper_hits(
+    bumpers: Query<(Entity, &Bumper), Changed<Bumper>>,
+    mut commands: Commands,
+) {
+    for (entity, bumper) in bumpers.iter() {
+        if let Some(hits) = bumper.hits {
+            commands.entity(entity).insert(RemainingHits(hits));
+        }
+    }
+}
+
 fn despawn_bumpers(bumpers: Query<(Entity, &Bumper), Changed<Bumper>>, mut commands: Commands) {
     bumpers
         .into_iter()
@@ -115,8 +220,10 @@ fn apply_jump_pad_impulse(
     trigger: Trigger<OnCollisionStart>,
     jump_pads: Query<(), With<JumpPad>>,
     players: Query<(), With<Player>>,
+    mut cooldowns: ResMut<CollisionCooldowns>,
     mut commands: Commands,
     config: Res<Configuration>,
+    time: Res<Time<Fixed>>,
 ) {
     let jump_pad_entity = trigger.target();
     let Ok(_) = jump_pads.get(jump_pad_entity) else {
@@ -128,6 +235,17 @@ fn apply_jump_pad_impulse(
         return;
     };
 
+    let now = time.elapsed_secs();
+    if !cooldowns.try_handle(
+        CollisionEffect::JumpPad,
+        jump_pad_entity,
+        other_entity,
+        now,
+        config.collision_cooldown,
+    ) {
+        return;
+    }
+
     // todo: can get stuck on jump pads when entering without enough horizontal velocity
     let direction = Vector::Y;
 
@@ -141,6 +259,202 @@ fn apply_jump_pad_impulse(
         .insert(ExternalImpulse::new(direction * config.jump_pad_strength).with_persistence(false));
 }
 
+/// Platform that moves back and forth along a path, carrying the balls on it.
+#[derive(Component, Reflect, Clone, Debug)]
+#[require(
+    RigidBody::Kinematic,
+    CollisionLayers::new(GameLayer::Default, [GameLayer::Default, GameLayer::Player]))]
+pub(crate) struct MovingPlatform {
+    /// Positions relative to the hole which the platform moves between, starting at the first one.
+    pub(crate) path: Vec<Vec3>,
+    /// Distance travelled along the path per second.
+    pub(crate) speed: f32,
+    /// Seconds since the platform started moving.
+    pub(crate) elapsed: f32,
+}
+
+impl MovingPlatform {
+    /// Gets the position relative to the hole at the specified time since the platform started moving.
+    pub(crate) fn position_at(&self, time: f32) -> Vec3 {
+        let Some(&first) = self.path.first() else {
+            return Vec3::ZERO;
+        };
+
+        let length: f32 = self
+            .path
+            .windows(2)
+            .map(|points| points[0].distance(points[1]))
+            .sum();
+
+        if length <= 0.0 {
+            return first;
+        }
+
+        // the platform returns along the same path, so a full cycle is twice the length
+        let distance = (time * self.speed).rem_euclid(2.0 * length);
+        let mut remaining = match distance > length {
+            true => 2.0 * length - distance,
+            false => distance,
+        };
+
+        for points in self.path.windows(2) {
+            let segment = points[0].distance(points[1]);
+            // consecutive points at the same position are skipped, they would divide by zero
+            if segment > 0.0 && remaining <= segment {
+                return points[0].lerp(points[1], remaining / segment);
+            }
+
+            remaining -= segment;
+        }
+
+        self.path[self.path.len() - 1]
+    }
+}
+
+/// Moves platforms by setting their velocity instead of their position,
+/// so that the balls on them are carried along by friction.
+fn move_platforms(
+    mut platforms: Query<(
+        &mut MovingPlatform,
+        &Position,
+        &mut LinearVelocity,
+        &ChildOf,
+    )>,
+    holes: Query<&GlobalTransform>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+    if delta <= 0.0 {
+        return;
+    }
+
+    for (mut platform, position, mut velocity, parent) in platforms.iter_mut() {
+        let Ok(hole_transform) = holes.get(parent.parent()) else {
+            continue;
+        };
+
+        platform.elapsed += delta;
+        let target = hole_transform.transform_point(platform.position_at(platform.elapsed));
+        velocity.0 = (Vector::from(target) - position.0) / delta as Scalar;
+    }
+}
+
+/// Area that continuously pushes balls inside it in a direction.
+#[derive(Component, Reflect, Copy, Clone, Debug)]
+#[require(
+    RigidBody::Static,
+    ColliderConstructor::Cuboid{ x_length: 1.0, y_length: 1.0, z_length: 1.0 },
+    Sensor,
+    CollisionLayers::new(GameLayer::Default, [GameLayer::Player]),
+    CollidingEntities)]
+pub(crate) struct Conveyor {
+    /// Direction in which balls are pushed, relative to the rotation of the conveyor.
+    pub(crate) direction: Vec3,
+    pub(crate) strength: Scalar,
+}
+
+fn apply_conveyors(
+    conveyors: Query<(&Conveyor, &GlobalTransform, &CollidingEntities)>,
+    mut players: Query<Option<&mut ExternalForce>, With<Player>>,
+    mut commands: Commands,
+) {
+    for (conveyor, transform, colliding_entities) in conveyors.iter() {
+        let direction = transform.rotation() * conveyor.direction.normalize_or_zero();
+        let force = Vector::from(direction) * conveyor.strength;
+
+        for &player in colliding_entities.iter() {
+            let Ok(existing_force) = players.get_mut(player) else {
+                continue;
+            };
+
+            if let Some(mut existing_force) = existing_force {
+                existing_force.apply_force(force);
+            } else {
+                let force = ExternalForce::new(force).with_persistence(false);
+                commands.entity(player).insert(force);
+            }
+        }
+    }
+}
+
+/// Area that gives a penalty stroke to balls entering it, and moves them out of it.
+#[derive(Component, Reflect, Copy, Clone, Debug)]
+#[require(
+    RigidBody::Static,
+    ColliderConstructor::Cuboid{ x_length: 1.0, y_length: 1.0, z_length: 1.0 },
+    Sensor,
+    CollisionLayers::new(GameLayer::Default, [GameLayer::Player]),
+    CollisionEventsEnabled,
+    HazardZone)]
+pub(crate) struct Hazard {
+    /// Position the ball is moved to, the last position where the ball was at rest if not set.
+    pub(crate) drop_zone: Option<Vec3>,
+}
+
+fn apply_hazard_penalty(
+    trigger: Trigger<OnCollisionStart>,
+    hazards: Query<&Hazard>,
+    mut players: Query<
+        (
+            &mut Transform,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+            &LastPlayerPosition,
+            &mut HoleStrokes,
+            &mut PlayerScore,
+        ),
+        With<Player>,
+    >,
+    mut cooldowns: ResMut<CollisionCooldowns>,
+    config: Res<Configuration>,
+    time: Res<Time<Fixed>>,
+) {
+    let hazard_entity = trigger.target();
+    let Ok(hazard) = hazards.get(hazard_entity) else {
+        return;
+    };
+
+    let other_entity = trigger.collider;
+    let Ok((mut transform, mut linear, mut angular, last, mut strokes, mut score)) =
+        players.get_mut(other_entity)
+    else {
+        return;
+    };
+
+    let now = time.elapsed_secs();
+    if !cooldowns.try_handle(
+        CollisionEffect::Hazard,
+        hazard_entity,
+        other_entity,
+        now,
+        config.collision_cooldown,
+    ) {
+        return;
+    }
+
+    info!(
+        "Player {:?} entered hazard {:?}, adding {} penalty strokes",
+        other_entity, hazard_entity, config.hazard_penalty
+    );
+
+    strokes.0 += config.hazard_penalty;
+    score.score += config.hazard_penalty;
+
+    linear.0 = Vector::ZERO;
+    angular.0 = Vector::ZERO;
+
+    match hazard.drop_zone {
+        Some(drop_zone) => {
+            transform.translation = drop_zone;
+            transform.rotation = Quat::IDENTITY;
+        }
+        None => {
+            transform.translation = last.position;
+            transform.rotation = last.rotation;
+        }
+    }
+}
+
 #[derive(Component, Reflect, Debug)]
 #[require(
     RigidBody::Static,
@@ -200,3 +514,129 @@ fn apply_ball_magnet(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, bevy::ecs::system::RunSystemOnce};
+
+    #[test]
+    fn cooldowns_are_tracked_for_each_effect() {
+        let mut cooldowns = CollisionCooldowns::default();
+        let (ball, walls) = (Entity::from_raw(1), Entity::from_raw(2));
+
+        assert!(cooldowns.try_handle(CollisionEffect::Bumper, ball, walls, 0.0, 0.1));
+        assert!(cooldowns.try_handle(CollisionEffect::Sticky, walls, ball, 0.0, 0.1));
+
+        assert!(!cooldowns.try_handle(CollisionEffect::Bumper, walls, ball, 0.05, 0.1));
+        assert!(cooldowns.try_handle(CollisionEffect::Bumper, ball, walls, 0.1, 0.1));
+    }
+
+    #[test]
+    fn platforms_skip_points_at_the_same_position() {
+        let platform = MovingPlatform {
+            path: vec![Vec3::ZERO, Vec3::ZERO, Vec3::X, Vec3::X],
+            speed: 1.0,
+            elapsed: 0.0,
+        };
+
+        assert_eq!(platform.position_at(0.0), Vec3::ZERO);
+        assert_eq!(platform.position_at(0.5), Vec3::new(0.5, 0.0, 0.0));
+        assert_eq!(platform.position_at(1.0), Vec3::X);
+        assert_eq!(platform.position_at(1.5), Vec3::new(0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn remaining_bumper_hits_are_replicated() {
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(replicate_bumper_hits);
+
+        let temporary = world.spawn(Bumper::with_hits(3)).id();
+        let permanent = world.spawn(Bumper::permanent()).id();
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get::<RemainingHits>(temporary),
+            Some(&RemainingHits(3))
+        );
+        assert!(world.get::<RemainingHits>(permanent).is_none());
+
+        world.get_mut::<Bumper>(temporary).unwrap().hits = Some(2);
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get::<RemainingHits>(temporary),
+            Some(&RemainingHits(2))
+        );
+    }
+
+    #[test]
+    fn balls_on_conveyors_are_pushed_in_its_direction() {
+        let mut world = World::new();
+        let on_conveyor = world.spawn(Player::new()).id();
+        let elsewhere = world.spawn(Player::new()).id();
+
+        let rotation = Quat::from_rotation_y(core::f32::consts::FRAC_PI_2);
+        world.spawn((
+            Conveyor {
+                direction: Vec3::X,
+                strength: 2.0,
+            },
+            GlobalTransform::from(Transform::from_rotation(rotation)),
+            CollidingEntities(EntityHashSet::from_iter([on_conveyor])),
+        ));
+
+        world.run_system_once(apply_conveyors).unwrap();
+
+        let force = world.get::<ExternalForce>(on_conveyor).unwrap().force();
+        assert!((force - Vector::new(0.0, 0.0, -2.0)).length() < 1e-5);
+        assert!(world.get::<ExternalForce>(elsewhere).is_none());
+    }
+
+    #[test]
+    fn entering_hazard_adds_penalty_and_respawns_ball() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<CollisionCooldowns>();
+        world.init_resource::<Time<Fixed>>();
+        world.add_observer(apply_hazard_penalty);
+
+        let hazard = world.spawn(Hazard { drop_zone: None }).id();
+        let last = LastPlayerPosition {
+            position: Vec3::new(1.0, 0.0, 2.0),
+            rotation: Quat::IDENTITY,
+        };
+        let player = world
+            .spawn((
+                Player::new(),
+                Transform::from_xyz(5.0, -1.0, 5.0),
+                LinearVelocity(Vector::X),
+                AngularVelocity(Vector::Y),
+                last,
+                HoleStrokes(2),
+                PlayerScore {
+                    score: 2,
+                    ..default()
+                },
+            ))
+            .id();
+
+        world.trigger_targets(
+            OnCollisionStart {
+                collider: player,
+                body: Some(player),
+            },
+            hazard,
+        );
+        world.flush();
+
+        let penalty = world.resource::<Configuration>().hazard_penalty;
+        let player = world.entity(player);
+        assert_eq!(player.get::<HoleStrokes>().unwrap().0, 2 + penalty);
+        assert_eq!(player.get::<PlayerScore>().unwrap().score, 2 + penalty);
+        assert_eq!(
+            player.get::<Transform>().unwrap().translation,
+            Vec3::new(1.0, 0.0, 2.0)
+        );
+        assert_eq!(player.get::<LinearVelocity>().unwrap().0, Vector::ZERO);
+        assert_eq!(player.get::<AngularVelocity>().unwrap().0, Vector::ZERO);
+    }
+}
@@ -4,15 +4,26 @@ pub(crate) mod setup;
 
 use {
     crate::{
-        Configuration, CourseState, GameLayer, GameState, HoleState, LastPlayerPosition,
-        LoadingCourseSystems, PlayingSystems, ServerState, ValidPlayerInput,
+        Configuration, CourseState, DEACTIVATION_TIME, GameLayer, GameState, HoleState,
+        LastPlayerPosition, LoadingCourseSystems, PlayerSession, PlayingSystems,
+        SLEEPING_THRESHOLD, ServerState, ValidPlayerInput,
         course::{
             entities::CourseEntitiesPlugin, power_ups::PowerUpPlugin, setup::CourseSetupPlugin,
         },
     },
-    avian3d::{math::Vector, prelude::*},
+    avian3d::{
+        math::{Scalar, Vector},
+        prelude::*,
+    },
     bevy::{app::App, prelude::*},
-    minigolf::{CourseDetails, Player, PlayerInput, PlayerScore, PowerUp},
+    bevy_replicon::prelude::*,
+    minigolf::{
+        CameraHint, CourseDetails, HoleMusic, HoleResult, HoleResults, HoleTimer, Intermission,
+        PinDistance, Player, PlayerScore, PowerUp, PowerUpType, ScoringMode, WeightedPowerUps,
+        is_valid_course_id, lobby::PlayerId,
+    },
+    rand::{Rng, distr::Distribution},
+    std::f32::consts::{PI, TAU},
 };
 
 pub(crate) struct CoursePlugin;
@@ -24,6 +35,7 @@ impl Plugin for CoursePlugin {
         app.add_plugins(CourseSetupPlugin);
 
         app.register_type::<GameConfig>();
+        app.register_type::<PhysicsConfig>();
 
         app.register_type::<Course>();
         app.register_type::<Hole>();
@@ -32,19 +44,41 @@ impl Plugin for CoursePlugin {
         app.register_type::<HoleWalls>();
 
         app.register_type::<CurrentHole>();
+        app.register_type::<HoleStrokes>();
+        app.register_type::<PlayerHole>();
 
         app.register_required_components::<PowerUp, CollidingEntities>();
+        // power ups are picked up by passing through them, so they must never push the ball,
+        // regardless of how they are spawned or which collision layers they are on
+        app.register_required_components::<PowerUp, Sensor>();
+        app.register_required_components::<Player, HoleStrokes>();
 
         app.init_resource::<PhysicsConfig>();
 
         app.add_observer(on_hole_added);
+        app.add_observer(advance_player);
+        app.add_observer(complete_player_hole);
 
         app.add_systems(OnEnter(CourseState::Waiting), (pause_physics, setup_course));
         app.add_systems(Update, test.in_set(LoadingCourseSystems));
 
         app.add_systems(OnEnter(CourseState::Playing), resume_physics);
 
-        app.add_systems(OnEnter(HoleState::Playing), reset_player_position);
+        app.add_systems(
+            OnEnter(HoleState::Playing),
+            (
+                reset_player_position,
+                assign_player_holes,
+                reset_hole_strokes,
+                start_hole_timer,
+                start_hole_watchdog,
+                apply_hole_gravity,
+                apply_hole_sleeping,
+                spawn_camera_hint,
+                spawn_hole_music,
+            ),
+        );
+        app.add_systems(OnExit(HoleState::Playing), (reset_gravity, reset_sleeping));
         app.add_systems(
             Update,
             (increment_score, log_score_changes).in_set(PlayingSystems),
@@ -54,17 +88,47 @@ impl Plugin for CoursePlugin {
             FixedUpdate,
             (
                 handle_hole_sensors,
+                capture_balls_in_cup,
                 handle_hole_bounding_box,
+                tick_hole_timer.before(current_hole_modified),
+                tick_hole_watchdog
+                    .before(current_hole_modified)
+                    .run_if(resource_exists::<HoleWatchdog>),
+                tick_player_hole_watchdogs,
                 current_hole_modified,
+                track_pin_distance,
             )
                 .in_set(PlayingSystems),
         );
 
-        app.add_systems(OnEnter(HoleState::Completed), on_hole_completed);
+        app.add_systems(
+            OnEnter(HoleState::Completed),
+            (
+                (record_pin_distance, send_hole_results).chain(),
+                on_hole_completed,
+            ),
+        );
+
+        app.add_systems(
+            OnEnter(HoleState::Intermission),
+            (pause_physics, start_intermission),
+        );
+        app.add_systems(OnExit(HoleState::Intermission), resume_physics);
+        app.add_systems(
+            Update,
+            tick_intermission.run_if(in_state(HoleState::Intermission)),
+        );
+
         app.add_systems(
             OnEnter(CourseState::Completed),
             (remove_current_hole, on_course_completed),
         );
+
+        app.add_systems(OnEnter(GameState::Playing), start_game_timer);
+        app.add_systems(
+            Update,
+            tick_game_timer.run_if(in_state(GameState::Playing).and(resource_exists::<GameTimer>)),
+        );
     }
 }
 
@@ -81,17 +145,258 @@ fn resume_physics(mut time: ResMut<Time<Physics>>) {
 }
 
 fn reset_player_position(
-    mut players: Query<(&mut Position, &mut LastPlayerPosition), With<Player>>,
+    mut players: Query<(Entity, &mut Position, &mut LastPlayerPosition), With<Player>>,
     hole: Res<CurrentHole>,
+    configuration: Res<Configuration>,
 ) {
-    for (mut position, mut last_position) in &mut players {
-        position.0 = hole.hole.start_position.into();
+    let mut players = players.iter_mut().collect::<Vec<_>>();
+    players.sort_by_key(|(entity, ..)| *entity);
+
+    let spawn_offset = Vec3::Y * configuration.spawn_height;
+    let count = players.len();
+    for (index, (_, mut position, mut last_position)) in players.into_iter().enumerate() {
+        let start_position = hole.hole.start_position + spawn_offset + start_offset(index, count);
+        position.0 = start_position.into();
 
-        last_position.position = hole.hole.start_position;
+        last_position.position = start_position;
         last_position.rotation = Quat::IDENTITY;
     }
 }
 
+fn assign_player_holes(
+    players: Query<Entity, With<Player>>,
+    current_hole: Res<CurrentHole>,
+    configuration: Res<Configuration>,
+    mut commands: Commands,
+) {
+    for player in &players {
+        match configuration.hole_progression {
+            HoleProgression::Together => {
+                commands.entity(player).remove::<PlayerHole>();
+            }
+            HoleProgression::Independent => {
+                commands
+                    .entity(player)
+                    .insert(PlayerHole(current_hole.hole_entity));
+            }
+        }
+    }
+}
+
+fn advance_player(
+    trigger: Trigger<AdvancePlayer>,
+    holes: Query<&Hole>,
+    timers: Query<(Entity, &HoleTimer)>,
+    mut players: Query<(
+        &mut Player,
+        &mut PlayerHole,
+        &mut Position,
+        &mut LinearVelocity,
+        &mut AngularVelocity,
+        &mut LastPlayerPosition,
+        &mut HoleStrokes,
+        &mut PinDistance,
+    )>,
+    player_entities: Query<Entity, With<Player>>,
+    configuration: Res<Configuration>,
+    mut commands: Commands,
+) {
+    let player_entity = trigger.target();
+    let hole_entity = trigger.hole;
+    let (
+        Ok(hole),
+        Ok((
+            mut player,
+            mut player_hole,
+            mut position,
+            mut linear,
+            mut angular,
+            mut last_position,
+            mut strokes,
+            mut pin_distance,
+        )),
+    ) = (holes.get(hole_entity), players.get_mut(player_entity))
+    else {
+        return;
+    };
+
+    info!("Moving player {player_entity} to hole {hole_entity}");
+
+    // players keep their place around the start position, so that they do not overlap
+    let mut player_entities = player_entities.iter().collect::<Vec<_>>();
+    player_entities.sort();
+    let index = player_entities
+        .iter()
+        .position(|entity| *entity == player_entity)
+        .unwrap_or_default();
+
+    let start_position = hole.start_position
+        + Vec3::Y * configuration.spawn_height
+        + start_offset(index, player_entities.len());
+    position.0 = start_position.into();
+    linear.0 = Vector::ZERO;
+    angular.0 = Vector::ZERO;
+
+    last_position.position = start_position;
+    last_position.rotation = Quat::IDENTITY;
+
+    player_hole.0 = hole_entity;
+    player.can_move = true;
+    strokes.0 = 0;
+    record_hole_pin_distance(&mut pin_distance);
+
+    for (timer_entity, timer) in &timers {
+        if timer.player == Some(player.id) {
+            commands.entity(timer_entity).despawn();
+        }
+    }
+
+    if let Some(time_limit) = hole.time_limit {
+        commands.spawn(hole_timer_bundle(time_limit, Some(player.id)));
+    }
+
+    if let Some(duration) = configuration.max_hole_duration {
+        commands
+            .entity(player_entity)
+            .insert(PlayerHoleWatchdog(Timer::from_seconds(
+                duration,
+                TimerMode::Once,
+            )));
+    }
+}
+
+/// Completes the hole that the target player is playing, when players progress independently.
+///
+/// The player is moved to the next hole, or completes the course if it was the last hole.
+#[derive(Event, Debug)]
+pub(crate) struct CompletePlayerHole;
+
+fn complete_player_hole(
+    trigger: Trigger<CompletePlayerHole>,
+    mut players: Query<(&mut Player, &PlayerHole, &HoleStrokes, &PlayerScore)>,
+    sessions: Query<(Entity, &PlayerSession)>,
+    course: Single<&Course>,
+    mut current_hole: ResMut<CurrentHole>,
+    mut writer: EventWriter<ToClients<HoleResults>>,
+    mut commands: Commands,
+) {
+    let player_entity = trigger.target();
+    let Ok((mut player, player_hole, strokes, score)) = players.get_mut(player_entity) else {
+        return;
+    };
+
+    if current_hole.is_completed_by(&player) {
+        return;
+    }
+
+    info!("Player {player_entity} completed hole {}", player_hole.0);
+
+    // other players are still playing other holes, so only the player's own result is sent
+    for (session, _) in sessions.iter().filter(|(_, s)| s.player == player_entity) {
+        writer.write(ToClients {
+            mode: SendMode::Direct(session),
+            event: HoleResults {
+                standings: vec![HoleResult {
+                    player: player.id,
+                    strokes: strokes.0,
+                    total: score.total(),
+                }],
+            },
+        });
+    }
+
+    match course.next_hole(player_hole.0) {
+        Some(hole) => {
+            commands.trigger_targets(AdvancePlayer { hole }, player_entity);
+        }
+        None => {
+            current_hole.complete(*player);
+            player.can_move = false;
+        }
+    }
+}
+
+fn apply_hole_gravity(hole: Res<CurrentHole>, mut gravity: ResMut<Gravity>) {
+    let scale = hole.hole.gravity_scale.unwrap_or(1.0);
+    if scale != 1.0 {
+        info!("Scaling gravity by {scale} for the current hole");
+    }
+
+    gravity.0 = Gravity::default().0 * scale;
+}
+
+fn apply_hole_sleeping(
+    hole: Res<CurrentHole>,
+    mut deactivation_time: ResMut<DeactivationTime>,
+    mut sleeping_threshold: ResMut<SleepingThreshold>,
+) {
+    if let Some(time) = hole.hole.deactivation_time {
+        info!("Using deactivation time {time} for the current hole");
+    }
+
+    if let Some(threshold) = hole.hole.sleeping_threshold {
+        info!("Using sleeping threshold {threshold:?} for the current hole");
+    }
+
+    deactivation_time.0 = hole.hole.deactivation_time.unwrap_or(DEACTIVATION_TIME);
+    *sleeping_threshold = hole.hole.sleeping_threshold.unwrap_or(SLEEPING_THRESHOLD);
+}
+
+fn spawn_camera_hint(current_hole: Res<CurrentHole>, mut commands: Commands) {
+    let Some(camera_hint) = current_hole.hole.camera_hint else {
+        return;
+    };
+
+    commands.spawn((
+        Name::new("Camera hint"),
+        camera_hint,
+        Replicated,
+        StateScoped(HoleState::Playing),
+    ));
+}
+
+fn spawn_hole_music(current_hole: Res<CurrentHole>, mut commands: Commands) {
+    let Some(asset) = &current_hole.hole.music else {
+        return;
+    };
+
+    commands.spawn((
+        Name::new("Hole music"),
+        HoleMusic {
+            asset: asset.clone(),
+        },
+        Replicated,
+        StateScoped(HoleState::Playing),
+    ));
+}
+
+fn reset_gravity(mut gravity: ResMut<Gravity>) {
+    *gravity = Gravity::default();
+}
+
+fn reset_sleeping(
+    mut deactivation_time: ResMut<DeactivationTime>,
+    mut sleeping_threshold: ResMut<SleepingThreshold>,
+) {
+    deactivation_time.0 = DEACTIVATION_TIME;
+    *sleeping_threshold = SLEEPING_THRESHOLD;
+}
+
+/// Minimum distance between the centers of balls at the start of a hole.
+const START_POSITION_SPACING: f32 = 0.06;
+
+/// Spreads players evenly on a ring around the start position, so that the balls do not overlap.
+fn start_offset(index: usize, count: usize) -> Vec3 {
+    if count <= 1 {
+        return Vec3::ZERO;
+    }
+
+    let angle = TAU * index as f32 / count as f32;
+    let radius = START_POSITION_SPACING / (2.0 * (PI / count as f32).sin());
+
+    Vec3::new(angle.cos(), 0.0, angle.sin()) * radius
+}
+
 fn on_course_completed(
     course_scene: Single<Entity, With<CourseSceneMarker>>,
     mut config: ResMut<GameConfig>,
@@ -107,20 +412,71 @@ fn on_course_completed(
     }
 }
 
+/// Time left until the game is completed regardless of the remaining holes,
+/// see [Configuration::max_game_duration].
+#[derive(Resource, Debug)]
+struct GameTimer(Timer);
+
+fn start_game_timer(configuration: Res<Configuration>, mut commands: Commands) {
+    match configuration.max_game_duration {
+        Some(duration) => {
+            commands.insert_resource(GameTimer(Timer::from_seconds(duration, TimerMode::Once)))
+        }
+        None => commands.remove_resource::<GameTimer>(),
+    }
+}
+
+fn tick_game_timer(
+    mut timer: ResMut<GameTimer>,
+    time: Res<Time>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        info!("Game time limit reached, completing the game");
+        game_state.set(GameState::Completed);
+    }
+}
+
 #[derive(Resource, Reflect, Default, Debug)]
 pub(crate) struct GameConfig {
     courses: Vec<CourseDetails>,
     current: usize,
+    holes_completed: usize,
+    /// Power ups that players can use, all power ups if not set.
+    enabled_power_ups: Option<Vec<PowerUpType>>,
+    /// Distribution of the enabled power ups, no power ups are spawned if not set.
+    power_ups: Option<WeightedPowerUps>,
 }
 
 impl GameConfig {
-    pub(crate) fn new(courses: Vec<CourseDetails>) -> Self {
+    pub(crate) fn new(
+        courses: Vec<CourseDetails>,
+        enabled_power_ups: Option<Vec<PowerUpType>>,
+        power_ups: Option<WeightedPowerUps>,
+    ) -> Self {
         GameConfig {
             courses,
             current: 0,
+            holes_completed: 0,
+            enabled_power_ups,
+            power_ups,
         }
     }
 
+    /// Whether players can use the power up, regardless of how likely it is to be spawned.
+    pub(crate) fn is_power_up_enabled(&self, power_up: PowerUpType) -> bool {
+        self.enabled_power_ups
+            .as_ref()
+            .is_none_or(|enabled| enabled.contains(&power_up))
+    }
+
+    /// Chooses a power up to spawn, or [None] if no power ups are enabled.
+    pub(crate) fn sample_power_up<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<PowerUpType> {
+        self.power_ups
+            .as_ref()
+            .map(|power_ups| power_ups.sample(rng))
+    }
+
     pub(crate) fn current(&self) -> &CourseDetails {
         &self.courses[self.current]
     }
@@ -133,8 +489,19 @@ impl GameConfig {
             Ok(())
         }
     }
+
+    /// Records a completed hole, returns whether an intermission should follow it.
+    pub(crate) fn complete_hole(&mut self, intermission_interval: Option<usize>) -> bool {
+        self.holes_completed += 1;
+
+        match intermission_interval {
+            Some(interval) if interval > 0 => self.holes_completed % interval == 0,
+            _ => false,
+        }
+    }
 }
 
+/// Friction and restitution of course surfaces, loaded from the configuration file.
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
 pub(crate) struct PhysicsConfig {
@@ -178,15 +545,37 @@ impl Course {
     pub(crate) fn new() -> Self {
         Course { holes: vec![] }
     }
+
+    /// Gets the hole following the specified hole, none if it is the last hole of the course.
+    pub(crate) fn next_hole(&self, hole: Entity) -> Option<Entity> {
+        self.holes
+            .iter()
+            .skip_while(|h| hole != **h)
+            .skip(1)
+            .copied()
+            .next()
+    }
 }
 
-#[derive(Component, Reflect, Copy, Clone, Debug)]
+#[derive(Component, Reflect, Clone, Debug)]
 #[require(
     RigidBody::Static,
     CollisionLayers::new(GameLayer::Default, [GameLayer::Default, GameLayer::Player]),
     Children)]
 pub(crate) struct Hole {
     pub(crate) start_position: Vec3,
+    /// Seconds the players have to complete the hole, unlimited if not set.
+    pub(crate) time_limit: Option<f32>,
+    /// Multiplier of the gravity while playing the hole, normal gravity if not set.
+    pub(crate) gravity_scale: Option<Scalar>,
+    /// Time a ball has to be at rest before it is deactivated, the server default if not set.
+    pub(crate) deactivation_time: Option<Scalar>,
+    /// Velocities below which a ball is considered to be at rest, the server default if not set.
+    pub(crate) sleeping_threshold: Option<SleepingThreshold>,
+    /// Camera viewpoint suggested to players when the hole starts.
+    pub(crate) camera_hint: Option<CameraHint>,
+    /// Music played to players while playing the hole, no music if not set.
+    pub(crate) music: Option<String>,
 }
 
 #[derive(Component, Reflect, Copy, Clone, Debug)]
@@ -236,12 +625,124 @@ pub(crate) struct CurrentHole {
     pub(crate) hole: Hole,
     hole_entity: Entity,
     pub(crate) players: Vec<Player>,
+    /// Whether the hole has been completed and is waiting to advance to the next one.
+    ///
+    /// Ensures that a hole advances only once per completion.
+    completed: bool,
+    /// Players that sank their ball first on each hole, see [ScoringMode::Race](minigolf::ScoringMode::Race).
+    ///
+    /// Contains more than one hole only if players progress independently.
+    winners: Vec<(Entity, PlayerId)>,
+}
+
+impl CurrentHole {
+    pub(crate) fn new(hole: Hole, hole_entity: Entity) -> Self {
+        CurrentHole {
+            hole,
+            hole_entity,
+            players: vec![],
+            completed: false,
+            winners: Vec::new(),
+        }
+    }
+
+    /// Records the player as the winner of the hole, returns false if another player already won it.
+    pub(crate) fn win(&mut self, hole: Entity, player: Player) -> bool {
+        if self.winners.iter().any(|(won_hole, _)| *won_hole == hole) {
+            return false;
+        }
+
+        self.winners.push((hole, player.id));
+        true
+    }
+
+    /// Gets the hole that the player is playing,
+    /// which is the current hole unless players progress independently.
+    pub(crate) fn hole_of(&self, player_hole: Option<&PlayerHole>) -> Entity {
+        player_hole.map_or(self.hole_entity, |hole| hole.0)
+    }
+
+    /// Whether the player has already completed the hole.
+    pub(crate) fn is_completed_by(&self, player: &Player) -> bool {
+        self.players.iter().any(|p| p.id == player.id)
+    }
+
+    /// Records that the player completed the hole, returns false if they already had.
+    pub(crate) fn complete(&mut self, player: Player) -> bool {
+        if self.is_completed_by(&player) {
+            return false;
+        }
+
+        self.players.push(player);
+        true
+    }
+
+    /// Gets the position of the cup of the current hole.
+    pub(crate) fn cup_position(
+        &self,
+        sensors: &Query<(&HoleSensor, &GlobalTransform)>,
+    ) -> Option<Vec3> {
+        self.player_cup_position(None, sensors)
+    }
+
+    /// Gets the position of the cup of the hole that the player is playing, see [CurrentHole::hole_of].
+    pub(crate) fn player_cup_position(
+        &self,
+        player_hole: Option<&PlayerHole>,
+        sensors: &Query<(&HoleSensor, &GlobalTransform)>,
+    ) -> Option<Vec3> {
+        let hole = self.hole_of(player_hole);
+        sensors
+            .iter()
+            .find(|(sensor, _)| sensor.hole == hole)
+            .map(|(_, transform)| transform.translation())
+    }
+}
+
+/// Whether players wait for each other at the end of each hole.
+#[derive(Reflect, Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum HoleProgression {
+    /// The next hole starts once all players have completed the current hole.
+    #[default]
+    Together,
+    /// Each player moves on to the next hole as soon as they complete their current hole,
+    /// the course is completed once all players have completed the last hole.
+    ///
+    /// Settings of holes that apply to all players, such as gravity, camera hints and music,
+    /// are taken from the first hole of the course.
+    Independent,
 }
 
+/// Hole that the player is playing, only set if players progress independently,
+/// see [HoleProgression::Independent].
 #[derive(Component, Reflect, Debug)]
-struct CourseSceneMarker;
+pub(crate) struct PlayerHole(pub(crate) Entity);
+
+/// Moves the target player to the start of the hole, when players progress independently.
+#[derive(Event, Debug)]
+pub(crate) struct AdvancePlayer {
+    pub(crate) hole: Entity,
+}
+
+#[derive(Component, Reflect, Debug)]
+pub(crate) struct CourseSceneMarker;
+
+fn setup_course(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut config: ResMut<GameConfig>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    // courses that cannot be loaded are skipped, otherwise the players would wait forever
+    while !is_valid_course_id(&config.current().id) {
+        error!("Skipping course with invalid id {:?}", config.current().id);
+
+        if let Err(()) = config.next_course() {
+            game_state.set(GameState::Completed);
+            return;
+        }
+    }
 
-fn setup_course(mut commands: Commands, server: Res<AssetServer>, config: Res<GameConfig>) {
     let course_id = &config.current().id;
 
     commands.spawn((
@@ -270,26 +771,36 @@ fn on_hole_added(
 
     if let &[_] = course.holes.as_slice() {
         let hole = hole.get(hole_entity).unwrap();
-        commands.insert_resource::<CurrentHole>(CurrentHole {
-            hole: *hole,
-            hole_entity,
-            players: vec![],
-        });
+        commands.insert_resource(CurrentHole::new(hole.clone(), hole_entity));
     }
 }
 
-fn increment_score(mut reader: EventReader<ValidPlayerInput>, mut scores: Query<&mut PlayerScore>) {
+pub(crate) fn increment_score(
+    mut reader: EventReader<ValidPlayerInput>,
+    mut scores: Query<(&mut PlayerScore, &mut HoleStrokes)>,
+) {
     for input in reader.read() {
-        let PlayerInput::Move(_) = input.input else {
+        if !input.input.is_movement() {
             continue;
-        };
+        }
 
-        let Ok(mut score) = scores.get_mut(input.player) else {
+        let Ok((mut score, mut strokes)) = scores.get_mut(input.player) else {
             warn!("Received {:?} without player score component", input);
             continue;
         };
 
         score.score += 1;
+        strokes.0 += 1;
+    }
+}
+
+/// Number of strokes the player has taken on the current hole.
+#[derive(Component, Reflect, Default, Debug)]
+pub(crate) struct HoleStrokes(pub(crate) u32);
+
+fn reset_hole_strokes(mut strokes: Query<&mut HoleStrokes>) {
+    for mut strokes in &mut strokes {
+        strokes.0 = 0;
     }
 }
 
@@ -323,9 +834,35 @@ fn handle_hole_sensors(
     }
 }
 
+/// Stops slow balls overlapping the cup, so that they come to rest in it and complete the hole.
+fn capture_balls_in_cup(
+    holes: Query<&CollidingEntities, With<HoleSensor>>,
+    mut players: Query<(Entity, &mut LinearVelocity, &mut AngularVelocity), With<Player>>,
+    configuration: Res<Configuration>,
+) {
+    let Some(capture_speed) = configuration.cup_capture_speed else {
+        return;
+    };
+
+    for (entity, mut linear, mut angular) in players.iter_mut() {
+        let horizontal_speed = Vector::new(linear.x, 0.0, linear.z).length();
+        if horizontal_speed > capture_speed || horizontal_speed == 0.0 {
+            continue;
+        }
+
+        if holes.iter().any(|colliding| colliding.contains(&entity)) {
+            debug!("Capturing player {entity} in the cup");
+            // only the horizontal movement is stopped, so that the ball still drops into the cup
+            linear.x = 0.0;
+            linear.z = 0.0;
+            angular.0 = Vector::ZERO;
+        }
+    }
+}
+
 fn handle_hole_bounding_box(
     bounds: Query<(Entity, &HoleBoundingBox, &CollidingEntities), Changed<CollidingEntities>>,
-    players: Query<(Entity, &Player)>,
+    players: Query<(Entity, &Player, Option<&PlayerHole>)>,
     mut transforms: Query<
         (
             &mut Transform,
@@ -338,11 +875,11 @@ fn handle_hole_bounding_box(
     current_hole: Res<CurrentHole>,
 ) {
     for (bounds_entity, bounding_box, colliding_entities) in bounds.iter() {
-        if current_hole.hole_entity != bounding_box.hole {
-            continue;
-        }
+        for (player_entity, player, player_hole) in players.iter() {
+            if current_hole.hole_of(player_hole) != bounding_box.hole {
+                continue;
+            }
 
-        for (player_entity, player) in players.iter() {
             if colliding_entities.contains(&player_entity) {
                 info!(
                     "Player {:?} entered bounds of hole {:?}",
@@ -370,18 +907,25 @@ fn handle_hole_bounding_box(
 }
 
 fn current_hole_modified(
-    current_hole: Res<CurrentHole>,
-    players: Query<(), With<Player>>,
+    mut current_hole: ResMut<CurrentHole>,
+    players: Query<&Player>,
+    mut removed_players: RemovedComponents<Player>,
     mut state: ResMut<NextState<HoleState>>,
 ) {
-    if !current_hole.is_changed() {
+    // the remaining players might have all completed the hole when a player disconnects
+    let players_removed = removed_players.read().count() > 0;
+    if !(current_hole.is_changed() || players_removed) || current_hole.completed {
         return;
     }
 
     info!("Current hole changed");
 
+    // players that completed the hole and then disconnected are not counted
     let player_count = players.iter().count();
-    let completed_player_count = current_hole.players.len();
+    let completed_player_count = players
+        .iter()
+        .filter(|player| current_hole.is_completed_by(player))
+        .count();
 
     if player_count != completed_player_count {
         info!(
@@ -392,39 +936,914 @@ fn current_hole_modified(
         return;
     }
 
+    current_hole.completed = true;
     state.set(HoleState::Completed);
 }
 
+/// Tracks the closest distance of each player to the cup of the current hole.
+fn track_pin_distance(
+    mut players: Query<(&Transform, &mut PinDistance, Option<&PlayerHole>), With<Player>>,
+    sensors: Query<(&HoleSensor, &GlobalTransform)>,
+    current_hole: Res<CurrentHole>,
+) {
+    for (transform, mut pin_distance, player_hole) in &mut players {
+        let Some(cup) = current_hole.player_cup_position(player_hole, &sensors) else {
+            continue;
+        };
+
+        let distance = transform.translation.distance(cup);
+
+        match pin_distance.hole {
+            Some(closest) if closest <= distance => {}
+            _ => pin_distance.hole = Some(distance),
+        }
+    }
+}
+
+fn record_pin_distance(mut players: Query<&mut PinDistance>) {
+    for mut pin_distance in &mut players {
+        record_hole_pin_distance(&mut pin_distance);
+    }
+}
+
+fn send_hole_results(
+    players: Query<(&Player, &HoleStrokes, &PlayerScore, &PinDistance)>,
+    configuration: Res<Configuration>,
+    mut writer: EventWriter<ToClients<HoleResults>>,
+) {
+    // players progressing independently receive the results of each hole once they complete it
+    if configuration.hole_progression == HoleProgression::Independent {
+        return;
+    }
+
+    let standings = hole_standings(players.iter().collect(), configuration.scoring_mode);
+
+    writer.write(ToClients {
+        mode: SendMode::Broadcast,
+        event: HoleResults { standings },
+    });
+}
+
+/// Gets the results of the completed hole, ordered like the standings shown to the players.
+fn hole_standings(
+    mut players: Vec<(&Player, &HoleStrokes, &PlayerScore, &PinDistance)>,
+    scoring_mode: ScoringMode,
+) -> Vec<HoleResult> {
+    // ties are broken by whoever got closest to the cup
+    players.sort_by(|(.., score, distance), (.., other_score, other_distance)| {
+        scoring_mode
+            .compare(score, other_score)
+            .then(distance.total.total_cmp(&other_distance.total))
+    });
+
+    players
+        .into_iter()
+        .map(|(player, strokes, score, _)| HoleResult {
+            player: player.id,
+            strokes: strokes.0,
+            total: score.total(),
+        })
+        .collect()
+}
+
+fn record_hole_pin_distance(pin_distance: &mut PinDistance) {
+    if let Some(closest) = pin_distance.hole.take() {
+        pin_distance.total += closest;
+    }
+}
+
+fn start_hole_timer(
+    players: Query<&Player>,
+    current_hole: Res<CurrentHole>,
+    configuration: Res<Configuration>,
+    mut commands: Commands,
+) {
+    let Some(time_limit) = current_hole.hole.time_limit else {
+        return;
+    };
+
+    match configuration.hole_progression {
+        HoleProgression::Together => {
+            commands.spawn(hole_timer_bundle(time_limit, None));
+        }
+        HoleProgression::Independent => {
+            for player in &players {
+                commands.spawn(hole_timer_bundle(time_limit, Some(player.id)));
+            }
+        }
+    }
+}
+
+fn hole_timer_bundle(time_limit: f32, player: Option<PlayerId>) -> impl Bundle {
+    (
+        Name::new("Hole timer"),
+        HoleTimer {
+            remaining: time_limit,
+            player,
+        },
+        Replicated,
+        StateScoped(HoleState::Playing),
+    )
+}
+
+/// Counts down the [HoleTimer], and completes the hole for all remaining players when it runs out.
+///
+/// Timers of a single player only complete the hole for that player.
+fn tick_hole_timer(
+    mut timers: Query<&mut HoleTimer>,
+    mut players: Query<(Entity, &mut Player)>,
+    mut current_hole: ResMut<CurrentHole>,
+    time: Res<Time>,
+    physics_time: Res<Time<Physics>>,
+    mut commands: Commands,
+) {
+    if physics_time.is_paused() {
+        return;
+    }
+
+    for mut timer in &mut timers {
+        if timer.remaining <= 0.0 {
+            continue;
+        }
+
+        timer.remaining = (timer.remaining - time.delta_secs()).max(0.0);
+        if timer.remaining > 0.0 {
+            continue;
+        }
+
+        match timer.player {
+            Some(player_id) => {
+                info!("Hole time limit reached, completing the hole for player {player_id:?}");
+                for (entity, _) in players.iter().filter(|(_, p)| p.id == player_id) {
+                    commands.trigger_targets(CompletePlayerHole, entity);
+                }
+            }
+            None => {
+                info!("Hole time limit reached, completing the hole for remaining players");
+                complete_remaining_players(&mut players, &mut current_hole);
+            }
+        }
+    }
+}
+
+fn complete_remaining_players(
+    players: &mut Query<(Entity, &mut Player)>,
+    current_hole: &mut CurrentHole,
+) {
+    for (_, mut player) in players {
+        if current_hole.complete(*player) {
+            player.can_move = false;
+        }
+    }
+}
+
+/// Completes the hole after [Configuration::max_hole_duration], regardless of the hole's own time limit.
+#[derive(Resource, Debug)]
+struct HoleWatchdog(Timer);
+
+/// Completes the hole for the player after [Configuration::max_hole_duration],
+/// when players progress independently.
+#[derive(Component, Debug)]
+struct PlayerHoleWatchdog(Timer);
+
+fn start_hole_watchdog(
+    players: Query<Entity, With<Player>>,
+    configuration: Res<Configuration>,
+    mut commands: Commands,
+) {
+    commands.remove_resource::<HoleWatchdog>();
+    for player in &players {
+        commands.entity(player).remove::<PlayerHoleWatchdog>();
+    }
+
+    let Some(duration) = configuration.max_hole_duration else {
+        return;
+    };
+
+    match configuration.hole_progression {
+        HoleProgression::Together => {
+            commands.insert_resource(HoleWatchdog(Timer::from_seconds(duration, TimerMode::Once)))
+        }
+        HoleProgression::Independent => {
+            for player in &players {
+                commands
+                    .entity(player)
+                    .insert(PlayerHoleWatchdog(Timer::from_seconds(
+                        duration,
+                        TimerMode::Once,
+                    )));
+            }
+        }
+    }
+}
+
+fn tick_player_hole_watchdogs(
+    mut watchdogs: Query<(Entity, &mut PlayerHoleWatchdog)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (player, mut watchdog) in &mut watchdogs {
+        if watchdog.0.tick(time.delta()).just_finished() {
+            warn!("Player {player} did not complete their hole in time, completing it");
+            commands.trigger_targets(CompletePlayerHole, player);
+        }
+    }
+}
+
+fn tick_hole_watchdog(
+    mut watchdog: ResMut<HoleWatchdog>,
+    mut players: Query<(Entity, &mut Player)>,
+    mut current_hole: ResMut<CurrentHole>,
+    time: Res<Time>,
+) {
+    // without players the hole would be completed immediately, so wait for a player to join
+    if players.is_empty() {
+        return;
+    }
+
+    if watchdog.0.tick(time.delta()).just_finished() {
+        warn!("Hole was not completed in time, completing it for remaining players");
+        complete_remaining_players(&mut players, &mut current_hole);
+    }
+}
+
 fn on_hole_completed(
     course: Query<&Course>,
     holes: Query<&Hole>,
     mut current_hole: ResMut<CurrentHole>,
+    mut game_config: ResMut<GameConfig>,
+    configuration: Res<Configuration>,
     mut hole_state: ResMut<NextState<HoleState>>,
     mut course_state: ResMut<NextState<CourseState>>,
 ) {
+    if !current_hole.completed {
+        warn!(
+            "Hole {:?} was already advanced, ignoring completion",
+            current_hole.hole_entity
+        );
+        return;
+    }
+
+    current_hole.completed = false;
+    current_hole.winners.clear();
     let _ = current_hole.players.drain(..).collect::<Vec<_>>();
+    let intermission = game_config.complete_hole(configuration.intermission_interval);
     let course = course.single().unwrap();
     info!(
         "Course {:?}, current hole {:?}",
         course, current_hole.hole_entity
     );
 
-    let next_hole = course
-        .holes
-        .iter()
-        .skip_while(|h| current_hole.hole_entity != **h)
-        .skip(1)
-        .map(|h| *h)
-        .next();
+    // players only complete the current hole once they complete the last hole of the course
+    if configuration.hole_progression == HoleProgression::Independent {
+        course_state.set(CourseState::Completed);
+        return;
+    }
 
-    let Some(next_hole_entity) = next_hole else {
+    let Some(next_hole_entity) = course.next_hole(current_hole.hole_entity) else {
         course_state.set(CourseState::Completed);
         return;
     };
 
     let next_hole = holes.get(next_hole_entity).unwrap();
     current_hole.hole_entity = next_hole_entity;
-    current_hole.hole = *next_hole;
+    current_hole.hole = next_hole.clone();
+
+    if intermission {
+        hole_state.set(HoleState::Intermission);
+    } else {
+        hole_state.set(HoleState::Playing);
+    }
+}
+
+fn start_intermission(configuration: Res<Configuration>, mut commands: Commands) {
+    info!(
+        "Starting intermission for {}s",
+        configuration.intermission_duration
+    );
+
+    commands.spawn((
+        Name::new("Intermission"),
+        Intermission {
+            remaining: configuration.intermission_duration,
+        },
+        Replicated,
+        StateScoped(HoleState::Intermission),
+    ));
+}
+
+fn tick_intermission(
+    mut intermissions: Query<&mut Intermission>,
+    time: Res<Time>,
+    mut hole_state: ResMut<NextState<HoleState>>,
+) {
+    for mut intermission in &mut intermissions {
+        intermission.remaining = (intermission.remaining - time.delta_secs()).max(0.0);
+
+        if intermission.remaining <= 0.0 {
+            hole_state.set(HoleState::Playing);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, bevy::ecs::system::RunSystemOnce, core::time::Duration, minigolf::PowerUpWeight,
+    };
+
+    fn hole(start_position: Vec3) -> Hole {
+        Hole {
+            start_position,
+            time_limit: None,
+            gravity_scale: None,
+            deactivation_time: None,
+            sleeping_threshold: None,
+            camera_hint: None,
+            music: None,
+        }
+    }
+
+    fn player(hole: Entity) -> impl Bundle {
+        (
+            Player::new(),
+            PlayerHole(hole),
+            Position::default(),
+            LinearVelocity::default(),
+            AngularVelocity::default(),
+            LastPlayerPosition {
+                position: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+            },
+            HoleStrokes(3),
+            PinDistance::default(),
+        )
+    }
+
+    #[test]
+    fn next_hole_follows_course_order() {
+        let holes = [1, 2, 3].map(Entity::from_raw);
+        let course = Course {
+            holes: holes.to_vec(),
+        };
+
+        assert_eq!(course.next_hole(holes[0]), Some(holes[1]));
+        assert_eq!(course.next_hole(holes[1]), Some(holes[2]));
+        assert_eq!(course.next_hole(holes[2]), None);
+        assert_eq!(course.next_hole(Entity::from_raw(4)), None);
+    }
+
+    #[test]
+    fn hole_advances_once_per_completion() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<NextState<HoleState>>();
+        world.init_resource::<NextState<CourseState>>();
+        world.insert_resource(GameConfig::new(Vec::new(), None, None));
+
+        let holes = [Vec3::ZERO, Vec3::X, Vec3::Z].map(|start| world.spawn(hole(start)).id());
+        world.spawn(Course {
+            holes: holes.to_vec(),
+        });
+        world.insert_resource(CurrentHole::new(hole(Vec3::ZERO), holes[0]));
+
+        let player = Player::new();
+        world.spawn(player);
+        world.resource_mut::<CurrentHole>().complete(player);
+
+        world.run_system_once(current_hole_modified).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<HoleState>>(),
+            NextState::Pending(HoleState::Completed)
+        ));
+
+        // the completion is handled more than once before the next hole starts
+        for _ in 0..2 {
+            world.run_system_once(on_hole_completed).unwrap();
+            world.run_system_once(current_hole_modified).unwrap();
+        }
+
+        let current_hole = world.resource::<CurrentHole>();
+        assert_eq!(current_hole.hole_entity, holes[1]);
+        assert!(!current_hole.completed);
+        assert!(matches!(
+            *world.resource::<NextState<HoleState>>(),
+            NextState::Pending(HoleState::Playing)
+        ));
+    }
+
+    #[test]
+    fn players_can_be_on_different_holes() {
+        let mut app = App::new();
+        app.init_resource::<Configuration>();
+        app.add_observer(advance_player);
+
+        let world = app.world_mut();
+        let first_hole = world.spawn(hole(Vec3::ZERO)).id();
+        let second_hole = world.spawn(hole(Vec3::X)).id();
+        let advanced = world.spawn(player(first_hole)).id();
+        let waiting = world.spawn(player(first_hole)).id();
+
+        world.trigger_targets(AdvancePlayer { hole: second_hole }, advanced);
+        world.flush();
+
+        assert_eq!(world.get::<PlayerHole>(advanced).unwrap().0, second_hole);
+        assert_eq!(world.get::<HoleStrokes>(advanced).unwrap().0, 0);
+        assert_eq!(world.get::<PlayerHole>(waiting).unwrap().0, first_hole);
+        assert_eq!(world.get::<HoleStrokes>(waiting).unwrap().0, 3);
+    }
+
+    #[test]
+    fn power_ups_without_weight_are_still_enabled() {
+        let weights = WeightedPowerUps {
+            weights: vec![
+                PowerUpWeight {
+                    power_up: PowerUpType::Teleport,
+                    weight: 1.0,
+                },
+                PowerUpWeight {
+                    power_up: PowerUpType::ChipShot,
+                    weight: 0.0,
+                },
+            ],
+        };
+        let enabled = vec![PowerUpType::Teleport, PowerUpType::ChipShot];
+        let power_ups = weights.restrict_to(&enabled);
+        let config = GameConfig::new(Vec::new(), Some(enabled), power_ups);
+
+        assert!(config.is_power_up_enabled(PowerUpType::Teleport));
+        assert!(config.is_power_up_enabled(PowerUpType::ChipShot));
+        assert!(!config.is_power_up_enabled(PowerUpType::HoleMagnet));
+
+        let config = GameConfig::new(Vec::new(), None, None);
+        assert!(config.is_power_up_enabled(PowerUpType::HoleMagnet));
+    }
+
+    #[test]
+    fn intermission_follows_every_interval_of_holes() {
+        let mut config = GameConfig::new(Vec::new(), None, None);
+        let intermissions = (0..6)
+            .map(|_| config.complete_hole(Some(3)))
+            .collect::<Vec<_>>();
+        assert_eq!(intermissions, [false, false, true, false, false, true]);
+
+        let mut config = GameConfig::new(Vec::new(), None, None);
+        assert!((0..6).all(|_| !config.complete_hole(None)));
+        assert!((0..6).all(|_| !config.complete_hole(Some(0))));
+    }
+
+    #[test]
+    fn intermission_ends_after_its_duration() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<NextState<HoleState>>();
+        world.insert_resource(Configuration {
+            intermission_duration: 5.0,
+            ..default()
+        });
+        world.run_system_once(start_intermission).unwrap();
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(4));
+        world.run_system_once(tick_intermission).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<HoleState>>(),
+            NextState::Unchanged
+        ));
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world.run_system_once(tick_intermission).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<HoleState>>(),
+            NextState::Pending(HoleState::Playing)
+        ));
+    }
+
+    #[test]
+    fn game_is_completed_once_time_limit_is_exceeded() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<NextState<GameState>>();
+        world.insert_resource(Configuration {
+            max_game_duration: Some(10.0),
+            ..default()
+        });
+        world.run_system_once(start_game_timer).unwrap();
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(9));
+        world.run_system_once(tick_game_timer).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<GameState>>(),
+            NextState::Unchanged
+        ));
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(2));
+        world.run_system_once(tick_game_timer).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<GameState>>(),
+            NextState::Pending(GameState::Completed)
+        ));
+    }
+
+    #[test]
+    fn hole_timer_counts_down_only_while_playing() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Time<Physics>>();
+        world.insert_resource(State::new(HoleState::Playing));
+        world.insert_resource(CurrentHole {
+            hole: hole(Vec3::ZERO),
+            hole_entity: Entity::PLACEHOLDER,
+            players: vec![],
+            completed: false,
+            winners: Vec::new(),
+        });
+        let timer = world.spawn(hole_timer_bundle(10.0, None)).id();
+
+        // the timer is ticked with the other systems of the hole being played
+        let mut schedule = Schedule::default();
+        schedule.add_systems(tick_hole_timer.run_if(in_state(HoleState::Playing)));
+
+        let mut tick = |world: &mut World| {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs(1));
+            schedule.run(world);
+            world.get::<HoleTimer>(timer).unwrap().remaining
+        };
+
+        assert_eq!(tick(&mut world), 9.0);
+
+        world.insert_resource(State::new(HoleState::Intermission));
+        assert_eq!(tick(&mut world), 9.0);
+
+        world.insert_resource(State::new(HoleState::Playing));
+        world.resource_mut::<Time<Physics>>().pause();
+        assert_eq!(tick(&mut world), 9.0);
+
+        world.resource_mut::<Time<Physics>>().unpause();
+        assert_eq!(tick(&mut world), 8.0);
+    }
+
+    #[test]
+    fn unresponsive_player_does_not_block_hole_completion() {
+        let mut app = App::new();
+        app.init_resource::<Time>();
+        app.init_resource::<NextState<HoleState>>();
+        app.insert_resource(Configuration {
+            max_hole_duration: Some(5.0),
+            ..default()
+        });
+        app.insert_resource(CurrentHole {
+            hole: hole(Vec3::ZERO),
+            hole_entity: Entity::PLACEHOLDER,
+            players: vec![],
+            completed: false,
+            winners: Vec::new(),
+        });
+        app.add_systems(
+            Update,
+            (
+                tick_hole_watchdog.run_if(resource_exists::<HoleWatchdog>),
+                current_hole_modified,
+            )
+                .chain(),
+        );
+
+        let world = app.world_mut();
+        let finished = Player::new();
+        world.spawn(finished);
+        // this player never takes another shot
+        world.spawn(Player::new());
+        world.resource_mut::<CurrentHole>().complete(finished);
+        world.run_system_once(start_hole_watchdog).unwrap();
+
+        app.update();
+        assert!(matches!(
+            *app.world().resource::<NextState<HoleState>>(),
+            NextState::Unchanged
+        ));
+
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(6));
+        app.update();
+        assert!(matches!(
+            *app.world().resource::<NextState<HoleState>>(),
+            NextState::Pending(HoleState::Completed)
+        ));
+    }
+
+    #[test]
+    fn closest_pin_distance_is_tracked() {
+        let mut world = World::new();
+        let hole_entity = world.spawn(hole(Vec3::ZERO)).id();
+        world.insert_resource(CurrentHole {
+            hole: hole(Vec3::ZERO),
+            hole_entity,
+            players: vec![],
+            completed: false,
+            winners: Vec::new(),
+        });
+        world.spawn((
+            HoleSensor::new(hole_entity),
+            GlobalTransform::from_translation(Vec3::new(1.0, 0.0, 0.0)),
+        ));
+
+        let player = world
+            .spawn((Player::new(), Transform::default(), PinDistance::default()))
+            .id();
+
+        for (position, closest) in [
+            (Vec3::new(-2.0, 0.0, 0.0), 3.0),
+            (Vec3::new(0.5, 0.0, 0.0), 0.5),
+            (Vec3::new(4.0, 0.0, 0.0), 0.5),
+        ] {
+            world.get_mut::<Transform>(player).unwrap().translation = position;
+            world.run_system_once(track_pin_distance).unwrap();
+            assert_eq!(
+                world.get::<PinDistance>(player).unwrap().hole,
+                Some(closest)
+            );
+        }
+
+        world.run_system_once(record_pin_distance).unwrap();
+        let pin_distance = world.get::<PinDistance>(player).unwrap();
+        assert_eq!(pin_distance.hole, None);
+        assert_eq!(pin_distance.total, 0.5);
+    }
+
+    #[test]
+    fn hole_results_reflect_strokes_of_the_completed_hole() {
+        let (first, second) = (Player::new(), Player::new());
+        let first_score = PlayerScore {
+            score: 10,
+            handicap: 4,
+            points: 0,
+        };
+        let second_score = PlayerScore {
+            score: 7,
+            handicap: 0,
+            points: 1,
+        };
+        let distance = PinDistance::default();
+        let players = [
+            (&first, &HoleStrokes(3), &first_score, &distance),
+            (&second, &HoleStrokes(2), &second_score, &distance),
+        ];
+
+        let standings = hole_standings(players.to_vec(), ScoringMode::Strokes);
+        let results = standings
+            .iter()
+            .map(|result| (result.player, result.strokes, result.total))
+            .collect::<Vec<_>>();
+        assert_eq!(results, [(first.id, 3, 6), (second.id, 2, 7)]);
+
+        let standings = hole_standings(players.to_vec(), ScoringMode::Race);
+        assert_eq!(standings[0].player, second.id);
+    }
+
+    #[test]
+    fn advanced_players_do_not_overlap() {
+        let mut app = App::new();
+        app.init_resource::<Configuration>();
+        app.add_observer(advance_player);
+
+        let world = app.world_mut();
+        let first_hole = world.spawn(hole(Vec3::ZERO)).id();
+        let second_hole = world.spawn(hole(Vec3::X)).id();
+        let first = world.spawn(player(first_hole)).id();
+        let second = world.spawn(player(first_hole)).id();
+
+        world.trigger_targets(AdvancePlayer { hole: second_hole }, first);
+        world.trigger_targets(AdvancePlayer { hole: second_hole }, second);
+        world.flush();
+
+        let first_position = world.get::<Position>(first).unwrap().0;
+        let second_position = world.get::<Position>(second).unwrap().0;
+        let distance = first_position.distance(second_position) as f32;
+        assert!(distance >= START_POSITION_SPACING - 1e-5);
+    }
+
+    #[test]
+    fn configured_combine_rules_are_applied_to_walls() {
+        let config = PhysicsConfig {
+            walls: PhysicsParameters {
+                friction: Friction::new(0.5).with_combine_rule(CoefficientCombine::Min),
+                restitution: Restitution::new(0.7).with_combine_rule(CoefficientCombine::Multiply),
+            },
+            ..default()
+        };
+
+        let mut world = World::new();
+        let walls = world.spawn(config.walls.default_components()).id();
+
+        let friction = world.get::<Friction>(walls).unwrap();
+        assert_eq!(friction.combine_rule, CoefficientCombine::Min);
+        assert_eq!(friction.dynamic_coefficient, 0.5);
+
+        let restitution = world.get::<Restitution>(walls).unwrap();
+        assert_eq!(restitution.combine_rule, CoefficientCombine::Multiply);
+        assert_eq!(restitution.coefficient, 0.7);
+    }
+
+    #[test]
+    fn players_start_at_distinct_positions() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        let hole_entity = world.spawn_empty().id();
+        world.insert_resource(CurrentHole::new(
+            hole(Vec3::new(1.0, 0.0, 2.0)),
+            hole_entity,
+        ));
+
+        let players = (0..5)
+            .map(|_| world.spawn(player(hole_entity)).id())
+            .collect::<Vec<_>>();
+
+        world.run_system_once(reset_player_position).unwrap();
+
+        let positions = players
+            .iter()
+            .map(|player| world.get::<Position>(*player).unwrap().0)
+            .collect::<Vec<_>>();
+        for (index, first) in positions.iter().enumerate() {
+            for second in &positions[index + 1..] {
+                let distance = first.distance(*second) as f32;
+                assert!(distance >= START_POSITION_SPACING - 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn only_slow_balls_are_captured_in_cup() {
+        let mut world = World::new();
+        world.insert_resource(Configuration {
+            cup_capture_speed: Some(0.5),
+            ..default()
+        });
 
-    hole_state.set(HoleState::Playing);
+        let hole_entity = world.spawn_empty().id();
+        let [slow, fast] =
+            [Vector::new(0.3, -0.1, 0.0), Vector::new(2.0, -0.1, 0.0)].map(|velocity| {
+                let mut ball = world.spawn(player(hole_entity));
+                ball.insert((LinearVelocity(velocity), AngularVelocity(Vector::X)));
+                ball.id()
+            });
+
+        let mut collisions = CollidingEntities::default();
+        collisions.extend([slow, fast]);
+        world.spawn((HoleSensor::new(hole_entity), collisions));
+
+        world.run_system_once(capture_balls_in_cup).unwrap();
+
+        let velocity = |entity| world.get::<LinearVelocity>(entity).unwrap().0;
+        assert_eq!(velocity(slow), Vector::new(0.0, -0.1, 0.0));
+        assert_eq!(velocity(fast), Vector::new(2.0, -0.1, 0.0));
+        assert_eq!(world.get::<AngularVelocity>(slow).unwrap().0, Vector::ZERO);
+    }
+
+    #[test]
+    fn balls_are_placed_above_start_position() {
+        let mut world = World::new();
+        world.insert_resource(Configuration {
+            spawn_height: 0.05,
+            ..default()
+        });
+        let hole_entity = world.spawn_empty().id();
+        let start_position = Vec3::new(1.0, 0.0, 2.0);
+        world.insert_resource(CurrentHole::new(hole(start_position), hole_entity));
+        let player = world.spawn(player(hole_entity)).id();
+
+        world.run_system_once(reset_player_position).unwrap();
+
+        let expected = start_position + Vec3::Y * 0.05;
+        assert_eq!(
+            world.get::<Position>(player).unwrap().0,
+            expected.as_dvec3()
+        );
+        let last_position = world.get::<LastPlayerPosition>(player).unwrap();
+        assert_eq!(last_position.position, expected);
+    }
+
+    #[test]
+    fn player_completing_twice_is_counted_once() {
+        let mut world = World::new();
+        world.init_resource::<NextState<HoleState>>();
+        let hole_entity = world.spawn_empty().id();
+        let mut current_hole = CurrentHole::new(hole(Vec3::ZERO), hole_entity);
+
+        let (first, second) = (Player::new(), Player::new());
+        world.spawn_batch([first, second]);
+
+        assert!(current_hole.complete(first));
+        assert!(!current_hole.complete(first));
+        assert_eq!(current_hole.players.len(), 1);
+
+        world.insert_resource(current_hole);
+        world.run_system_once(current_hole_modified).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<HoleState>>(),
+            NextState::Unchanged
+        ));
+    }
+
+    #[test]
+    fn hole_gravity_scale_is_applied_and_restored() {
+        let mut world = World::new();
+        world.init_resource::<Gravity>();
+        let hole_entity = world.spawn_empty().id();
+        let moon_hole = Hole {
+            gravity_scale: Some(0.5),
+            ..hole(Vec3::ZERO)
+        };
+        world.insert_resource(CurrentHole::new(moon_hole, hole_entity));
+
+        world.run_system_once(apply_hole_gravity).unwrap();
+        assert_eq!(world.resource::<Gravity>().0, Gravity::default().0 * 0.5);
+
+        world.run_system_once(reset_gravity).unwrap();
+        assert_eq!(world.resource::<Gravity>().0, Gravity::default().0);
+    }
+
+    #[test]
+    fn hole_sleeping_overrides_are_applied_and_reverted() {
+        let mut world = World::new();
+        world.insert_resource(DeactivationTime(DEACTIVATION_TIME));
+        world.insert_resource(SLEEPING_THRESHOLD);
+        let hole_entity = world.spawn_empty().id();
+        let downhill_hole = Hole {
+            deactivation_time: Some(1.0),
+            sleeping_threshold: Some(SleepingThreshold {
+                linear: 0.5,
+                angular: 0.5,
+            }),
+            ..hole(Vec3::ZERO)
+        };
+        world.insert_resource(CurrentHole::new(downhill_hole, hole_entity));
+
+        world.run_system_once(apply_hole_sleeping).unwrap();
+        assert_eq!(world.resource::<DeactivationTime>().0, 1.0);
+        assert_eq!(world.resource::<SleepingThreshold>().linear, 0.5);
+        assert_eq!(world.resource::<SleepingThreshold>().angular, 0.5);
+
+        world.run_system_once(reset_sleeping).unwrap();
+        assert_eq!(world.resource::<DeactivationTime>().0, DEACTIVATION_TIME);
+        assert_eq!(
+            world.resource::<SleepingThreshold>().linear,
+            SLEEPING_THRESHOLD.linear
+        );
+        assert_eq!(
+            world.resource::<SleepingThreshold>().angular,
+            SLEEPING_THRESHOLD.angular
+        );
+    }
+
+    #[test]
+    fn balls_passing_through_power_ups_keep_their_velocity() {
+        use {bevy::time::TimeUpdateStrategy, setup::power_up_bundle};
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            AssetPlugin::default(),
+            bevy::scene::ScenePlugin,
+            PhysicsPlugins::default(),
+        ));
+        app.init_resource::<Assets<Mesh>>();
+        app.insert_resource(Gravity::ZERO);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / 64.0,
+        )));
+
+        app.world_mut()
+            .spawn(power_up_bundle(PowerUpType::Teleport, Transform::IDENTITY));
+        let ball = app
+            .world_mut()
+            .spawn((
+                RigidBody::Dynamic,
+                Collider::sphere(0.02),
+                CollisionLayers::new(GameLayer::Player, [GameLayer::Default]),
+                Transform::from_xyz(-0.3, 0.0, 0.0),
+                LinearVelocity(Vector::X),
+            ))
+            .id();
+
+        for _ in 0..64 {
+            app.update();
+        }
+
+        let world = app.world();
+        assert!(world.get::<Transform>(ball).unwrap().translation.x > 0.2);
+        let velocity = world.get::<LinearVelocity>(ball).unwrap();
+        assert!((velocity.0 - Vector::X).length() < 1e-6);
+    }
 }
@@ -1,15 +1,22 @@
 use {
     crate::{
-        HoleState, LastPlayerPosition, PlayingSystems, ServerState, ValidPlayerInput,
+        CourseState, GameRng, HoleState, LastPlayerPosition, PlayerSession, PlayingSystems,
+        ServerState, ValidPlayerInput,
         course::{
-            Configuration, CurrentHole, HoleSensor, HoleWalls,
-            setup::{SpawnBlackHoleBumper, SpawnBumper},
+            Configuration, CurrentHole, GameConfig, HoleBoundingBox, HoleSensor, HoleWalls,
+            PlayerHole,
+            entities::{Bumper, CollisionCooldowns, CollisionEffect},
+            setup::{SpawnBlackHoleBumper, SpawnBumper, power_up_bundle},
         },
     },
     avian3d::{math::Vector, prelude::*},
     bevy::prelude::*,
-    minigolf::{Player, PlayerInput, PlayerPowerUps, PowerUp},
-    std::ops::Deref,
+    bevy_replicon::prelude::*,
+    minigolf::{
+        ActiveEffects, Player, PlayerInput, PlayerPowerUps, PowerUp, PowerUpNotImplemented,
+        PowerUpType, lobby::PlayerId,
+    },
+    std::{collections::HashMap, ops::Deref},
 };
 
 pub(crate) struct PowerUpPlugin;
@@ -19,27 +26,57 @@ impl Plugin for PowerUpPlugin {
         app.register_type::<HoleMagnetPowerUp>();
         app.register_type::<StickyWalls>();
         app.register_type::<StickyBall>();
+        app.register_type::<StuckToWall>();
         app.register_type::<ChipShotMarker>();
+        app.register_type::<PowerUpRespawn>();
+        app.register_type::<IceRink>();
+        app.register_type::<InactivePowerUp>();
+
+        // effects are replicated for each player, since players might be playing different holes
+        app.register_required_components::<Player, ActiveEffects>();
+
+        app.add_observer(activate_power_ups);
 
         app.add_systems(OnEnter(ServerState::Playing), setup_observers);
 
-        app.add_systems(Update, apply_power_ups.in_set(PlayingSystems));
+        app.add_systems(
+            Update,
+            (
+                apply_power_ups,
+                release_stuck_balls,
+                mark_hole_magnet_shot,
+                update_active_effects,
+            )
+                .in_set(PlayingSystems),
+        );
 
         app.add_systems(
             FixedUpdate,
             (
                 handle_power_up_sensors,
+                respawn_power_ups,
+                tick_power_up_activation.run_if(resource_exists::<PowerUpActivationTimer>),
                 apply_winds,
                 apply_hole_magnet,
                 remove_hole_magnet,
+                release_stuck_balls_after_timeout,
             )
                 .in_set(PlayingSystems),
         );
 
+        app.add_systems(OnEnter(HoleState::Playing), deactivate_power_ups);
         app.add_systems(
             OnEnter(HoleState::Completed),
-            (remove_sticky_ball, despawn_winds),
+            (
+                remove_sticky_ball,
+                despawn_winds,
+                remove_power_up_activation_timer,
+            ),
         );
+
+        // catches effects of holes that did not complete normally, for example when the game timer runs out
+        app.add_systems(OnEnter(CourseState::Completed), clear_effects);
+        app.add_systems(OnEnter(CourseState::Waiting), clear_effects);
     }
 }
 
@@ -51,6 +88,40 @@ fn setup_observers(mut commands: Commands) {
     ));
 }
 
+/// Marks a hole made slippery by the [minigolf::PowerUpType::IceRink] power up.
+#[derive(Component, Reflect, Debug)]
+struct IceRink;
+
+/// Replicates which power ups affect the environment of the hole each player is playing,
+/// see [ActiveEffects].
+fn update_active_effects(
+    mut players: Query<(&mut ActiveEffects, Option<&PlayerHole>)>,
+    winds: Query<&Wind>,
+    sticky_walls: Query<&HoleWalls, With<StickyWalls>>,
+    ice_rinks: Query<(), With<IceRink>>,
+    current_hole: Res<CurrentHole>,
+) {
+    for (mut active, player_hole) in &mut players {
+        let hole = current_hole.hole_of(player_hole);
+
+        let mut effects = Vec::new();
+        if winds.iter().any(|wind| wind.hole == hole) {
+            effects.push(PowerUpType::Wind);
+        }
+
+        if sticky_walls.iter().any(|walls| walls.hole_entity == hole) {
+            effects.push(PowerUpType::StickyWalls);
+        }
+
+        if ice_rinks.contains(hole) {
+            effects.push(PowerUpType::IceRink);
+        }
+
+        // only changed effects are replicated
+        active.set_if_neq(ActiveEffects(effects));
+    }
+}
+
 /// Indicates that [minigolf::PowerUpType::ChipShot] should apply to the next hit for the player.
 #[derive(Component, Reflect, Debug)]
 pub(crate) struct ChipShotMarker;
@@ -59,12 +130,76 @@ fn apply_power_ups(
     mut reader: EventReader<ValidPlayerInput>,
     current_hole: Res<CurrentHole>,
     mut commands: Commands,
-    players: Query<Entity, With<Player>>,
+    players: Query<(Entity, &Player)>,
+    player_holes: Query<&PlayerHole>,
     hole_walls: Query<(Entity, &HoleWalls)>,
+    bounds: Query<(&HoleBoundingBox, &ColliderAabb)>,
+    mut power_ups: Query<&mut PlayerPowerUps>,
+    sessions: Query<(Entity, &PlayerSession)>,
+    mut writer: EventWriter<ToClients<PowerUpNotImplemented>>,
+    bumpers: Query<(&Bumper, &ChildOf)>,
+    winds: Query<&Wind>,
+    configuration: Res<Configuration>,
 ) {
+    let placed_entities = |hole: Entity| {
+        bumpers
+            .iter()
+            .filter(|(bumper, child_of)| !bumper.is_permanent() && child_of.parent() == hole)
+            .count()
+            + winds.iter().filter(|wind| wind.hole == hole).count()
+    };
+
+    // entities placed by previous inputs are only spawned once the commands are applied
+    let mut newly_placed = HashMap::<Entity, usize>::new();
+
     for &ValidPlayerInput { input, player } in reader.read() {
+        let hole = current_hole.hole_of(player_holes.get(player).ok());
+
+        if let Some(power_up) = input.get_power_up_type() {
+            // used here instead of when the input is received, so that rejected inputs can be refunded
+            let used = power_ups
+                .get_mut(player)
+                .ok()
+                .and_then(|mut player_power_ups| player_power_ups.use_power_up(power_up));
+
+            if let None = used {
+                warn!("Player {:?} no longer has power up {:?}", player, power_up);
+                continue;
+            }
+        }
+
         match input {
-            PlayerInput::Move(_) => {}
+            PlayerInput::Move(_) | PlayerInput::Putt { .. } => {}
+
+            PlayerInput::Teleport(translation)
+            | PlayerInput::Bumper(translation)
+            | PlayerInput::BlackHoleBumper(translation)
+                if !is_in_hole(translation, hole, &bounds) =>
+            {
+                warn!(
+                    "Input {:?} from player {:?} is outside of their current hole, refunding it",
+                    input, player
+                );
+
+                if let Some(power_up) = input.get_power_up_type() {
+                    refund_power_up(player, power_up, &mut power_ups);
+                }
+            }
+
+            PlayerInput::Bumper(_) | PlayerInput::BlackHoleBumper(_) | PlayerInput::Wind(_)
+                if !configuration.can_place_entity(
+                    placed_entities(hole) + newly_placed.get(&hole).copied().unwrap_or(0),
+                ) =>
+            {
+                warn!(
+                    "Input {:?} from player {:?} exceeds the maximum number of placed entities, refunding it",
+                    input, player
+                );
+
+                if let Some(power_up) = input.get_power_up_type() {
+                    refund_power_up(player, power_up, &mut power_ups);
+                }
+            }
 
             PlayerInput::Teleport(translation) => {
                 let mut vec = Vector::from(translation);
@@ -74,7 +209,7 @@ fn apply_power_ups(
             }
 
             PlayerInput::HoleMagnet => {
-                commands.entity(player).insert(HoleMagnetPowerUp);
+                commands.entity(player).insert(HoleMagnetPowerUp::default());
             }
 
             PlayerInput::ChipShot => {
@@ -82,34 +217,39 @@ fn apply_power_ups(
             }
 
             PlayerInput::StickyBall => {
-                for other_player in players.iter().filter(|e| *e != player) {
+                for (other_player, _) in players.iter().filter(|(e, _)| *e != player) {
                     commands.entity(other_player).insert(StickyBall);
                 }
             }
 
             PlayerInput::Bumper(translation) => {
-                // todo: have to validate and adjust the translation
-                commands.trigger(SpawnBumper::with_hits(Transform::from_translation(
-                    translation,
-                )));
+                // todo: have to adjust the translation
+                commands.trigger(SpawnBumper::with_hits(
+                    Transform::from_translation(translation),
+                    hole,
+                ));
+                *newly_placed.entry(hole).or_default() += 1;
             }
 
             PlayerInput::BlackHoleBumper(translation) => {
-                // todo: have to validate and adjust the translation
+                // todo: have to adjust the translation
                 commands.trigger(SpawnBlackHoleBumper::with_hits(
                     Transform::from_translation(translation),
+                    hole,
                 ));
+                *newly_placed.entry(hole).or_default() += 1;
             }
 
             PlayerInput::Wind(direction) => {
                 let direction = direction.normalize();
-                commands.spawn((Name::new("Wind"), Wind { direction }));
+                commands.spawn((Name::new("Wind"), Wind { direction, hole }));
+                *newly_placed.entry(hole).or_default() += 1;
             }
 
             PlayerInput::StickyWalls => {
                 let walls = hole_walls
                     .iter()
-                    .filter(|(_, w)| w.hole_entity == current_hole.hole_entity)
+                    .filter(|(_, w)| w.hole_entity == hole)
                     .map(|(e, _)| e)
                     .next()
                     .unwrap();
@@ -119,24 +259,135 @@ fn apply_power_ups(
 
             PlayerInput::IceRink => {
                 // todo: visual effect
-                commands
-                    .entity(current_hole.hole_entity)
-                    .insert(Friction::new(0.01).with_combine_rule(CoefficientCombine::Min));
+                commands.entity(hole).insert((
+                    Friction::new(0.01).with_combine_rule(CoefficientCombine::Min),
+                    IceRink,
+                ));
+            }
+
+            PlayerInput::StealPowerUp(target_id) => {
+                let stolen = steal_target(players.iter(), player, target_id)
+                    .and_then(|target| power_ups.get_mut(target).ok()?.take_latest());
+
+                let Some(stolen) = stolen else {
+                    warn!(
+                        "Player {:?} could not steal a power up from {}, refunding it",
+                        player,
+                        target_id.short()
+                    );
+
+                    refund_power_up(player, PowerUpType::StealPowerUp, &mut power_ups);
+                    continue;
+                };
+
+                info!(
+                    "Player {:?} stole power up {:?} from {}",
+                    player,
+                    stolen,
+                    target_id.short()
+                );
+
+                refund_power_up(player, stolen, &mut power_ups);
             }
 
-            _ => {
-                warn!("Unhandled player input type {:?}", input);
+            PlayerInput::TinyBall
+            | PlayerInput::HugeBall
+            | PlayerInput::ZanyBall
+            | PlayerInput::ReversiBall
+            | PlayerInput::Tornado(_) => {
+                let Some(power_up) = input.get_power_up_type() else {
+                    continue;
+                };
+
+                warn!(
+                    "Power up {:?} is not implemented, refunding it to player {:?}",
+                    power_up, player
+                );
+
+                refund_power_up(player, power_up, &mut power_ups);
+
+                for (session, _) in sessions.iter().filter(|(_, s)| s.player == player) {
+                    writer.write(ToClients {
+                        mode: SendMode::Direct(session),
+                        event: PowerUpNotImplemented { power_up },
+                    });
+                }
             }
         }
     }
 }
 
+/// Finds the player to steal from, only other players spawned for this game can be targeted.
+fn steal_target<'a>(
+    players: impl IntoIterator<Item = (Entity, &'a Player)>,
+    thief: Entity,
+    target_id: PlayerId,
+) -> Option<Entity> {
+    players
+        .into_iter()
+        .find(|(entity, player)| player.id == target_id && *entity != thief)
+        .map(|(entity, _)| entity)
+}
+
+/// Gives the power up back to the player, since it was already used when the input was applied.
+///
+/// The player always has space for it, unless it picked up another power up in the meantime.
+fn refund_power_up(
+    player: Entity,
+    power_up: PowerUpType,
+    power_ups: &mut Query<&mut PlayerPowerUps>,
+) {
+    if let Ok(mut player_power_ups) = power_ups.get_mut(player) {
+        if let Err(_) = player_power_ups.add_power_up(power_up) {
+            warn!("Could not refund power up {:?} to {:?}", power_up, player);
+        }
+    }
+}
+
+/// Whether the translation is within the bounds of the hole.
+fn is_in_hole(
+    translation: Vec3,
+    hole: Entity,
+    bounds: &Query<(&HoleBoundingBox, &ColliderAabb)>,
+) -> bool {
+    let translation = Vector::from(translation);
+
+    bounds
+        .iter()
+        .filter(|(bounding_box, _)| bounding_box.hole == hole)
+        .any(|(_, aabb)| aabb.min.cmple(translation).all() && translation.cmple(aabb.max).all())
+}
+
+/// Replaces the power up with a new one after the delay once it is picked up.
+#[derive(Component, Reflect, Debug)]
+pub(crate) struct PowerUpRespawn {
+    /// Seconds after which the new power up is spawned.
+    pub(crate) delay: f32,
+}
+
+/// Placeholder for a picked up power up with [PowerUpRespawn], until the timer finishes.
+#[derive(Component, Debug)]
+struct PowerUpRespawnTimer {
+    timer: Timer,
+    delay: f32,
+}
+
 fn handle_power_up_sensors(
-    power_ups: Query<(Entity, &PowerUp, &CollidingEntities), Changed<CollidingEntities>>,
+    power_ups: Query<
+        (
+            Entity,
+            &PowerUp,
+            &CollidingEntities,
+            &Transform,
+            Option<&ChildOf>,
+            Option<&PowerUpRespawn>,
+        ),
+        (Changed<CollidingEntities>, Without<InactivePowerUp>),
+    >,
     mut players: Query<(Entity, &mut PlayerPowerUps), With<Player>>,
     mut commands: Commands,
 ) {
-    for (power_up_entity, power_up, collisions) in power_ups.iter() {
+    for (power_up_entity, power_up, collisions, transform, parent, respawn) in power_ups.iter() {
         for (player, mut player_power_ups) in &mut players {
             if !collisions.contains(&player) {
                 continue;
@@ -155,6 +406,20 @@ fn handle_power_up_sensors(
                     );
 
                     commands.entity(power_up_entity).despawn();
+
+                    if let (Some(respawn), Some(parent)) = (respawn, parent) {
+                        commands.spawn((
+                            Name::new("Power up respawn"),
+                            PowerUpRespawnTimer {
+                                timer: Timer::from_seconds(respawn.delay, TimerMode::Once),
+                                delay: respawn.delay,
+                            },
+                            *transform,
+                            ChildOf(parent.parent()),
+                        ));
+                    }
+
+                    break;
                 }
                 Err(_) => {
                     info!(
@@ -167,15 +432,133 @@ fn handle_power_up_sensors(
     }
 }
 
+/// When the power ups of a hole can be picked up, see [InactivePowerUp].
+#[derive(Reflect, Default, Copy, Clone, PartialEq, Debug)]
+pub(crate) enum PowerUpActivation {
+    /// Power ups can be picked up as soon as the hole starts.
+    #[default]
+    Immediately,
+    /// Power ups can be picked up once the first player completes a hole.
+    FirstCompletion,
+    /// Power ups can be picked up after the specified number of seconds since the hole started.
+    Delay(f32),
+}
+
+/// Marks a power up that cannot be picked up yet, see [PowerUpActivation].
+///
+/// Inactive power ups are not replicated, so they are hidden from the players until activated.
+#[derive(Component, Reflect, Debug)]
+struct InactivePowerUp;
+
+/// Activates all inactive power ups, see [PowerUpActivation].
+#[derive(Event, Debug)]
+pub(crate) struct ActivatePowerUps;
+
+#[derive(Resource, Debug)]
+struct PowerUpActivationTimer(Timer);
+
+fn deactivate_power_ups(
+    power_ups: Query<Entity, With<PowerUp>>,
+    configuration: Res<Configuration>,
+    mut commands: Commands,
+) {
+    match configuration.power_up_activation {
+        PowerUpActivation::Immediately => return,
+        PowerUpActivation::FirstCompletion => {}
+        PowerUpActivation::Delay(seconds) => {
+            commands.insert_resource(PowerUpActivationTimer(Timer::from_seconds(
+                seconds,
+                TimerMode::Once,
+            )));
+        }
+    }
+
+    for power_up in &power_ups {
+        commands
+            .entity(power_up)
+            .insert(InactivePowerUp)
+            .remove::<Replicated>();
+    }
+}
+
+fn tick_power_up_activation(
+    mut timer: ResMut<PowerUpActivationTimer>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    if timer.0.tick(time.delta()).finished() {
+        commands.remove_resource::<PowerUpActivationTimer>();
+        commands.trigger(ActivatePowerUps);
+    }
+}
+
+fn remove_power_up_activation_timer(mut commands: Commands) {
+    commands.remove_resource::<PowerUpActivationTimer>();
+}
+
+fn activate_power_ups(
+    _trigger: Trigger<ActivatePowerUps>,
+    power_ups: Query<Entity, With<InactivePowerUp>>,
+    mut commands: Commands,
+) {
+    if power_ups.is_empty() {
+        return;
+    }
+
+    info!("Activating {} power ups", power_ups.iter().len());
+    for power_up in &power_ups {
+        commands
+            .entity(power_up)
+            .remove::<InactivePowerUp>()
+            .insert(Replicated);
+    }
+}
+
+fn respawn_power_ups(
+    mut timers: Query<(Entity, &mut PowerUpRespawnTimer, &Transform, &ChildOf)>,
+    game_config: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut respawn, transform, parent) in &mut timers {
+        if !respawn.timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        commands.entity(entity).despawn();
+
+        let Some(power_up) = game_config.sample_power_up(&mut **rng) else {
+            continue;
+        };
+
+        info!(
+            "Respawning power up {power_up:?} at {}",
+            transform.translation
+        );
+
+        commands.spawn((
+            power_up_bundle(power_up, *transform),
+            PowerUpRespawn {
+                delay: respawn.delay,
+            },
+            ChildOf(parent.parent()),
+        ));
+    }
+}
+
 #[derive(Component, Reflect, Debug)]
 struct Wind {
     direction: Vec2,
+    /// Hole on which the wind blows.
+    hole: Entity,
 }
 
 fn apply_winds(
     winds: Query<&Wind>,
-    players: Query<(Entity, Option<&ExternalForce>), With<Player>>,
+    players: Query<(Entity, Option<&ExternalForce>, Option<&PlayerHole>), With<Player>>,
     holes: Query<&CollidingEntities, With<HoleSensor>>,
+    current_hole: Res<CurrentHole>,
     config: Res<Configuration>,
     mut commands: Commands,
 ) {
@@ -183,11 +566,21 @@ fn apply_winds(
         return;
     }
 
-    let direction: Vec2 = winds.iter().map(|wind| wind.direction.normalize()).sum();
-    let wind_force =
-        Vector::new(direction.x.into(), 0.0, direction.y.into()) * config.wind_strength;
+    for (player, existing_force, player_hole) in players {
+        let hole = current_hole.hole_of(player_hole);
+        let direction: Vec2 = winds
+            .iter()
+            .filter(|wind| wind.hole == hole)
+            .map(|wind| wind.direction.normalize())
+            .sum();
+
+        if direction == Vec2::ZERO {
+            continue;
+        }
+
+        let wind_force =
+            Vector::new(direction.x.into(), 0.0, direction.y.into()) * config.wind_strength;
 
-    for (player, existing_force) in players {
         if holes.iter().any(|colliding| colliding.contains(&player)) {
             // todo: delay to disable wind while inside hole?
             continue;
@@ -205,22 +598,31 @@ fn despawn_winds(winds: Query<Entity, With<Wind>>, mut commands: Commands) {
     winds.iter().for_each(|e| commands.entity(e).despawn());
 }
 
-#[derive(Component, Reflect)]
-struct HoleMagnetPowerUp;
+/// Attracts the player to the hole until the ball comes to rest after the next shot.
+#[derive(Component, Reflect, Default)]
+struct HoleMagnetPowerUp {
+    /// Whether the player has hit the ball since activating the power up.
+    ///
+    /// The power up may be activated while the ball is at rest, so resting alone does not end it.
+    shot_taken: bool,
+}
 
 fn apply_hole_magnet(
     current_hole: Res<CurrentHole>,
     mut commands: Commands,
     transforms: Query<&GlobalTransform>,
-    players: Query<(Entity, &GlobalTransform), (With<Player>, With<HoleMagnetPowerUp>)>,
+    players: Query<
+        (Entity, &GlobalTransform, Option<&PlayerHole>),
+        (With<Player>, With<HoleMagnetPowerUp>),
+    >,
     time: Res<Time<Fixed>>,
     config: Res<Configuration>,
 ) {
-    let Ok(hole_transform) = transforms.get(current_hole.hole_entity) else {
-        return;
-    };
+    for (player, transform, player_hole) in players.iter() {
+        let Ok(hole_transform) = transforms.get(current_hole.hole_of(player_hole)) else {
+            continue;
+        };
 
-    for (player, transform) in players.iter() {
         let vector = hole_transform.translation() - transform.translation();
         let distance = vector.length();
 
@@ -237,20 +639,31 @@ fn apply_hole_magnet(
     }
 }
 
+fn mark_hole_magnet_shot(
+    mut reader: EventReader<ValidPlayerInput>,
+    mut magnets: Query<&mut HoleMagnetPowerUp>,
+) {
+    for input in reader.read() {
+        if !input.input.is_movement() {
+            continue;
+        }
+
+        if let Ok(mut magnet) = magnets.get_mut(input.player) {
+            magnet.shot_taken = true;
+        }
+    }
+}
+
 fn remove_hole_magnet(
-    players: Query<
-        Entity,
-        (
-            With<Player>,
-            With<HoleMagnetPowerUp>,
-            Changed<LastPlayerPosition>,
-        ),
-    >,
+    players: Query<(Entity, &HoleMagnetPowerUp), (With<Player>, Changed<LastPlayerPosition>)>,
     mut commands: Commands,
 ) {
-    players.iter().for_each(|player| {
-        commands.entity(player).remove::<HoleMagnetPowerUp>();
-    });
+    players
+        .iter()
+        .filter(|(_, magnet)| magnet.shot_taken)
+        .for_each(|(player, _)| {
+            commands.entity(player).remove::<HoleMagnetPowerUp>();
+        });
 }
 
 #[derive(Component, Reflect)]
@@ -259,6 +672,21 @@ struct StickyWalls;
 #[derive(Component, Reflect)]
 pub(crate) struct StickyBall;
 
+/// Marks a ball that was stopped by a sticky effect, until the player takes their next shot
+/// or the timeout runs out.
+#[derive(Component, Reflect)]
+struct StuckToWall {
+    timeout: Option<Timer>,
+}
+
+impl StuckToWall {
+    fn new(timeout: Option<f32>) -> Self {
+        StuckToWall {
+            timeout: timeout.map(|seconds| Timer::from_seconds(seconds, TimerMode::Once)),
+        }
+    }
+}
+
 fn on_player_collided(
     trigger: Trigger<OnCollisionStart>,
     walls: Query<(), With<HoleWalls>>,
@@ -266,6 +694,9 @@ fn on_player_collided(
     players: Query<&Player>,
     sticky_players: Query<(), (With<Player>, With<StickyBall>)>,
     mut velocities: Query<(&mut LinearVelocity, &mut AngularVelocity)>,
+    mut cooldowns: ResMut<CollisionCooldowns>,
+    configuration: Res<Configuration>,
+    time: Res<Time<Fixed>>,
     mut commands: Commands,
 ) {
     let player_entity = trigger.target();
@@ -286,19 +717,448 @@ fn on_player_collided(
         return;
     }
 
+    let now = time.elapsed_secs();
+    if !cooldowns.try_handle(
+        CollisionEffect::Sticky,
+        player_entity,
+        other_entity,
+        now,
+        configuration.collision_cooldown,
+    ) {
+        return;
+    }
+
     info!(
         "Applying sticky effect for player {:?}, walls {:?}",
         player_entity, other_entity
     );
 
-    commands.entity(player_entity).insert(Sleeping);
+    commands
+        .entity(player_entity)
+        .insert((Sleeping, StuckToWall::new(configuration.sticky_timeout)));
     let (mut linear, mut angular) = velocities.get_mut(player_entity).unwrap();
     linear.0 = Vector::ZERO;
     angular.0 = Vector::ZERO;
 }
 
+/// Wakes up balls stuck to a wall when the player takes a shot, so that the shot is applied.
+fn release_stuck_balls(
+    mut reader: EventReader<ValidPlayerInput>,
+    stuck_players: Query<(), With<StuckToWall>>,
+    mut commands: Commands,
+) {
+    for &ValidPlayerInput { ref input, player } in reader.read() {
+        if !input.is_movement() || stuck_players.get(player).is_err() {
+            continue;
+        }
+
+        info!("Releasing stuck ball of player {:?}", player);
+        commands.entity(player).remove::<(Sleeping, StuckToWall)>();
+    }
+}
+
+/// Releases stuck balls after [Configuration::sticky_timeout], so that a ball does not stay stuck
+/// until the end of the hole if the player does not take a shot.
+fn release_stuck_balls_after_timeout(
+    mut players: Query<(Entity, &mut StuckToWall)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (player, mut stuck) in &mut players {
+        let Some(timeout) = stuck.timeout.as_mut() else {
+            continue;
+        };
+
+        if !timeout.tick(time.delta()).finished() {
+            continue;
+        }
+
+        info!("Releasing stuck ball of player {:?} after timeout", player);
+        commands.entity(player).remove::<(Sleeping, StuckToWall)>();
+    }
+}
+
 fn remove_sticky_ball(players: Query<Entity, With<Player>>, mut commands: Commands) {
     players.iter().for_each(|entity| {
-        commands.entity(entity).remove::<StickyBall>();
+        commands
+            .entity(entity)
+            .remove::<(StickyBall, StuckToWall)>();
+    });
+}
+
+/// Removes all effects of power ups that only last until the end of a hole.
+///
+/// Bumpers placed by players are children of the hole, so they are despawned along with the course.
+fn clear_effects(
+    winds: Query<Entity, With<Wind>>,
+    players: Query<Entity, With<Player>>,
+    mut commands: Commands,
+) {
+    winds.iter().for_each(|e| commands.entity(e).despawn());
+
+    players.iter().for_each(|entity| {
+        commands
+            .entity(entity)
+            .remove::<(StickyBall, StuckToWall, HoleMagnetPowerUp)>();
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, crate::course::Hole, bevy::ecs::system::RunSystemOnce, core::time::Duration,
+        minigolf::WeightedPowerUps,
+    };
+
+    fn current_hole(hole_entity: Entity) -> CurrentHole {
+        let hole = Hole {
+            start_position: Vec3::ZERO,
+            time_limit: None,
+            gravity_scale: None,
+            deactivation_time: None,
+            sleeping_threshold: None,
+            camera_hint: None,
+            music: None,
+        };
+
+        CurrentHole::new(hole, hole_entity)
+    }
+
+    #[test]
+    fn unimplemented_power_ups_are_refunded() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<Events<ValidPlayerInput>>();
+        world.init_resource::<Events<ToClients<PowerUpNotImplemented>>>();
+        let hole = world.spawn_empty().id();
+        world.insert_resource(current_hole(hole));
+
+        let mut power_ups = PlayerPowerUps::default();
+        power_ups.retain(|_| false);
+        power_ups.add_power_up(PowerUpType::TinyBall).unwrap();
+        let player = world.spawn((Player::new(), power_ups)).id();
+        let session = world.spawn(PlayerSession { player }).id();
+
+        world.send_event(ValidPlayerInput {
+            player,
+            input: PlayerInput::TinyBall,
+        });
+        world.run_system_once(apply_power_ups).unwrap();
+
+        let refunded = world.get::<PlayerPowerUps>(player).unwrap();
+        assert_eq!(refunded.get_power_ups(), &[PowerUpType::TinyBall]);
+
+        let events = world.resource::<Events<ToClients<PowerUpNotImplemented>>>();
+        let sent = events.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(sent.len(), 1);
+        assert!(matches!(sent[0].mode, SendMode::Direct(client) if client == session));
+        assert_eq!(sent[0].event.power_up, PowerUpType::TinyBall);
+    }
+
+    #[test]
+    fn rejected_placements_leave_power_ups_intact() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<Events<ValidPlayerInput>>();
+        world.init_resource::<Events<ToClients<PowerUpNotImplemented>>>();
+        let hole = world.spawn_empty().id();
+        world.insert_resource(current_hole(hole));
+        world.spawn((
+            HoleBoundingBox::new(hole),
+            ColliderAabb {
+                min: Vector::NEG_ONE,
+                max: Vector::ONE,
+            },
+        ));
+
+        let mut power_ups = PlayerPowerUps::default();
+        power_ups.retain(|_| false);
+        power_ups.add_power_up(PowerUpType::Bumper).unwrap();
+        let player = world.spawn((Player::new(), power_ups)).id();
+
+        world.send_event(ValidPlayerInput {
+            player,
+            input: PlayerInput::Bumper(Vec3::splat(5.0)),
+        });
+        world.run_system_once(apply_power_ups).unwrap();
+
+        let refunded = world.get::<PlayerPowerUps>(player).unwrap();
+        assert_eq!(refunded.get_power_ups(), &[PowerUpType::Bumper]);
+    }
+
+    #[test]
+    fn effects_are_cleared_when_course_changes() {
+        let mut world = World::new();
+        let hole = world.spawn_empty().id();
+        let wind = world
+            .spawn(Wind {
+                direction: Vec2::X,
+                hole,
+            })
+            .id();
+        let player = world
+            .spawn((
+                Player::new(),
+                StickyBall,
+                StuckToWall::new(None),
+                HoleMagnetPowerUp::default(),
+            ))
+            .id();
+
+        world.run_system_once(clear_effects).unwrap();
+
+        assert!(world.get_entity(wind).is_err());
+        let player = world.entity(player);
+        assert!(!player.contains::<StickyBall>());
+        assert!(!player.contains::<StuckToWall>());
+        assert!(!player.contains::<HoleMagnetPowerUp>());
+    }
+
+    #[test]
+    fn active_effects_follow_applied_and_cleared_effects() {
+        let mut world = World::new();
+        let hole = world.spawn_empty().id();
+        world.insert_resource(current_hole(hole));
+        let player = world.spawn((Player::new(), ActiveEffects::default())).id();
+
+        let wind = world
+            .spawn(Wind {
+                direction: Vec2::X,
+                hole,
+            })
+            .id();
+        world.entity_mut(hole).insert(IceRink);
+        world.run_system_once(update_active_effects).unwrap();
+
+        let active = world.get::<ActiveEffects>(player).unwrap();
+        assert_eq!(active.0, [PowerUpType::Wind, PowerUpType::IceRink]);
+
+        world.despawn(wind);
+        world.entity_mut(hole).remove::<IceRink>();
+        world.run_system_once(update_active_effects).unwrap();
+
+        let active = world.get::<ActiveEffects>(player).unwrap();
+        assert!(active.0.is_empty());
+    }
+
+    #[test]
+    fn power_ups_are_only_stolen_from_other_players_in_the_game() {
+        let (thief, target) = (Player::new(), Player::new());
+        let players = [
+            (Entity::from_raw(1), &thief),
+            (Entity::from_raw(2), &target),
+        ];
+
+        assert_eq!(
+            steal_target(players, Entity::from_raw(1), target.id),
+            Some(Entity::from_raw(2))
+        );
+        assert_eq!(steal_target(players, Entity::from_raw(1), thief.id), None);
+        assert_eq!(
+            steal_target(players, Entity::from_raw(1), PlayerId::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn stuck_balls_are_released_by_next_shot() {
+        let mut world = World::new();
+        world.init_resource::<Events<ValidPlayerInput>>();
+        let stuck = world.spawn((Sleeping, StuckToWall::new(None))).id();
+        let resting = world.spawn(Sleeping).id();
+
+        world.send_event(ValidPlayerInput {
+            player: stuck,
+            input: PlayerInput::HoleMagnet,
+        });
+        world.run_system_once(release_stuck_balls).unwrap();
+        assert!(world.get::<StuckToWall>(stuck).is_some());
+
+        for player in [stuck, resting] {
+            world.send_event(ValidPlayerInput {
+                player,
+                input: PlayerInput::Move(Vec2::X),
+            });
+        }
+        world.run_system_once(release_stuck_balls).unwrap();
+
+        assert!(world.get::<StuckToWall>(stuck).is_none());
+        assert!(world.get::<Sleeping>(stuck).is_none());
+        assert!(world.get::<Sleeping>(resting).is_some());
+    }
+
+    #[test]
+    fn stuck_balls_are_released_after_timeout() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        let ball = world.spawn((Sleeping, StuckToWall::new(Some(1.0)))).id();
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(500));
+        world
+            .run_system_once(release_stuck_balls_after_timeout)
+            .unwrap();
+        assert!(world.get::<StuckToWall>(ball).is_some());
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(600));
+        world
+            .run_system_once(release_stuck_balls_after_timeout)
+            .unwrap();
+        assert!(world.get::<StuckToWall>(ball).is_none());
+        assert!(world.get::<Sleeping>(ball).is_none());
+    }
+
+    #[test]
+    fn power_ups_activate_only_after_delay() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.insert_resource(Configuration {
+            power_up_activation: PowerUpActivation::Delay(2.0),
+            ..default()
+        });
+        world.add_observer(activate_power_ups);
+        let power_up = world
+            .spawn((PowerUp::from(PowerUpType::Teleport), Replicated))
+            .id();
+
+        world.run_system_once(deactivate_power_ups).unwrap();
+        assert!(world.get::<InactivePowerUp>(power_up).is_some());
+        assert!(world.get::<Replicated>(power_up).is_none());
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world.run_system_once(tick_power_up_activation).unwrap();
+        assert!(world.get::<InactivePowerUp>(power_up).is_some());
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(2));
+        world.run_system_once(tick_power_up_activation).unwrap();
+        world.flush();
+        assert!(world.get::<InactivePowerUp>(power_up).is_none());
+        assert!(world.get::<Replicated>(power_up).is_some());
+    }
+
+    #[test]
+    fn power_ups_activate_only_after_first_completion() {
+        let mut world = World::new();
+        world.insert_resource(Configuration {
+            power_up_activation: PowerUpActivation::FirstCompletion,
+            ..default()
+        });
+        world.add_observer(activate_power_ups);
+        let power_up = world
+            .spawn((PowerUp::from(PowerUpType::Teleport), Replicated))
+            .id();
+
+        world.run_system_once(deactivate_power_ups).unwrap();
+        assert!(world.get::<InactivePowerUp>(power_up).is_some());
+        assert!(!world.contains_resource::<PowerUpActivationTimer>());
+
+        world.trigger(ActivatePowerUps);
+        world.flush();
+        assert!(world.get::<InactivePowerUp>(power_up).is_none());
+        assert!(world.get::<Replicated>(power_up).is_some());
+    }
+
+    #[test]
+    fn respawnable_power_ups_reappear_after_delay() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.insert_resource(GameRng::new(Some(1635)));
+        world.insert_resource(GameConfig::new(
+            Vec::new(),
+            None,
+            Some(WeightedPowerUps::default()),
+        ));
+
+        let mut power_ups = PlayerPowerUps::default();
+        power_ups.retain(|_| false);
+        let player = world.spawn((Player::new(), power_ups)).id();
+
+        let hole = world.spawn_empty().id();
+        let mut collisions = CollidingEntities::default();
+        collisions.insert(player);
+        let transform = Transform::from_xyz(1.0, 0.0, 2.0);
+        world.spawn((
+            power_up_bundle(PowerUpType::Teleport, transform),
+            PowerUpRespawn { delay: 2.0 },
+            collisions,
+            ChildOf(hole),
+        ));
+
+        let mut power_ups = world.query_filtered::<(&Transform, &ChildOf), With<PowerUpRespawn>>();
+
+        world.run_system_once(handle_power_up_sensors).unwrap();
+        let picked_up = world.get::<PlayerPowerUps>(player).unwrap();
+        assert_eq!(picked_up.get_power_ups(), &[PowerUpType::Teleport]);
+        assert_eq!(power_ups.iter(&world).count(), 0);
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world.run_system_once(respawn_power_ups).unwrap();
+        assert_eq!(power_ups.iter(&world).count(), 0);
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(2));
+        world.run_system_once(respawn_power_ups).unwrap();
+        let (respawned, parent) = power_ups.single(&world).unwrap();
+        assert_eq!(*respawned, transform);
+        assert_eq!(parent.parent(), hole);
+    }
+
+    #[test]
+    fn hole_magnet_activated_while_resting_affects_next_shot() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<Events<ValidPlayerInput>>();
+        world.init_resource::<Time<Fixed>>();
+        world
+            .resource_mut::<Time<Fixed>>()
+            .advance_by(Duration::from_millis(16));
+
+        let hole = world.spawn(GlobalTransform::IDENTITY).id();
+        world.insert_resource(current_hole(hole));
+
+        // the ball is at rest when the power up is activated
+        let last_position = LastPlayerPosition {
+            position: Vec3::new(0.1, 0.0, 0.0),
+            rotation: Quat::IDENTITY,
+        };
+        let player = world
+            .spawn((
+                Player::new(),
+                GlobalTransform::from_xyz(0.1, 0.0, 0.0),
+                last_position,
+                HoleMagnetPowerUp::default(),
+            ))
+            .id();
+
+        world.run_system_once(remove_hole_magnet).unwrap();
+        assert!(world.get::<HoleMagnetPowerUp>(player).is_some());
+
+        world.send_event(ValidPlayerInput {
+            player,
+            input: PlayerInput::Move(Vec2::X),
+        });
+        world.run_system_once(mark_hole_magnet_shot).unwrap();
+        world.run_system_once(apply_hole_magnet).unwrap();
+
+        let force = world.get::<ExternalForce>(player).unwrap();
+        assert!(force.force().x < 0.0);
+
+        // the ball settles after the shot
+        world
+            .get_mut::<LastPlayerPosition>(player)
+            .unwrap()
+            .position = Vec3::ZERO;
+        world.run_system_once(remove_hole_magnet).unwrap();
+        assert!(world.get::<HoleMagnetPowerUp>(player).is_none());
+    }
+}
@@ -1,16 +1,21 @@
 use {
     crate::{
-        CourseState, GameLayer,
+        Configuration, CourseState, GameLayer, GameRng,
         course::{
-            Course, CurrentHole, Hole, HoleBoundingBox, HoleSensor, HoleWalls, PhysicsConfig,
-            entities::{BallMagnet, Bumper, JumpPad},
+            Course, GameConfig, Hole, HoleBoundingBox, HoleSensor, HoleWalls, PhysicsConfig,
+            entities::{BallMagnet, Bumper, Conveyor, Hazard, JumpPad, MovingPlatform},
+            power_ups::PowerUpRespawn,
         },
+        is_valid_seconds,
     },
-    avian3d::prelude::*,
+    avian3d::{math::Scalar, prelude::*},
     bevy::prelude::*,
     bevy_replicon::prelude::*,
-    minigolf::{LevelMesh, PlayableArea, PowerUp, PowerUpType},
-    rand::Rng,
+    minigolf::{
+        CameraHint, ConveyorZone, LevelMesh, PlayableArea, PowerUp, PowerUpType,
+        mesh::extrude_outline,
+    },
+    rand::{distr::Distribution, seq::IteratorRandom},
 };
 
 /// Plugin that handles course serialization to/from files
@@ -32,7 +37,16 @@ impl Plugin for CourseSetupPlugin {
 
         app.add_systems(
             Update,
-            course_configuration_changed.run_if(resource_changed::<CourseConfiguration>),
+            (
+                course_configuration_changed.run_if(resource_changed::<CourseConfiguration>),
+                generate_hole_walls.run_if(any_with_component::<GeneratedWalls>),
+            ),
+        );
+
+        #[cfg(feature = "dev")]
+        app.add_systems(
+            Update,
+            reload_course_scene.before(course_configuration_changed),
         );
     }
 }
@@ -47,16 +61,88 @@ pub(crate) struct CourseConfiguration {
 pub(crate) struct HoleConfiguration {
     transform: Transform,
     start_position: Vec3,
+    /// Seconds the players have to complete the hole, unlimited if not set.
+    time_limit: Option<f32>,
+    /// Multiplier of the gravity while playing the hole, normal gravity if not set.
+    gravity_scale: Option<Scalar>,
+    /// Time a ball has to be at rest before it is deactivated, the server default if not set.
+    /// Fast downhill holes may need a longer time, so that balls do not stop prematurely.
+    deactivation_time: Option<Scalar>,
+    /// Velocities below which a ball is considered to be at rest, the server default if not set.
+    sleeping_threshold: Option<SleepingThreshold>,
+    /// Camera viewpoint suggested to players when the hole starts, the camera is not moved if not set.
+    camera_hint: Option<CameraHint>,
+    /// Music played to players while playing the hole, no music if not set.
+    music: Option<String>,
 
     hole_asset: String,
-    wall_asset: String,
+    /// Mesh of the walls around the hole.
+    /// If not set, walls are generated from the outline of the [hole mesh](Self::hole_asset).
+    wall_asset: Option<String>,
 
     bounding_box: Transform,
     hole_sensor: Transform,
 
     power_ups: Vec<Transform>,
+    /// How many of the [power_ups](Self::power_ups) positions to randomly spawn power ups at,
+    /// or all of them if not set.
+    power_up_count: Option<usize>,
+    /// Seconds after which a picked up power up is replaced by a new one, or never if not set.
+    power_up_respawn_delay: Option<f32>,
     bumpers: Vec<Transform>,
     jump_pads: Vec<Transform>,
+    hazards: Vec<HazardConfiguration>,
+    moving_platforms: Vec<MovingPlatformConfiguration>,
+    conveyors: Vec<ConveyorConfiguration>,
+}
+
+impl HoleConfiguration {
+    /// Gets the [power_up_respawn_delay](Self::power_up_respawn_delay),
+    /// or [None] if it is not a valid number of seconds.
+    fn power_up_respawn_delay(&self) -> Option<f32> {
+        let delay = self.power_up_respawn_delay?;
+        if !is_valid_seconds(delay) {
+            error!(
+                "Power up respawn delay of {delay} is not a valid number of seconds, power ups will not respawn"
+            );
+            return None;
+        }
+
+        Some(delay)
+    }
+}
+
+/// Area that gives a penalty stroke to balls entering it, such as water.
+#[derive(Reflect)]
+struct HazardConfiguration {
+    /// Transform of a unit cube covering the area.
+    transform: Transform,
+    /// Position the ball is moved to after entering the hazard,
+    /// the last position where the ball was at rest if not set.
+    drop_zone: Option<Vec3>,
+}
+
+/// Area that pushes balls in a direction, like a conveyor belt.
+#[derive(Reflect)]
+struct ConveyorConfiguration {
+    /// Transform of a unit cube covering the area.
+    transform: Transform,
+    /// Direction in which balls are pushed, relative to the rotation of the conveyor.
+    direction: Vec3,
+    strength: Scalar,
+}
+
+/// Platform that moves back and forth along a path.
+#[derive(Reflect)]
+struct MovingPlatformConfiguration {
+    /// Rotation and scale of the platform, the translation is determined by the [path](Self::path).
+    transform: Transform,
+    /// Mesh of the platform, also used for its collider.
+    asset: String,
+    /// Positions relative to the hole which the platform moves between, starting at the first one.
+    path: Vec<Vec3>,
+    /// Distance travelled along the path per second.
+    speed: f32,
 }
 
 /// Updates [CourseConfiguration] resource with the current values of the course,
@@ -72,33 +158,110 @@ pub(crate) fn capture_course_state(
     power_ups: Query<&Transform, With<PowerUp>>,
     bumpers: Query<&Transform, With<Bumper>>,
     jump_pads: Query<&Transform, With<JumpPad>>,
+    hazards: Query<(&Transform, &Hazard)>,
+    moving_platforms: Query<(&Transform, &MovingPlatform, &LevelMesh)>,
+    conveyors: Query<(&Transform, &Conveyor)>,
 ) {
-    config.holes = course
+    let holes = course
         .holes
         .iter()
-        .map(|hole| {
+        .enumerate()
+        .map(|(index, hole)| {
             let (transform, hole, mesh, children) = holes.get(*hole).unwrap();
 
-            let walls_mesh = map_single_component(children, walls);
+            let walls_mesh = children.iter().find_map(|entity| walls.get(entity).ok());
             let bounding_transform = map_single_component(children, bounding_box);
             let sensor_transform = map_single_component(children, hole_sensor);
 
             HoleConfiguration {
                 transform: transform.to_owned(),
                 start_position: hole.start_position.to_owned(),
+                time_limit: hole.time_limit,
+                gravity_scale: hole.gravity_scale,
+                deactivation_time: hole.deactivation_time,
+                sleeping_threshold: hole.sleeping_threshold,
+                camera_hint: hole.camera_hint,
+                music: hole.music.clone(),
 
                 hole_asset: mesh.asset.to_owned(),
-                wall_asset: walls_mesh.asset,
+                wall_asset: walls_mesh
+                    .filter(|mesh| mesh.outline_height.is_none())
+                    .map(|mesh| mesh.asset.to_owned()),
 
                 bounding_box: bounding_transform,
                 hole_sensor: sensor_transform,
 
                 power_ups: map_components(children, power_ups),
+                power_up_count: config.holes.get(index).and_then(|hole| hole.power_up_count),
+                power_up_respawn_delay: config
+                    .holes
+                    .get(index)
+                    .and_then(|hole| hole.power_up_respawn_delay),
                 bumpers: map_components(children, bumpers),
                 jump_pads: map_components(children, jump_pads),
+                hazards: children
+                    .iter()
+                    .filter_map(|entity| hazards.get(entity).ok())
+                    .map(|(transform, hazard)| HazardConfiguration {
+                        transform: *transform,
+                        drop_zone: hazard.drop_zone,
+                    })
+                    .collect(),
+                moving_platforms: children
+                    .iter()
+                    .filter_map(|entity| moving_platforms.get(entity).ok())
+                    .map(|(transform, platform, mesh)| MovingPlatformConfiguration {
+                        transform: *transform,
+                        asset: mesh.asset.to_owned(),
+                        path: platform.path.clone(),
+                        speed: platform.speed,
+                    })
+                    .collect(),
+                conveyors: children
+                    .iter()
+                    .filter_map(|entity| conveyors.get(entity).ok())
+                    .map(|(transform, conveyor)| ConveyorConfiguration {
+                        transform: *transform,
+                        direction: conveyor.direction,
+                        strength: conveyor.strength,
+                    })
+                    .collect(),
             }
         })
         .collect::<Vec<_>>();
+
+    config.holes = holes;
+}
+
+/// Despawns the current course when its scene file is modified.
+///
+/// The scene spawner reapplies the modified scene, which replaces [CourseConfiguration],
+/// so the course is then spawned again with the new configuration.
+#[cfg(feature = "dev")]
+fn reload_course_scene(
+    mut events: EventReader<AssetEvent<DynamicScene>>,
+    scene: Option<Single<&DynamicSceneRoot, With<super::CourseSceneMarker>>>,
+    courses: Query<Entity, With<Course>>,
+    mut commands: Commands,
+) {
+    let Some(scene) = scene else {
+        events.clear();
+        return;
+    };
+
+    // read all events, so that the same modification is not handled again in the next frame
+    let modified = events
+        .read()
+        .filter(|event| event.is_modified(scene.id()))
+        .count();
+    if modified == 0 {
+        return;
+    }
+
+    info!("Course scene was modified, reloading the course");
+    for course in &courses {
+        commands.entity(course).despawn();
+    }
 }
 
 #[cfg(feature = "dev")]
@@ -129,6 +292,9 @@ fn map_components<TComponent: Component + Clone, TTFilter: Component>(
 fn course_configuration_changed(
     config: Res<CourseConfiguration>,
     physics_config: Res<PhysicsConfig>,
+    configuration: Res<Configuration>,
+    game_config: Option<Res<GameConfig>>,
+    mut rng: ResMut<GameRng>,
     mut commands: Commands,
     server: Res<AssetServer>,
 ) {
@@ -136,6 +302,8 @@ fn course_configuration_changed(
         return;
     }
 
+    let rng = &mut **rng;
+
     let course = commands
         .spawn((
             Name::new("Course"),
@@ -150,14 +318,18 @@ fn course_configuration_changed(
     for (index, hole_config) in config.holes.iter().enumerate() {
         let floor_path = &hole_config.hole_asset;
         let floor_handle: Handle<Mesh> = server.load(floor_path);
-        let walls_path = &hole_config.wall_asset;
-        let walls_handle: Handle<Mesh> = server.load(walls_path);
 
         let hole_entity = commands
             .spawn((
                 Name::new(format!("Hole {index}")),
                 Hole {
                     start_position: hole_config.start_position,
+                    time_limit: hole_config.time_limit,
+                    gravity_scale: hole_config.gravity_scale,
+                    deactivation_time: hole_config.deactivation_time,
+                    sleeping_threshold: hole_config.sleeping_threshold,
+                    camera_hint: hole_config.camera_hint,
+                    music: hole_config.music.clone(),
                 },
                 hole_config.transform,
                 PlayableArea,
@@ -170,18 +342,31 @@ fn course_configuration_changed(
             .insert(physics_config.floor.default_components())
             .id();
 
-        commands
-            .spawn((
-                Name::new(format!("Hole {index} walls")),
-                Transform::IDENTITY,
-                HoleWalls { hole_entity },
-                Replicated,
-                Mesh3d(walls_handle),
-                LevelMesh::from_path(walls_path),
-                ColliderConstructor::TrimeshFromMeshWithConfig(TrimeshFlags::all()),
-                ChildOf(hole_entity),
-            ))
-            .insert(physics_config.walls.default_components());
+        let mut walls = commands.spawn((
+            Name::new(format!("Hole {index} walls")),
+            Transform::IDENTITY,
+            HoleWalls { hole_entity },
+            Replicated,
+            ChildOf(hole_entity),
+        ));
+        walls.insert(physics_config.walls.default_components());
+
+        match &hole_config.wall_asset {
+            Some(walls_path) => {
+                let walls_handle: Handle<Mesh> = server.load(walls_path);
+                walls.insert((
+                    Mesh3d(walls_handle),
+                    LevelMesh::from_path(walls_path),
+                    ColliderConstructor::TrimeshFromMeshWithConfig(TrimeshFlags::all()),
+                ));
+            }
+            None => {
+                walls.insert((
+                    GeneratedWalls,
+                    LevelMesh::outline_of(floor_path, GENERATED_WALL_HEIGHT),
+                ));
+            }
+        }
 
         commands.spawn((
             Name::new(format!("Hole {index} bounding box")),
@@ -202,18 +387,35 @@ fn course_configuration_changed(
             ChildOf(hole_entity),
         ));
 
-        hole_config.power_ups.iter().for_each(|transform| {
-            commands.spawn((
-                Name::new("Power up"),
-                *transform,
-                Sensor,
-                RigidBody::Static,
-                CollisionLayers::new(GameLayer::Default, [GameLayer::Player]),
-                ColliderConstructor::Sphere { radius: 0.1 },
-                PowerUp::from(rand::rng().random::<PowerUpType>()),
-                Replicated,
-                ChildOf(hole_entity),
-            ));
+        let power_up_count = hole_config
+            .power_up_count
+            .unwrap_or(hole_config.power_ups.len());
+
+        let power_up_transforms = hole_config
+            .power_ups
+            .iter()
+            .choose_multiple(rng, power_up_count);
+        let power_up_respawn_delay = hole_config.power_up_respawn_delay();
+
+        power_up_transforms.into_iter().for_each(|transform| {
+            // the course can also be loaded outside of a game, for example while editing it
+            let power_up = match &game_config {
+                Some(game_config) => game_config.sample_power_up(rng),
+                None => configuration
+                    .power_up_weights_of(configuration.enabled_power_ups.as_deref())
+                    .map(|power_ups| power_ups.sample(rng)),
+            };
+
+            let Some(power_up) = power_up else {
+                return;
+            };
+
+            let mut power_up =
+                commands.spawn((power_up_bundle(power_up, *transform), ChildOf(hole_entity)));
+
+            if let Some(delay) = power_up_respawn_delay {
+                power_up.insert(PowerUpRespawn { delay });
+            }
         });
 
         hole_config.bumpers.iter().for_each(|transform| {
@@ -241,6 +443,101 @@ fn course_configuration_changed(
                 ChildOf(hole_entity),
             ));
         });
+
+        hole_config.hazards.iter().for_each(|hazard| {
+            commands.spawn((
+                Name::new("Hazard"),
+                Hazard {
+                    drop_zone: hazard.drop_zone,
+                },
+                hazard.transform,
+                Replicated,
+                ChildOf(hole_entity),
+            ));
+        });
+
+        hole_config.conveyors.iter().for_each(|conveyor| {
+            commands.spawn((
+                Name::new("Conveyor"),
+                Conveyor {
+                    direction: conveyor.direction,
+                    strength: conveyor.strength,
+                },
+                ConveyorZone {
+                    direction: conveyor.direction,
+                },
+                conveyor.transform,
+                Replicated,
+                ChildOf(hole_entity),
+            ));
+        });
+
+        hole_config
+            .moving_platforms
+            .iter()
+            .for_each(|platform_config| {
+                let platform = MovingPlatform {
+                    path: platform_config.path.clone(),
+                    speed: platform_config.speed,
+                    elapsed: 0.0,
+                };
+
+                let transform = platform_config
+                    .transform
+                    .with_translation(platform.position_at(0.0));
+
+                commands.spawn((
+                    Name::new("Moving platform"),
+                    platform,
+                    transform,
+                    Mesh3d(server.load(&platform_config.asset)),
+                    LevelMesh::from_path(&platform_config.asset),
+                    ColliderConstructor::ConvexHullFromMesh,
+                    physics_config.floor.default_components(),
+                    Replicated,
+                    ChildOf(hole_entity),
+                ));
+            });
+    }
+}
+
+/// Height of the walls generated for holes without a [wall mesh](HoleConfiguration::wall_asset).
+const GENERATED_WALL_HEIGHT: f32 = 0.1;
+
+/// Marks [HoleWalls] which need to be generated from the hole mesh once it is loaded.
+#[derive(Component, Debug)]
+struct GeneratedWalls;
+
+fn generate_hole_walls(
+    walls: Query<(Entity, &HoleWalls), With<GeneratedWalls>>,
+    holes: Query<&Mesh3d, With<Hole>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    for (entity, hole_walls) in walls.iter() {
+        let Ok(floor) = holes.get(hole_walls.hole_entity) else {
+            continue;
+        };
+
+        let Some(floor_mesh) = meshes.get(floor.id()) else {
+            continue;
+        };
+
+        let mut walls_entity = commands.entity(entity);
+        walls_entity.remove::<GeneratedWalls>();
+
+        let Some(walls_mesh) = extrude_outline(floor_mesh, GENERATED_WALL_HEIGHT) else {
+            warn!(
+                "Could not generate walls for hole {}",
+                hole_walls.hole_entity
+            );
+            continue;
+        };
+
+        walls_entity.insert((
+            Mesh3d(meshes.add(walls_mesh)),
+            ColliderConstructor::TrimeshFromMeshWithConfig(TrimeshFlags::all()),
+        ));
     }
 }
 
@@ -248,35 +545,47 @@ fn course_configuration_changed(
 pub(crate) struct SpawnBumper {
     transform: Transform,
     permanent: bool,
+    /// Hole on which the bumper is placed.
+    hole: Entity,
 }
 
 impl SpawnBumper {
-    /// Spawn a bumper which will despawn after certain amount of hits.
-    pub(crate) fn with_hits(transform: Transform) -> Self {
+    /// Spawn a bumper on the hole which will despawn after certain amount of hits.
+    pub(crate) fn with_hits(transform: Transform, hole: Entity) -> Self {
         Self {
             transform,
             permanent: false,
+            hole,
         }
     }
 }
 
 const BUMPER_HITS: usize = 3; // todo: hits based on player count?
 
-fn spawn_bumper_trigger(
-    trigger: Trigger<SpawnBumper>,
-    current_hole: Res<CurrentHole>,
-    mut commands: Commands,
-) {
+fn spawn_bumper_trigger(trigger: Trigger<SpawnBumper>, mut commands: Commands) {
     commands.spawn(bumper_bundle(
         match trigger.permanent {
             true => Bumper::permanent(),
             false => Bumper::with_hits(BUMPER_HITS),
         },
         trigger.transform,
-        current_hole.hole_entity,
+        trigger.hole,
     ));
 }
 
+pub(crate) fn power_up_bundle(power_up: PowerUpType, transform: Transform) -> impl Bundle {
+    (
+        Name::new("Power up"),
+        transform,
+        Sensor,
+        RigidBody::Static,
+        CollisionLayers::new(GameLayer::Default, [GameLayer::Player]),
+        ColliderConstructor::Sphere { radius: 0.1 },
+        PowerUp::from(power_up),
+        Replicated,
+    )
+}
+
 fn bumper_bundle(bumper: Bumper, transform: Transform, hole_entity: Entity) -> impl Bundle {
     let asset_path = "Entities.glb#Mesh1/Primitive0";
 
@@ -294,23 +603,22 @@ fn bumper_bundle(bumper: Bumper, transform: Transform, hole_entity: Entity) -> i
 pub(crate) struct SpawnBlackHoleBumper {
     transform: Transform,
     permanent: bool,
+    /// Hole on which the bumper is placed.
+    hole: Entity,
 }
 
 impl SpawnBlackHoleBumper {
-    /// Spawn a black hole bumper which will despawn after certain amount of hits.
-    pub(crate) fn with_hits(transform: Transform) -> Self {
+    /// Spawn a black hole bumper on the hole which will despawn after certain amount of hits.
+    pub(crate) fn with_hits(transform: Transform, hole: Entity) -> Self {
         Self {
             transform,
             permanent: false,
+            hole,
         }
     }
 }
 
-fn spawn_black_hole_bumper_trigger(
-    trigger: Trigger<SpawnBlackHoleBumper>,
-    current_hole: Res<CurrentHole>,
-    mut commands: Commands,
-) {
+fn spawn_black_hole_bumper_trigger(trigger: Trigger<SpawnBlackHoleBumper>, mut commands: Commands) {
     let asset_path = "Entities.glb#Mesh1/Primitive0"; // todo: different from default bumper
 
     commands.spawn((
@@ -322,7 +630,83 @@ fn spawn_black_hole_bumper_trigger(
         trigger.transform,
         Replicated,
         LevelMesh::from_path(asset_path),
-        ChildOf(current_hole.hole_entity),
+        ChildOf(trigger.hole),
         children![(Name::new("Ball magnet"), BallMagnet::default(),)],
     ));
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        bevy::reflect::{
+            TypeRegistry,
+            serde::{TypedReflectDeserializer, TypedReflectSerializer},
+        },
+        core::any::TypeId,
+        serde::de::DeserializeSeed,
+    };
+
+    #[test]
+    fn hole_music_is_serialized_with_course_configuration() {
+        let hole = HoleConfiguration {
+            transform: Transform::IDENTITY,
+            start_position: Vec3::ZERO,
+            time_limit: None,
+            gravity_scale: None,
+            deactivation_time: None,
+            sleeping_threshold: None,
+            camera_hint: None,
+            music: Some("music/0002.ogg".to_owned()),
+            hole_asset: "courses/0002.glb#Mesh0/Primitive0".to_owned(),
+            wall_asset: None,
+            bounding_box: Transform::IDENTITY,
+            hole_sensor: Transform::IDENTITY,
+            power_ups: vec![],
+            power_up_count: None,
+            power_up_respawn_delay: None,
+            bumpers: vec![],
+            jump_pads: vec![],
+            hazards: vec![],
+            moving_platforms: vec![],
+            conveyors: vec![],
+        };
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<HoleConfiguration>();
+
+        let serialized =
+            serde_json::to_string(&TypedReflectSerializer::new(&hole, &registry)).unwrap();
+        assert!(serialized.contains("music/0002.ogg"));
+
+        let registration = registry.get(TypeId::of::<HoleConfiguration>()).unwrap();
+        let deserialized = TypedReflectDeserializer::new(registration, &registry)
+            .deserialize(&mut serde_json::Deserializer::from_str(&serialized))
+            .unwrap();
+        let hole = HoleConfiguration::from_reflect(&*deserialized).unwrap();
+        assert_eq!(hole.music.as_deref(), Some("music/0002.ogg"));
+    }
+
+    #[test]
+    #[cfg(feature = "dev")]
+    fn modified_course_scene_reloads_the_course() {
+        use {crate::course::CourseSceneMarker, bevy::ecs::system::RunSystemOnce};
+
+        let mut world = World::new();
+        world.init_resource::<Events<AssetEvent<DynamicScene>>>();
+        let scene = Handle::<DynamicScene>::weak_from_u128(1);
+        world.spawn((DynamicSceneRoot(scene.clone()), CourseSceneMarker));
+        let course = world.spawn(Course::new()).id();
+
+        let other_scene = Handle::<DynamicScene>::weak_from_u128(2);
+        world.send_event(AssetEvent::Modified {
+            id: other_scene.id(),
+        });
+        world.run_system_once(reload_course_scene).unwrap();
+        assert!(world.get_entity(course).is_ok());
+
+        world.send_event(AssetEvent::Modified { id: scene.id() });
+        world.run_system_once(reload_course_scene).unwrap();
+        assert!(world.get_entity(course).is_err());
+    }
+}
@@ -1,23 +1,55 @@
 use {
     crate::{
+        chat::ChatPlugin,
         config::ServerPlugin,
-        course::{CoursePlugin, CurrentHole, HoleSensor, power_ups::ChipShotMarker},
+        course::{
+            CompletePlayerHole, CoursePlugin, CurrentHole, GameConfig, HoleProgression, HoleSensor,
+            HoleStrokes, PlayerHole,
+            power_ups::{ActivatePowerUps, ChipShotMarker, PowerUpActivation},
+            setup::power_up_bundle,
+        },
         network::{PlayerAuthenticated, ServerNetworkPlugin},
+        recording::{RecordingPlugin, is_replaying},
+        turns::TurnsPlugin,
     },
     aeronet::io::connection::Disconnected,
     avian3d::{math::Scalar, prelude::*},
     bevy::prelude::*,
     bevy_replicon::prelude::*,
-    minigolf::{CourseDetails, MinigolfPlugin, Player, PlayerInput, PlayerPowerUps, PlayerScore},
+    minigolf::{
+        AbandonedBall, CourseDetails, DisconnectedPlayer, GamePhase, HoleInOne, MAX_SHOT_FORCE,
+        MinigolfPlugin, PinDistance, Player, PlayerInput, PlayerPowerUps, PlayerScore, PowerUpType,
+        ScoringMode, WeightedPowerUps,
+    },
+    rand::{SeedableRng, rngs::StdRng},
     std::{
+        f32::consts::TAU,
         net::{IpAddr, Ipv6Addr, SocketAddr},
         path::PathBuf,
+        time::Duration,
     },
 };
 
+mod chat;
 mod config;
 mod course;
 mod network;
+mod recording;
+mod turns;
+
+/// Time a body has to be below the [sleeping threshold](SLEEPING_THRESHOLD) before it is deactivated,
+/// unless overridden by the current hole.
+const DEACTIVATION_TIME: Scalar = 0.2;
+/// Velocities below which a body is considered to be at rest, unless overridden by the current hole.
+const SLEEPING_THRESHOLD: SleepingThreshold = SleepingThreshold {
+    angular: 10.0,
+    linear: 1.0,
+};
+
+/// Radius of the ball of a player.
+const BALL_RADIUS: Scalar = 0.021336;
+/// Mass of the ball of a player, unless changed by a power up.
+const BALL_MASS: Scalar = minigolf::BALL_MASS as Scalar;
 
 fn main() -> AppExit {
     App::new()
@@ -30,27 +62,40 @@ fn main() -> AppExit {
             PhysicsDebugPlugin::default(),
         ))
         .add_plugins(StatesPlugin)
-        .add_plugins(CoursePlugin)
+        .add_plugins((CoursePlugin, ChatPlugin, RecordingPlugin, TurnsPlugin))
         .add_observer(on_disconnected)
         .insert_resource(Time::<Fixed>::from_hz(128.0))
-        .insert_resource(SubstepCount(8))
         .insert_resource(PhysicsLengthUnit(0.005))
-        .insert_resource(DeactivationTime(0.2))
-        .insert_resource(SleepingThreshold {
-            angular: 10.0,
-            linear: 1.0,
-            ..default()
-        })
+        .insert_resource(DeactivationTime(DEACTIVATION_TIME))
+        .insert_resource(SLEEPING_THRESHOLD)
         .register_type::<Configuration>()
         .init_resource::<Configuration>()
-        .add_systems(Startup, load_configuration)
+        .init_resource::<GameRng>()
+        .add_systems(Startup, (load_configuration, configure_physics_debug))
+        .add_systems(
+            Update,
+            (validate_configuration, apply_physics_configuration)
+                .chain()
+                .run_if(resource_changed::<Configuration>),
+        )
+        .add_systems(OnEnter(ServerState::Playing), reset_game_rng)
         .add_systems(FixedPreUpdate, bevy_replicon::server::increment_tick)
-        .add_systems(FixedUpdate, recv_input.run_if(server_or_singleplayer))
+        .add_systems(
+            FixedUpdate,
+            recv_input.run_if(server_or_singleplayer.and(not(is_replaying))),
+        )
         .add_systems(
             Update,
             on_player_authenticated.in_set(WaitingForPlayersSystems),
         )
-        .add_systems(FixedUpdate, player_can_move.in_set(PlayingSystems))
+        .add_systems(
+            FixedUpdate,
+            (
+                player_can_move,
+                apply_pending_moves.after(player_can_move).after(recv_input),
+            )
+                .in_set(PlayingSystems),
+        )
         .add_systems(Update, (move_player, reset_can_move).in_set(PlayingSystems))
         .add_event::<ValidPlayerInput>()
         .run()
@@ -70,6 +115,18 @@ impl Plugin for StatesPlugin {
         app.register_type::<CourseState>();
         app.register_type::<HoleState>();
 
+        #[cfg(feature = "state_log")]
+        app.add_systems(
+            Last,
+            (
+                log_state_transitions::<ServerState>,
+                log_state_transitions::<GameState>,
+                log_state_transitions::<CourseState>,
+                log_state_transitions::<HoleState>,
+            )
+                .chain(),
+        );
+
         app.configure_sets(
             Update,
             ConnectingToLobbySystems.run_if(in_state(ServerState::WaitingForLobby)),
@@ -112,6 +169,27 @@ impl Plugin for StatesPlugin {
             PlayingSystems.run_if(in_state(HoleState::Playing)),
         );
 
+        app.add_systems(
+            OnEnter(ServerState::Playing),
+            (spawn_game_phase, spawn_scoring_mode),
+        );
+        app.add_systems(
+            OnEnter(HoleState::Playing),
+            set_game_phase(GamePhase::Playing),
+        );
+        app.add_systems(
+            OnEnter(HoleState::Completed),
+            set_game_phase(GamePhase::HoleComplete),
+        );
+        app.add_systems(
+            OnEnter(CourseState::Completed),
+            set_game_phase(GamePhase::CourseComplete),
+        );
+        app.add_systems(
+            OnEnter(GameState::Completed),
+            set_game_phase(GamePhase::GameComplete),
+        );
+
         app.add_systems(
             OnEnter(GameState::Completed),
             |mut state: ResMut<NextState<ServerState>>| state.set(ServerState::WaitingForGame),
@@ -119,6 +197,50 @@ impl Plugin for StatesPlugin {
     }
 }
 
+fn spawn_game_phase(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Game phase"),
+        GamePhase::default(),
+        Replicated,
+        StateScoped(ServerState::Playing),
+    ));
+}
+
+fn spawn_scoring_mode(configuration: Res<Configuration>, mut commands: Commands) {
+    commands.spawn((
+        Name::new("Scoring mode"),
+        configuration.scoring_mode,
+        Replicated,
+        StateScoped(ServerState::Playing),
+    ));
+}
+
+/// Replicates the phase of the game to the clients, see [GamePhase].
+fn set_game_phase(phase: GamePhase) -> impl FnMut(Query<&mut GamePhase>) {
+    move |mut phases: Query<&mut GamePhase>| {
+        for mut current_phase in &mut phases {
+            *current_phase = phase;
+        }
+    }
+}
+
+/// Logs the transitions of the state `S`, to help trace how the nested states change.
+#[cfg(feature = "state_log")]
+fn log_state_transitions<S: States>(
+    mut reader: EventReader<StateTransitionEvent<S>>,
+    time: Res<Time<Real>>,
+) {
+    for transition in reader.read() {
+        debug!(
+            "{:.3}s {}: {:?} -> {:?}",
+            time.elapsed_secs(),
+            core::any::type_name::<S>(),
+            transition.exited,
+            transition.entered
+        );
+    }
+}
+
 #[derive(States, Reflect, Default, Clone, Eq, PartialEq, Hash, Debug)]
 #[states(scoped_entities)]
 enum ServerState {
@@ -163,6 +285,8 @@ enum HoleState {
     Playing,
     /// All the players have completed the hole.
     Completed,
+    /// Break before the next hole, see [Configuration::intermission_interval].
+    Intermission,
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
@@ -207,16 +331,41 @@ pub(crate) struct Args {
     /// Certificate to use for the WebSocket and WebTransport servers
     #[arg(long)]
     pub(crate) certificate_filepath: Option<PathBuf>,
-    /// Private key for [certificate_filepath]
+    /// Private key for the certificate file
     #[arg(long)]
     pub(crate) private_key_filepath: Option<PathBuf>,
+    /// Origins from which web clients are allowed to connect, all origins are allowed if not specified
+    #[arg(long)]
+    pub(crate) allowed_origins: Vec<String>,
 
-    /// Address to publish for clients to connect to the server
+    /// Address to publish for clients to connect to the server,
+    /// instead of one built from the publish host and WebSocket port
     #[arg(long)]
     pub(crate) publish_address: Option<String>,
+    /// Host to publish for clients to connect to the server
+    #[arg(long, default_value = "localhost")]
+    pub(crate) publish_host: String,
     /// The address of the minigolf lobby server
     #[arg(long, default_value_t = LOBBY_ADDRESS)]
     pub(crate) lobby_address: SocketAddr,
+    /// Number of times to retry connecting to the lobby server before exiting
+    #[arg(long, default_value_t = 5)]
+    pub(crate) lobby_retries: usize,
+
+    /// Seed for gameplay randomness, so that games can be reproduced; random if not specified
+    #[arg(long)]
+    pub(crate) seed: Option<u64>,
+
+    /// File to record the inputs of each game to, for replaying it later
+    #[arg(long)]
+    pub(crate) record: Option<PathBuf>,
+    /// File with recorded inputs to replay in each game, requires the same seed as the recording
+    #[arg(long)]
+    pub(crate) replay: Option<PathBuf>,
+
+    /// Render colliders and other physics debug gizmos
+    #[arg(long)]
+    pub(crate) physics_debug: bool,
 }
 
 impl Args {
@@ -224,8 +373,28 @@ impl Args {
         if let Some(address) = &self.publish_address {
             address.clone()
         } else {
-            format!("ws://localhost:{}", &self.web_socket_port)
+            let scheme = if self.is_encrypted() { "wss" } else { "ws" };
+            format!("{scheme}://{}:{}", self.publish_host, self.web_socket_port)
+        }
+    }
+
+    /// Whether a client connecting from the specified origin is allowed to connect,
+    /// see [Args::allowed_origins].
+    pub(crate) fn is_origin_allowed(&self, origin: Option<&str>) -> bool {
+        if self.allowed_origins.is_empty() {
+            return true;
         }
+
+        origin.is_some_and(|origin| {
+            self.allowed_origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+        })
+    }
+
+    /// Whether the certificate for a secure connection was provided.
+    pub(crate) fn is_encrypted(&self) -> bool {
+        self.certificate_filepath.is_some() && self.private_key_filepath.is_some()
     }
 }
 
@@ -243,6 +412,19 @@ pub(crate) struct PlayerSession {
 #[derive(Resource, Reflect, Debug)]
 #[reflect(Resource)]
 pub(crate) struct Configuration {
+    /// Number of physics substeps per fixed update.
+    /// More substeps make collisions of the fast moving ball more accurate, but use more CPU time.
+    pub(crate) substep_count: u32,
+
+    /// Seconds to wait for the players from the lobby to join, after which the missing players are removed.
+    pub(crate) join_timeout: f32,
+    /// Seconds after being issued by the lobby server after which player credentials are rejected,
+    /// never expire if not set.
+    pub(crate) credentials_ttl: Option<f32>,
+
+    /// Height above the start position at which balls are spawned, so that they do not clip into the floor.
+    pub(crate) spawn_height: f32,
+
     pub(crate) wind_strength: Scalar,
 
     pub(crate) hole_magnet_min_distance: f32,
@@ -253,12 +435,93 @@ pub(crate) struct Configuration {
 
     pub(crate) jump_pad_strength: f64,
 
+    /// Seconds during which repeated collisions between the same entities are ignored by bumpers,
+    /// jump pads and sticky effects, so that a single bounce is not handled several times.
+    pub(crate) collision_cooldown: f32,
+    /// Seconds after which a ball stopped by a sticky effect is released,
+    /// stays stuck until the next shot if not set.
+    pub(crate) sticky_timeout: Option<f32>,
+    /// Maximum number of bumpers and winds placed by players that can be active on a hole at once,
+    /// further placements are refunded; unlimited if not set.
+    pub(crate) max_placed_entities: Option<usize>,
+
+    /// How likely each power up is to be spawned on a course.
+    pub(crate) power_up_weights: WeightedPowerUps,
+    /// Power ups that can be spawned and used, all implemented power ups if not set.
+    /// Can be overridden for each game by the lobby server.
+    pub(crate) enabled_power_ups: Option<Vec<PowerUpType>>,
+    /// When the power ups of a hole can be picked up, they are hidden from players until then.
+    pub(crate) power_up_activation: PowerUpActivation,
+
+    /// Seconds after which the game is completed, even if there are holes left; unlimited if not set.
+    pub(crate) max_game_duration: Option<f32>,
+    /// Seconds after which a hole is completed for all remaining players, even if it has no time limit;
+    /// unlimited if not set.
+    ///
+    /// Ensures that the game continues if a player stops playing without disconnecting.
+    pub(crate) max_hole_duration: Option<f32>,
+
+    /// Number of holes after which there is an intermission, no intermissions if not set.
+    pub(crate) intermission_interval: Option<usize>,
+    /// Length of an intermission in seconds.
+    pub(crate) intermission_duration: f32,
+
+    /// Minimum force of a shot, weaker shots are ignored and do not count as a stroke.
+    pub(crate) min_shot_force: f32,
+    /// Distance from the cup within which shots are weaker for finer control, disabled if not set.
+    pub(crate) putting_distance: Option<f32>,
+    /// Maximum force of a shot within [Configuration::putting_distance] of the cup.
+    pub(crate) putting_max_force: f32,
+    /// Maximum angle in degrees above or below the ground at which putts can be aimed,
+    /// steeper shots require the [PowerUpType::ChipShot] power up.
+    pub(crate) max_putt_angle: f32,
+    /// How the force of a shot requested by a player is mapped to the applied impulse.
+    pub(crate) shot_curve: ShotCurve,
+    /// Whether the impulse of a shot is scaled by the mass of the ball relative to [BALL_MASS],
+    /// so that a shot of the same force moves balls of any size equally fast.
+    pub(crate) scale_shots_by_mass: bool,
+
+    /// Speed below which a ball overlapping the cup is stopped, so that it sinks instead of rolling over it.
+    /// Faster balls pass over the cup, disabled if not set.
+    pub(crate) cup_capture_speed: Option<Scalar>,
+    /// Distance from the cup within which a ball at rest completes the hole, disabled if not set.
+    pub(crate) gimme_radius: Option<f32>,
+    /// Strokes added for completing a hole within [Configuration::gimme_radius].
+    pub(crate) gimme_strokes: u32,
+    /// Strokes added when a ball enters a hazard.
+    pub(crate) hazard_penalty: u32,
+
+    /// Whether players take turns shooting, instead of all shooting at the same time.
+    pub(crate) turn_based: bool,
+    /// Whether players wait for each other at the end of each hole.
+    pub(crate) hole_progression: HoleProgression,
+    /// How the players are ranked in the standings.
+    pub(crate) scoring_mode: ScoringMode,
+
+    /// Strokes subtracted from the score of a player that completes a hole with a single stroke.
+    pub(crate) hole_in_one_bonus: u32,
+
+    /// Whether the power ups of a disconnected player are dropped for other players to pick up.
+    pub(crate) drop_power_ups_on_disconnect: bool,
+    /// Whether the ball of a disconnected player is left on the hole as an obstacle until the hole is completed.
+    ///
+    /// The ball is left as soon as the player disconnects, since there is no grace period for reconnecting
+    /// before the player is removed from the game.
+    pub(crate) keep_disconnected_balls: bool,
+
     pub(crate) courses: Vec<CourseDetails>,
 }
 
 impl Default for Configuration {
     fn default() -> Self {
         Configuration {
+            substep_count: 8,
+
+            join_timeout: 60.0,
+            credentials_ttl: None,
+
+            spawn_height: 0.01,
+
             wind_strength: 0.3,
 
             hole_magnet_min_distance: 0.05,
@@ -269,11 +532,210 @@ impl Default for Configuration {
 
             jump_pad_strength: 0.2,
 
+            collision_cooldown: 0.1,
+            sticky_timeout: None,
+            max_placed_entities: None,
+
+            power_up_weights: WeightedPowerUps::default(),
+            enabled_power_ups: None,
+            power_up_activation: PowerUpActivation::Immediately,
+
+            max_game_duration: None,
+            max_hole_duration: None,
+
+            intermission_interval: None,
+            intermission_duration: 5.0,
+
+            min_shot_force: 0.01,
+            putting_distance: None,
+            putting_max_force: 0.3,
+            max_putt_angle: 15.0,
+            shot_curve: ShotCurve::Linear,
+            scale_shots_by_mass: true,
+
+            cup_capture_speed: None,
+            gimme_radius: None,
+            gimme_strokes: 1,
+            hazard_penalty: 1,
+
+            turn_based: false,
+            hole_progression: HoleProgression::Together,
+            scoring_mode: ScoringMode::Strokes,
+
+            hole_in_one_bonus: 0,
+
+            drop_power_ups_on_disconnect: false,
+            keep_disconnected_balls: false,
+
             courses: vec![],
         }
     }
 }
 
+impl Configuration {
+    /// Gets the weights of the enabled power ups, or [None] if none of them can be spawned.
+    pub(crate) fn power_up_weights_of(
+        &self,
+        enabled: Option<&[PowerUpType]>,
+    ) -> Option<WeightedPowerUps> {
+        match enabled {
+            Some(enabled) => self.power_up_weights.restrict_to(enabled),
+            None => Some(self.power_up_weights.clone()),
+        }
+    }
+
+    /// Gets the maximum force of a shot taken at the specified distance from the cup.
+    pub(crate) fn max_shot_force(&self, distance_to_cup: Option<f32>) -> f32 {
+        match (self.putting_distance, distance_to_cup) {
+            (Some(putting_distance), Some(distance)) if distance <= putting_distance => {
+                self.putting_max_force.min(MAX_SHOT_FORCE)
+            }
+            _ => MAX_SHOT_FORCE,
+        }
+    }
+
+    /// Replaces values that would panic or produce NaN or infinite shots, logging an error for each of them.
+    fn validate(&mut self) {
+        let defaults = Configuration::default();
+
+        if !self.shot_curve.is_valid() {
+            error!(
+                "Shot curve {:?} must have a positive exponent, using a linear curve",
+                self.shot_curve
+            );
+            self.shot_curve = ShotCurve::Linear;
+        }
+
+        validate_seconds(
+            "join_timeout",
+            &mut self.join_timeout,
+            defaults.join_timeout,
+        );
+        validate_optional_seconds("credentials_ttl", &mut self.credentials_ttl);
+        validate_seconds(
+            "collision_cooldown",
+            &mut self.collision_cooldown,
+            defaults.collision_cooldown,
+        );
+        validate_optional_seconds("sticky_timeout", &mut self.sticky_timeout);
+        if let PowerUpActivation::Delay(seconds) = self.power_up_activation {
+            if !is_valid_seconds(seconds) {
+                error!(
+                    "Power up activation delay of {seconds} is not a valid number of seconds, activating power ups immediately"
+                );
+                self.power_up_activation = PowerUpActivation::Immediately;
+            }
+        }
+        validate_optional_seconds("max_game_duration", &mut self.max_game_duration);
+        validate_optional_seconds("max_hole_duration", &mut self.max_hole_duration);
+        validate_seconds(
+            "intermission_duration",
+            &mut self.intermission_duration,
+            defaults.intermission_duration,
+        );
+    }
+
+    /// Whether another entity can be placed on a hole with the specified number of placed entities,
+    /// see [Configuration::max_placed_entities].
+    pub(crate) fn can_place_entity(&self, placed: usize) -> bool {
+        self.max_placed_entities.is_none_or(|max| placed < max)
+    }
+
+    /// Limits how steeply a putt is aimed, see [Configuration::max_putt_angle].
+    ///
+    /// The horizontal direction is kept, only the vertical component is reduced.
+    pub(crate) fn limit_putt_direction(&self, direction: Vec3) -> Vec3 {
+        let max_vertical = direction.xz().length() * self.max_putt_angle.to_radians().tan();
+        let vertical = direction.y.clamp(-max_vertical, max_vertical);
+
+        Vec3::new(direction.x, vertical, direction.z)
+    }
+
+    /// Gets the force of a shot limited to the maximum force, or [None] if the input is not a shot.
+    ///
+    /// Putts are limited to the [Configuration::max_putt_angle] before the force is applied,
+    /// so that a negative power cannot reverse the shot.
+    pub(crate) fn shot_force(&self, input: &PlayerInput, max_force: f32) -> Option<Vec3> {
+        match *input {
+            PlayerInput::Putt { direction, power } => {
+                let direction = self.limit_putt_direction(direction).normalize_or_zero();
+                Some(direction * power.clamp(0.0, max_force))
+            }
+            _ => input
+                .movement()
+                .map(|movement| movement.clamp_length_max(max_force)),
+        }
+    }
+
+    /// Gets the impulse of a shot with the specified force on a ball with the specified mass,
+    /// see [Configuration::shot_curve] and [Configuration::scale_shots_by_mass].
+    pub(crate) fn shot_impulse(&self, force: Vec3, mass: Option<Scalar>) -> Vec3 {
+        let impulse = force.normalize_or_zero() * self.shot_curve.apply(force.length());
+        match mass {
+            Some(mass) if self.scale_shots_by_mass => impulse * (mass / BALL_MASS) as f32,
+            _ => impulse,
+        }
+    }
+}
+
+/// Response curve of shots, see [Configuration::shot_curve].
+#[derive(Reflect, Clone, Copy, PartialEq, Debug)]
+pub(crate) enum ShotCurve {
+    /// The impulse is equal to the requested force.
+    Linear,
+    /// Weak shots are weaker and strong shots are stronger, for more precise putts.
+    Quadratic,
+    /// The requested force relative to [MAX_SHOT_FORCE] is raised to the specified exponent.
+    Power(f32),
+}
+
+impl ShotCurve {
+    /// Whether the curve maps each force to a finite impulse.
+    pub(crate) fn is_valid(&self) -> bool {
+        match self {
+            ShotCurve::Power(exponent) => exponent.is_finite() && *exponent > 0.0,
+            _ => true,
+        }
+    }
+
+    /// Gets the impulse to apply for a shot with the specified force.
+    pub(crate) fn apply(&self, force: f32) -> f32 {
+        let exponent = match self {
+            ShotCurve::Linear => return force,
+            ShotCurve::Quadratic => 2.0,
+            ShotCurve::Power(exponent) => *exponent,
+        };
+
+        let relative_force = (force / MAX_SHOT_FORCE).clamp(0.0, 1.0);
+        relative_force.powf(exponent) * MAX_SHOT_FORCE
+    }
+}
+
+/// Random number generator for everything random in a game, like power ups.
+///
+/// Reset at the start of each game, so that the same [Args::seed] produces the same game.
+#[derive(Resource, Deref, DerefMut, Debug)]
+pub(crate) struct GameRng(StdRng);
+
+impl GameRng {
+    fn new(seed: Option<u64>) -> Self {
+        match seed {
+            Some(seed) => GameRng(StdRng::seed_from_u64(seed)),
+            None => GameRng(StdRng::from_os_rng()),
+        }
+    }
+}
+
+impl FromWorld for GameRng {
+    fn from_world(world: &mut World) -> Self {
+        GameRng::new(world.resource::<Args>().seed)
+    }
+}
+
+fn reset_game_rng(mut rng: ResMut<GameRng>, args: Res<Args>) {
+    *rng = GameRng::new(args.seed);
+}
+
 fn load_configuration(server: Res<AssetServer>, mut commands: Commands) {
     commands.spawn((
         Name::new("Configuration"),
@@ -281,12 +743,62 @@ fn load_configuration(server: Res<AssetServer>, mut commands: Commands) {
     ));
 }
 
+fn configure_physics_debug(args: Res<Args>, mut gizmos: ResMut<GizmoConfigStore>) {
+    gizmos.config_mut::<PhysicsGizmos>().0.enabled = args.physics_debug;
+}
+
+/// Replaces configuration values that would panic or produce NaN or infinite shots,
+/// see [Configuration::validate].
+fn validate_configuration(mut configuration: ResMut<Configuration>) {
+    // the configuration was just changed, so validating it does not have to be detected again
+    configuration.bypass_change_detection().validate();
+}
+
+/// Whether the number of seconds can be used for a timer, which panics for negative, NaN or infinite values.
+pub(crate) fn is_valid_seconds(seconds: f32) -> bool {
+    Duration::try_from_secs_f32(seconds).is_ok()
+}
+
+/// Replaces a number of seconds that cannot be used for a timer with the default.
+fn validate_seconds(name: &str, seconds: &mut f32, default: f32) {
+    if !is_valid_seconds(*seconds) {
+        error!("{name} of {seconds} is not a valid number of seconds, using {default}");
+        *seconds = default;
+    }
+}
+
+/// Disables an optional number of seconds that cannot be used for a timer.
+fn validate_optional_seconds(name: &str, seconds: &mut Option<f32>) {
+    if let Some(invalid) = seconds.filter(|seconds| !is_valid_seconds(*seconds)) {
+        error!("{name} of {invalid} is not a valid number of seconds, disabling it");
+        *seconds = None;
+    }
+}
+
+fn apply_physics_configuration(
+    configuration: Res<Configuration>,
+    mut substep_count: ResMut<SubstepCount>,
+) {
+    substep_count.0 = configuration.substep_count;
+}
+
 #[derive(Event, Reflect, Debug)]
 pub(crate) struct ValidPlayerInput {
     pub(crate) player: Entity,
     pub(crate) input: PlayerInput, // todo: need to handle different input types
 }
 
+/// Number of fixed updates a movement input received before the player can move is kept for.
+const PENDING_MOVE_TICKS: u8 = 2;
+
+/// Movement input received shortly before the player could move, applied once it can.
+#[derive(Component, Debug)]
+struct PendingMove {
+    input: PlayerInput,
+    /// Fixed updates until the input is discarded as stale.
+    remaining_ticks: u8,
+}
+
 #[derive(Component, Debug)]
 pub(crate) struct LastPlayerPosition {
     pub(crate) position: Vec3,
@@ -296,8 +808,10 @@ pub(crate) struct LastPlayerPosition {
 fn recv_input(
     mut inputs: EventReader<FromClient<PlayerInput>>,
     mut sessions: Query<&PlayerSession>,
-    mut players: Query<(&Player, &mut PlayerPowerUps)>,
+    players: Query<(&Player, &PlayerPowerUps)>,
+    configuration: Res<Configuration>,
     mut writer: EventWriter<ValidPlayerInput>,
+    mut commands: Commands,
 ) {
     for &FromClient {
         client_entity,
@@ -312,28 +826,60 @@ fn recv_input(
             continue;
         };
 
-        let (player, mut power_ups) = players.get_mut(session.player).unwrap();
-        if input.is_movement() && !player.can_move {
+        let (player, power_ups) = players.get(session.player).unwrap();
+        if !input.is_finite() {
+            warn!(
+                "Received player input from {:?} (player {}) with non-finite values",
+                client_entity,
+                player.display_id()
+            );
+            continue;
+        }
+
+        if !input.has_valid_direction() {
             warn!(
-                "Received player input from {:?} (player {:?}) when it cannot move",
-                client_entity, player
+                "Received player input from {:?} (player {}) without a direction",
+                client_entity,
+                player.display_id()
             );
             continue;
         }
 
+        // putts aimed straight up or with a negative power have no force once they are limited
+        let force = configuration
+            .shot_force(input, MAX_SHOT_FORCE)
+            .map(|force| force.length());
+        if force.is_some_and(|force| force < configuration.min_shot_force) {
+            warn!(
+                "Received player input from {:?} (player {}) below the minimum shot force",
+                client_entity,
+                player.display_id()
+            );
+            continue;
+        }
+
+        if input.is_movement() && !player.can_move {
+            // the ball might come to rest on the server slightly after it did on the client
+            debug!(
+                "Received player input from {:?} (player {}) when it cannot move, keeping it",
+                client_entity,
+                player.display_id()
+            );
+
+            commands.entity(session.player).insert(PendingMove {
+                input: input.clone(),
+                remaining_ticks: PENDING_MOVE_TICKS,
+            });
+            continue;
+        }
+
         if let Some(power_up_type) = input.get_power_up_type() {
+            // the power up is used once the input is applied
             if !power_ups.get_power_ups().contains(&power_up_type) {
                 warn!(
-                    "Received player input with power up {:?} that the player {:?} does not have",
-                    power_up_type, player
-                );
-                continue;
-            }
-
-            if let None = power_ups.use_power_up(power_up_type) {
-                warn!(
-                    "Could not use power up from input {:?} for player {:?}",
-                    input, player
+                    "Received player input with power up {:?} that the player {} does not have",
+                    power_up_type,
+                    player.display_id()
                 );
                 continue;
             }
@@ -346,22 +892,66 @@ fn recv_input(
     }
 }
 
+fn apply_pending_moves(
+    mut players: Query<(Entity, &Player, &mut PendingMove)>,
+    mut writer: EventWriter<ValidPlayerInput>,
+    mut commands: Commands,
+) {
+    for (entity, player, mut pending) in &mut players {
+        if player.can_move {
+            writer.write(ValidPlayerInput {
+                player: entity,
+                input: pending.input.clone(),
+            });
+
+            commands.entity(entity).remove::<PendingMove>();
+        } else if pending.remaining_ticks == 0 {
+            warn!(
+                "Discarding player input from {:?} received when it could not move",
+                player
+            );
+
+            commands.entity(entity).remove::<PendingMove>();
+        } else {
+            pending.remaining_ticks -= 1;
+        }
+    }
+}
+
 fn move_player(
     mut reader: EventReader<ValidPlayerInput>,
     chip_shot: Query<&ChipShotMarker>,
+    transforms: Query<(&Transform, Option<&PlayerHole>), With<Player>>,
+    masses: Query<&ComputedMass, With<Player>>,
+    sensors: Query<(&HoleSensor, &GlobalTransform)>,
+    current_hole: Res<CurrentHole>,
+    configuration: Res<Configuration>,
     mut commands: Commands,
 ) {
     for &ValidPlayerInput { ref input, player } in reader.read() {
-        let PlayerInput::Move(movement) = input else {
+        if !input.is_movement() {
             continue;
-        };
+        }
+
+        let distance_to_cup = transforms
+            .get(player)
+            .ok()
+            .and_then(|(transform, player_hole)| {
+                let cup = current_hole.player_cup_position(player_hole, &sensors)?;
+                Some(transform.translation.distance(cup))
+            });
 
-        let mut force_vec = Vec3::new(movement.x, 0.0, movement.y).clamp_length_max(10.0);
-        force_vec.y = match chip_shot.get(player) {
-            Ok(_) => force_vec.length(),
-            Err(_) => 0.0,
+        let max_force = configuration.max_shot_force(distance_to_cup);
+        let Some(force) = configuration.shot_force(input, max_force) else {
+            continue;
         };
 
+        let mass = masses.get(player).ok().map(ComputedMass::value);
+        let mut force_vec = configuration.shot_impulse(force, mass);
+        if let Ok(_) = chip_shot.get(player) {
+            force_vec.y = force_vec.xz().length();
+        }
+
         commands
             .entity(player)
             .insert(ExternalImpulse::new(force_vec.into()))
@@ -371,9 +961,9 @@ fn move_player(
 
 fn reset_can_move(mut reader: EventReader<ValidPlayerInput>, mut players: Query<&mut Player>) {
     for input in reader.read() {
-        let PlayerInput::Move(_) = input.input else {
+        if !input.input.is_movement() {
             continue;
-        };
+        }
 
         players.get_mut(input.player).unwrap().can_move = false;
     }
@@ -381,44 +971,111 @@ fn reset_can_move(mut reader: EventReader<ValidPlayerInput>, mut players: Query<
 
 fn player_can_move(
     mut player_velocity: Query<
-        (Entity, &mut Player, &Transform, &mut LastPlayerPosition),
+        (
+            Entity,
+            &mut Player,
+            &Transform,
+            &mut LastPlayerPosition,
+            &mut HoleStrokes,
+            &mut PlayerScore,
+            Option<&PlayerHole>,
+        ),
         Added<Sleeping>,
     >,
     holes: Query<&CollidingEntities, With<HoleSensor>>,
+    sensors: Query<(&HoleSensor, &GlobalTransform)>,
     mut current_hole: ResMut<CurrentHole>,
+    configuration: Res<Configuration>,
+    mut writer: EventWriter<ToClients<HoleInOne>>,
+    mut commands: Commands,
 ) {
-    for (entity, mut player, transform, mut position) in &mut player_velocity {
+    for (entity, mut player, transform, mut position, mut strokes, mut score, player_hole) in
+        &mut player_velocity
+    {
+        let cup = current_hole.player_cup_position(player_hole, &sensors);
         let is_in_hole = holes.iter().any(|h| h.contains(&entity));
+        let is_gimme = !is_in_hole
+            && cup
+                .zip(configuration.gimme_radius)
+                .is_some_and(|(cup, radius)| transform.translation.distance(cup) <= radius);
 
-        player.can_move = !is_in_hole;
+        player.can_move = !is_in_hole && !is_gimme;
 
         if player.can_move {
             position.position = transform.translation;
             position.rotation = transform.rotation;
 
             info!("Last position: {position:?}");
-        } else if is_in_hole {
-            info!("Player {:?} completed the hole", entity);
-            current_hole.players.push(*player);
+        } else {
+            let hole = current_hole.hole_of(player_hole);
+
+            // players progressing independently complete their holes once they are scored
+            let completed = match player_hole {
+                Some(_) => !current_hole.is_completed_by(&player),
+                None => current_hole.complete(*player),
+            };
+
+            if !completed {
+                continue;
+            }
+
+            info!("Player {:?} completed hole {:?}", entity, hole);
+
+            if configuration.power_up_activation == PowerUpActivation::FirstCompletion {
+                commands.trigger(ActivatePowerUps);
+            }
+
+            if is_in_hole
+                && configuration.scoring_mode == ScoringMode::Race
+                && current_hole.win(hole, *player)
+            {
+                info!("Player {:?} was the first to sink their ball", entity);
+                score.points += 1;
+            }
+
+            if is_gimme {
+                info!("Player {:?} was given a gimme", entity);
+                strokes.0 += configuration.gimme_strokes;
+                score.score += configuration.gimme_strokes;
+            } else if strokes.0 == 1 {
+                info!("Player {:?} got a hole in one", entity);
+                score.score = score.score.saturating_sub(configuration.hole_in_one_bonus);
+                writer.write(ToClients {
+                    mode: SendMode::Broadcast,
+                    event: HoleInOne { player: player.id },
+                });
+            }
+
+            if player_hole.is_some() {
+                commands.trigger_targets(CompletePlayerHole, entity);
+            }
         }
     }
 }
 
-fn on_player_authenticated(mut reader: EventReader<PlayerAuthenticated>, mut commands: Commands) {
+fn on_player_authenticated(
+    mut reader: EventReader<PlayerAuthenticated>,
+    configuration: Res<Configuration>,
+    game_config: Res<GameConfig>,
+    mut commands: Commands,
+) {
     for authenticated in reader.read() {
+        let mut power_ups = PlayerPowerUps::default();
+        power_ups.retain(|power_up| game_config.is_power_up_enabled(power_up));
+
         commands.entity(authenticated.player).insert((
             LastPlayerPosition {
                 position: Vec3::ZERO,
                 rotation: Quat::IDENTITY,
             },
-            PlayerScore::default(),
-            PlayerPowerUps::default(),
+            PinDistance::default(),
+            power_ups,
             Replicated,
             RigidBody::Dynamic,
-            Collider::sphere(0.021336),
+            Collider::sphere(BALL_RADIUS),
             CollisionLayers::new(GameLayer::Player, [GameLayer::Default]),
-            Mass::from(0.04593),
-            Transform::from_translation(Vec3::ZERO),
+            Mass::from(BALL_MASS),
+            Transform::from_translation(Vec3::Y * configuration.spawn_height),
             Friction::new(0.2),
             Restitution::new(0.99),
             AngularDamping(1.0),
@@ -438,6 +1095,13 @@ fn on_player_authenticated(mut reader: EventReader<PlayerAuthenticated>, mut com
 fn on_disconnected(
     trigger: Trigger<Disconnected>,
     sessions: Query<&PlayerSession>,
+    players: Query<(&PlayerPowerUps, &LastPlayerPosition)>,
+    names: Query<(&Player, &Name)>,
+    transforms: Query<&Transform, With<Player>>,
+    game_state: Option<Res<State<GameState>>>,
+    course_state: Option<Res<State<CourseState>>>,
+    hole_state: Option<Res<State<HoleState>>>,
+    configuration: Res<Configuration>,
     mut commands: Commands,
 ) {
     let client = trigger.target();
@@ -446,5 +1110,788 @@ fn on_disconnected(
         return;
     };
 
+    // keep the player from the lobby, so that they can reconnect before the game starts
+    if game_state.is_some_and(|state| *state.get() == GameState::Waiting) {
+        return;
+    }
+
+    let is_playing = course_state.is_some_and(|state| *state.get() == CourseState::Playing);
+    if configuration.drop_power_ups_on_disconnect && is_playing {
+        if let Ok((power_ups, position)) = players.get(session.player) {
+            drop_power_ups(power_ups, position.position, &mut commands);
+        }
+    }
+
+    let is_hole_playing = hole_state.is_some_and(|state| *state.get() == HoleState::Playing);
+    if configuration.keep_disconnected_balls && is_hole_playing {
+        if let Ok(transform) = transforms.get(session.player) {
+            spawn_abandoned_ball(*transform, &mut commands);
+        }
+    }
+
+    if let Ok((player, name)) = names.get(session.player) {
+        commands.spawn((
+            name.clone(),
+            DisconnectedPlayer { id: player.id },
+            Replicated,
+            StateScoped(ServerState::Playing),
+        ));
+    }
+
     commands.entity(session.player).despawn();
 }
+
+/// Spawns a static ball in place of the ball of a disconnected player, which remains until the hole is completed.
+///
+/// Players are removed immediately when they disconnect, so this is not delayed until a grace period expires.
+fn spawn_abandoned_ball(transform: Transform, commands: &mut Commands) {
+    info!("Leaving abandoned ball at {}", transform.translation);
+
+    commands.spawn((
+        Name::new("Abandoned ball"),
+        AbandonedBall,
+        Replicated,
+        transform,
+        RigidBody::Static,
+        Collider::sphere(BALL_RADIUS),
+        Restitution::new(0.99),
+        StateScoped(HoleState::Playing),
+    ));
+}
+
+/// Distance from the last position of the player at which dropped power ups are placed.
+const DROPPED_POWER_UP_DISTANCE: f32 = 0.25;
+
+/// Spawns the power ups of a player around the position, for other players to pick up.
+fn drop_power_ups(power_ups: &PlayerPowerUps, position: Vec3, commands: &mut Commands) {
+    let power_ups = power_ups.get_power_ups();
+    info!("Dropping {} power ups at {position}", power_ups.len());
+
+    for (index, power_up) in power_ups.iter().enumerate() {
+        let angle = TAU * index as f32 / power_ups.len() as f32;
+        let offset = Vec3::new(angle.cos(), 0.0, angle.sin()) * DROPPED_POWER_UP_DISTANCE;
+
+        commands.spawn((
+            power_up_bundle(*power_up, Transform::from_translation(position + offset)),
+            StateScoped(CourseState::Playing),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::course::{Hole, increment_score},
+        bevy::ecs::{system::RunSystemOnce, world::CommandQueue},
+        minigolf::PowerUp,
+        rand::distr::Distribution,
+    };
+
+    #[test]
+    fn abandoned_ball_is_static() {
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        spawn_abandoned_ball(
+            Transform::from_xyz(1.0, 0.0, 2.0),
+            &mut Commands::new(&mut queue, &world),
+        );
+        queue.apply(&mut world);
+
+        let mut balls = world.query_filtered::<(&RigidBody, &Transform), With<AbandonedBall>>();
+        let (body, transform) = balls.single(&world).unwrap();
+        assert_eq!(*body, RigidBody::Static);
+        assert_eq!(transform.translation, Vec3::new(1.0, 0.0, 2.0));
+    }
+
+    #[test]
+    fn all_power_ups_are_dropped_around_the_player() {
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let held = [PowerUpType::Teleport, PowerUpType::ChipShot];
+        let mut power_ups = PlayerPowerUps::default();
+        power_ups.retain(|power_up| held.contains(&power_up));
+
+        let position = Vec3::new(1.0, 0.0, 2.0);
+        drop_power_ups(&power_ups, position, &mut Commands::new(&mut queue, &world));
+        queue.apply(&mut world);
+
+        let mut dropped = world.query::<(&PowerUp, &Transform)>();
+        assert_eq!(dropped.iter(&world).count(), held.len());
+
+        for (power_up, transform) in dropped.iter(&world) {
+            assert!(held.contains(&power_up.power_up));
+
+            let distance = transform.translation.distance(position);
+            assert!((distance - DROPPED_POWER_UP_DISTANCE).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn putt_direction_is_limited_to_max_angle() {
+        let configuration = Configuration {
+            max_putt_angle: 45.0,
+            ..default()
+        };
+
+        let steep = configuration.limit_putt_direction(Vec3::new(1.0, 5.0, 0.0));
+        assert!((steep.y - 1.0).abs() < 1e-5);
+        assert_eq!(steep.x, 1.0);
+
+        let shallow = Vec3::new(0.0, -0.5, 1.0);
+        assert_eq!(configuration.limit_putt_direction(shallow), shallow);
+
+        let vertical = configuration.limit_putt_direction(Vec3::Y);
+        assert_eq!(vertical, Vec3::ZERO);
+    }
+
+    #[test]
+    fn putts_aimed_upwards_produce_impulse_along_aim() {
+        let configuration = Configuration {
+            max_putt_angle: 45.0,
+            ..default()
+        };
+
+        let putt = PlayerInput::Putt {
+            direction: Vec3::new(0.0, 1.0, 2.0),
+            power: 2.0,
+        };
+        let force = configuration.shot_force(&putt, MAX_SHOT_FORCE).unwrap();
+        let impulse = configuration.shot_impulse(force, Some(BALL_MASS));
+
+        let expected = Vec3::new(0.0, 1.0, 2.0).normalize() * 2.0;
+        assert!((impulse - expected).length() < 1e-5);
+    }
+
+    #[test]
+    fn shot_impulse_scales_with_mass() {
+        let configuration = Configuration::default();
+        let force = Vec3::X * MAX_SHOT_FORCE;
+
+        let normal = configuration.shot_impulse(force, Some(BALL_MASS));
+        assert_eq!(normal, force);
+
+        let huge = configuration.shot_impulse(force, Some(2.0 * BALL_MASS));
+        assert!((huge - 2.0 * force).length() < 1e-5);
+
+        let tiny = configuration.shot_impulse(force, Some(0.5 * BALL_MASS));
+        assert!((tiny - 0.5 * force).length() < 1e-5);
+
+        let unscaled = Configuration {
+            scale_shots_by_mass: false,
+            ..default()
+        };
+        assert_eq!(unscaled.shot_impulse(force, Some(2.0 * BALL_MASS)), force);
+    }
+
+    #[test]
+    fn putts_without_horizontal_force_have_no_force() {
+        let configuration = Configuration::default();
+
+        let reversed = PlayerInput::Putt {
+            direction: Vec3::X,
+            power: -1.0,
+        };
+        let force = configuration.shot_force(&reversed, MAX_SHOT_FORCE).unwrap();
+        assert_eq!(force, Vec3::ZERO);
+
+        let vertical = PlayerInput::Putt {
+            direction: Vec3::Y,
+            power: 1.0,
+        };
+        let force = configuration.shot_force(&vertical, MAX_SHOT_FORCE).unwrap();
+        assert_eq!(force, Vec3::ZERO);
+    }
+
+    #[test]
+    fn shots_below_minimum_force_do_not_count_as_strokes() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<Events<FromClient<PlayerInput>>>();
+        world.init_resource::<Events<ValidPlayerInput>>();
+
+        let mut player = Player::new();
+        player.can_move = true;
+        let player = world
+            .spawn((
+                player,
+                PlayerPowerUps::default(),
+                PlayerScore::default(),
+                HoleStrokes::default(),
+            ))
+            .id();
+        let session = world.spawn(PlayerSession { player }).id();
+
+        for input in [Vec2::ZERO, Vec2::X * 0.001, Vec2::X] {
+            world.send_event(FromClient {
+                client_entity: session,
+                event: PlayerInput::Move(input),
+            });
+        }
+        world.run_system_once(recv_input).unwrap();
+        world.run_system_once(increment_score).unwrap();
+
+        assert_eq!(world.get::<PlayerScore>(player).unwrap().score, 1);
+        assert_eq!(world.get::<HoleStrokes>(player).unwrap().0, 1);
+    }
+
+    #[test]
+    fn moves_received_one_tick_early_are_applied() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<Events<FromClient<PlayerInput>>>();
+        world.init_resource::<Events<ValidPlayerInput>>();
+
+        let early = world.spawn((Player::new(), PlayerPowerUps::default())).id();
+        let stale = world.spawn((Player::new(), PlayerPowerUps::default())).id();
+        for player in [early, stale] {
+            let session = world.spawn(PlayerSession { player }).id();
+            world.send_event(FromClient {
+                client_entity: session,
+                event: PlayerInput::Move(Vec2::X),
+            });
+        }
+
+        world.run_system_once(recv_input).unwrap();
+        world.run_system_once(apply_pending_moves).unwrap();
+        assert!(world.resource::<Events<ValidPlayerInput>>().is_empty());
+
+        world.get_mut::<Player>(early).unwrap().can_move = true;
+        world.run_system_once(apply_pending_moves).unwrap();
+        assert!(world.get::<PendingMove>(early).is_none());
+
+        let events = world.resource::<Events<ValidPlayerInput>>();
+        let applied = events.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].player, early);
+
+        for _ in 0..PENDING_MOVE_TICKS {
+            world.run_system_once(apply_pending_moves).unwrap();
+        }
+        assert!(world.get::<PendingMove>(stale).is_none());
+        assert_eq!(world.resource::<Events<ValidPlayerInput>>().len(), 1);
+    }
+
+    #[test]
+    fn first_player_to_sink_ball_wins_race() {
+        let mut world = World::new();
+        world.insert_resource(Configuration {
+            scoring_mode: ScoringMode::Race,
+            ..default()
+        });
+        world.init_resource::<Events<ToClients<HoleInOne>>>();
+
+        let hole = Hole {
+            start_position: Vec3::ZERO,
+            time_limit: None,
+            gravity_scale: None,
+            deactivation_time: None,
+            sleeping_threshold: None,
+            camera_hint: None,
+            music: None,
+        };
+        let hole_entity = world.spawn_empty().id();
+        world.insert_resource(CurrentHole::new(hole, hole_entity));
+
+        let players = [(); 2].map(|_| {
+            world
+                .spawn((
+                    Player::new(),
+                    Transform::default(),
+                    LastPlayerPosition {
+                        position: Vec3::ZERO,
+                        rotation: Quat::IDENTITY,
+                    },
+                    HoleStrokes(2),
+                    PlayerScore::default(),
+                ))
+                .id()
+        });
+
+        let mut collisions = CollidingEntities::default();
+        collisions.extend(players);
+        world.spawn((
+            HoleSensor::new(hole_entity),
+            GlobalTransform::IDENTITY,
+            collisions,
+        ));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(player_can_move);
+
+        for player in players {
+            world.entity_mut(player).insert(Sleeping);
+            schedule.run(&mut world);
+        }
+
+        let points = players.map(|player| world.get::<PlayerScore>(player).unwrap().points);
+        assert_eq!(points, [1, 0]);
+        assert_eq!(world.resource::<CurrentHole>().players.len(), 2);
+    }
+
+    #[test]
+    fn placed_entities_are_limited() {
+        let configuration = Configuration {
+            max_placed_entities: Some(2),
+            ..default()
+        };
+
+        assert!(configuration.can_place_entity(1));
+        assert!(!configuration.can_place_entity(2));
+        assert!(!configuration.can_place_entity(3));
+
+        let unlimited = Configuration::default();
+        assert!(unlimited.can_place_entity(100));
+    }
+
+    #[test]
+    fn shots_near_cup_are_clamped_more_tightly() {
+        let configuration = Configuration {
+            putting_distance: Some(0.5),
+            putting_max_force: 0.3,
+            ..default()
+        };
+
+        assert_eq!(configuration.max_shot_force(Some(0.2)), 0.3);
+        assert_eq!(configuration.max_shot_force(Some(1.0)), MAX_SHOT_FORCE);
+        assert_eq!(configuration.max_shot_force(None), MAX_SHOT_FORCE);
+
+        let shot = PlayerInput::Move(Vec2::X * MAX_SHOT_FORCE);
+        let max_force = configuration.max_shot_force(Some(0.2));
+        let force = configuration.shot_force(&shot, max_force).unwrap();
+        assert_eq!(force, Vec3::X * 0.3);
+
+        let disabled = Configuration::default();
+        assert_eq!(disabled.max_shot_force(Some(0.0)), MAX_SHOT_FORCE);
+    }
+
+    #[test]
+    fn disallowed_origins_are_rejected() {
+        let args = <Args as clap::Parser>::parse_from([
+            "minigolf_server",
+            "--allowed-origins",
+            "https://minigolf.example",
+        ]);
+
+        assert!(args.is_origin_allowed(Some("https://minigolf.example")));
+        assert!(args.is_origin_allowed(Some("HTTPS://Minigolf.example")));
+        assert!(!args.is_origin_allowed(Some("https://other.example")));
+        assert!(!args.is_origin_allowed(None));
+
+        let args = <Args as clap::Parser>::parse_from(["minigolf_server"]);
+        assert!(args.is_origin_allowed(Some("https://other.example")));
+        assert!(args.is_origin_allowed(None));
+    }
+
+    #[test]
+    fn publish_address_is_secure_with_certificate() {
+        let args = <Args as clap::Parser>::parse_from([
+            "minigolf_server",
+            "--publish-host",
+            "minigolf.example",
+        ]);
+        assert_eq!(
+            args.get_publish_address(),
+            format!("ws://minigolf.example:{}", args.web_socket_port)
+        );
+
+        let args = <Args as clap::Parser>::parse_from([
+            "minigolf_server",
+            "--publish-host",
+            "minigolf.example",
+            "--certificate-filepath",
+            "cert.pem",
+            "--private-key-filepath",
+            "key.pem",
+        ]);
+        assert_eq!(
+            args.get_publish_address(),
+            format!("wss://minigolf.example:{}", args.web_socket_port)
+        );
+
+        // both the certificate and its key are needed for a secure connection
+        let args = <Args as clap::Parser>::parse_from([
+            "minigolf_server",
+            "--certificate-filepath",
+            "cert.pem",
+        ]);
+        assert!(args.get_publish_address().starts_with("ws://"));
+
+        let args = <Args as clap::Parser>::parse_from([
+            "minigolf_server",
+            "--publish-address",
+            "wss://play.example",
+        ]);
+        assert_eq!(args.get_publish_address(), "wss://play.example");
+    }
+
+    #[test]
+    fn shot_curve_transforms_force() {
+        assert_eq!(ShotCurve::Linear.apply(2.5), 2.5);
+        assert_eq!(
+            ShotCurve::Quadratic.apply(MAX_SHOT_FORCE / 2.0),
+            MAX_SHOT_FORCE / 4.0
+        );
+        assert_eq!(ShotCurve::Quadratic.apply(MAX_SHOT_FORCE), MAX_SHOT_FORCE);
+        assert_eq!(
+            ShotCurve::Quadratic.apply(2.0 * MAX_SHOT_FORCE),
+            MAX_SHOT_FORCE
+        );
+
+        let cubic = ShotCurve::Power(3.0).apply(MAX_SHOT_FORCE / 2.0);
+        assert!((cubic - MAX_SHOT_FORCE / 8.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn seconds_that_cannot_be_timers_are_replaced() {
+        let mut configuration = Configuration {
+            join_timeout: -1.0,
+            credentials_ttl: Some(f32::NAN),
+            sticky_timeout: Some(2.0),
+            intermission_duration: f32::INFINITY,
+            ..default()
+        };
+        configuration.validate();
+
+        let defaults = Configuration::default();
+        assert_eq!(configuration.join_timeout, defaults.join_timeout);
+        assert_eq!(configuration.credentials_ttl, None);
+        assert_eq!(configuration.sticky_timeout, Some(2.0));
+        assert_eq!(
+            configuration.intermission_duration,
+            defaults.intermission_duration
+        );
+    }
+
+    #[test]
+    fn invalid_power_up_activation_delay_activates_immediately() {
+        let mut configuration = Configuration {
+            power_up_activation: PowerUpActivation::Delay(-1.0),
+            ..default()
+        };
+        configuration.validate();
+
+        assert_eq!(
+            configuration.power_up_activation,
+            PowerUpActivation::Immediately
+        );
+    }
+
+    #[test]
+    fn shot_curve_exponent_must_be_positive() {
+        assert!(ShotCurve::Power(0.5).is_valid());
+        assert!(!ShotCurve::Power(0.0).is_valid());
+        assert!(!ShotCurve::Power(-1.0).is_valid());
+        assert!(!ShotCurve::Power(f32::NAN).is_valid());
+    }
+
+    #[test]
+    fn same_seed_samples_same_power_ups() {
+        let weights = WeightedPowerUps::default();
+        let sample = |seed| {
+            let mut rng = GameRng::new(Some(seed));
+            (0..20)
+                .map(|_| weights.sample(&mut *rng))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sample(1599), sample(1599));
+    }
+
+    #[test]
+    fn only_single_stroke_completions_are_holes_in_one() {
+        let mut world = World::new();
+        world.insert_resource(Configuration {
+            hole_in_one_bonus: 1,
+            ..default()
+        });
+        world.init_resource::<Events<ToClients<HoleInOne>>>();
+
+        let hole = Hole {
+            start_position: Vec3::ZERO,
+            time_limit: None,
+            gravity_scale: None,
+            deactivation_time: None,
+            sleeping_threshold: None,
+            camera_hint: None,
+            music: None,
+        };
+        let hole_entity = world.spawn_empty().id();
+        world.insert_resource(CurrentHole::new(hole, hole_entity));
+
+        let players = [1, 2].map(|strokes| {
+            world
+                .spawn((
+                    Player::new(),
+                    Transform::default(),
+                    LastPlayerPosition {
+                        position: Vec3::ZERO,
+                        rotation: Quat::IDENTITY,
+                    },
+                    HoleStrokes(strokes),
+                    PlayerScore {
+                        score: strokes,
+                        ..default()
+                    },
+                    Sleeping,
+                ))
+                .id()
+        });
+
+        let mut collisions = CollidingEntities::default();
+        collisions.extend(players);
+        world.spawn((
+            HoleSensor::new(hole_entity),
+            GlobalTransform::IDENTITY,
+            collisions,
+        ));
+
+        world.run_system_once(player_can_move).unwrap();
+
+        let events = world.resource::<Events<ToClients<HoleInOne>>>();
+        let sent = events.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            sent[0].event.player,
+            world.get::<Player>(players[0]).unwrap().id
+        );
+
+        let scores = players.map(|player| world.get::<PlayerScore>(player).unwrap().score);
+        assert_eq!(scores, [0, 2]);
+    }
+
+    #[cfg(feature = "state_log")]
+    #[test]
+    fn state_transitions_are_logged() {
+        use {
+            bevy::log::{Level, tracing, tracing_subscriber},
+            std::{
+                io,
+                sync::{Arc, Mutex},
+            },
+        };
+
+        #[derive(Clone, Default)]
+        struct Logs(Arc<Mutex<Vec<u8>>>);
+
+        impl io::Write for Logs {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        let mut world = World::new();
+        world.init_resource::<Time<Real>>();
+        world.init_resource::<Events<StateTransitionEvent<HoleState>>>();
+        world.send_event(StateTransitionEvent {
+            exited: Some(HoleState::Playing),
+            entered: Some(HoleState::Completed),
+        });
+
+        tracing::subscriber::with_default(subscriber, || {
+            world
+                .run_system_once(log_state_transitions::<HoleState>)
+                .unwrap();
+        });
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("HoleState: Some(Playing) -> Some(Completed)"));
+    }
+
+    #[test]
+    fn configured_substep_count_is_applied() {
+        let mut world = World::new();
+        world.insert_resource(SubstepCount(8));
+        world.insert_resource(Configuration {
+            substep_count: 24,
+            ..default()
+        });
+
+        world.run_system_once(apply_physics_configuration).unwrap();
+        assert_eq!(world.resource::<SubstepCount>().0, 24);
+    }
+
+    #[test]
+    fn abandoned_ball_matches_player_ball() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<Events<PlayerAuthenticated>>();
+        world.insert_resource(GameConfig::new(Vec::new(), None, None));
+
+        let player = world.spawn(Player::new()).id();
+        let session = world.spawn_empty().id();
+        world.send_event(PlayerAuthenticated { player, session });
+        world.run_system_once(on_player_authenticated).unwrap();
+
+        let mut queue = CommandQueue::default();
+        spawn_abandoned_ball(Transform::default(), &mut Commands::new(&mut queue, &world));
+        queue.apply(&mut world);
+
+        let mut abandoned = world.query_filtered::<Entity, With<AbandonedBall>>();
+        let abandoned = abandoned.single(&world).unwrap();
+        let mut balls = world.query::<(&Collider, &Restitution)>();
+        let (player_collider, player_restitution) = balls.get(&world, player).unwrap();
+        let (abandoned_collider, abandoned_restitution) = balls.get(&world, abandoned).unwrap();
+
+        let radius = |collider: &Collider| collider.shape().as_ball().unwrap().radius;
+        assert_eq!(radius(abandoned_collider), radius(player_collider));
+        assert_eq!(abandoned_restitution, player_restitution);
+        assert_eq!(world.get::<PlayerSession>(session).unwrap().player, player);
+    }
+
+    #[test]
+    fn players_disconnecting_mid_game_are_replicated_as_disconnected() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.add_observer(on_disconnected);
+
+        let player = Player::new();
+        let player_entity = world.spawn((player, Name::new("Ada"))).id();
+        let session = world
+            .spawn(PlayerSession {
+                player: player_entity,
+            })
+            .id();
+
+        world.trigger_targets(Disconnected::ByUser("Left the game".to_owned()), session);
+        world.flush();
+
+        assert!(world.get_entity(player_entity).is_err());
+
+        let mut disconnected =
+            world.query_filtered::<(&DisconnectedPlayer, &Name), With<Replicated>>();
+        let (disconnected, name) = disconnected.single(&world).unwrap();
+        assert_eq!(disconnected.id, player.id);
+        assert_eq!(name.as_str(), "Ada");
+    }
+
+    #[test]
+    fn game_phase_follows_server_states() {
+        let mut app = App::new();
+        app.add_plugins((bevy::state::app::StatesPlugin, StatesPlugin));
+        app.init_resource::<Configuration>();
+        app.update();
+
+        fn phase(app: &mut App) -> GamePhase {
+            let world = app.world_mut();
+            *world.query::<&GamePhase>().single(world).unwrap()
+        }
+
+        fn transition<S: bevy::state::state::FreelyMutableState>(app: &mut App, state: S) {
+            app.world_mut().resource_mut::<NextState<S>>().set(state);
+            app.update();
+        }
+
+        transition(&mut app, ServerState::Playing);
+        assert_eq!(phase(&mut app), GamePhase::WaitingForPlayers);
+
+        transition(&mut app, GameState::Playing);
+        assert_eq!(phase(&mut app), GamePhase::WaitingForPlayers);
+
+        transition(&mut app, CourseState::Playing);
+        assert_eq!(phase(&mut app), GamePhase::Playing);
+
+        transition(&mut app, HoleState::Completed);
+        assert_eq!(phase(&mut app), GamePhase::HoleComplete);
+
+        transition(&mut app, HoleState::Playing);
+        assert_eq!(phase(&mut app), GamePhase::Playing);
+
+        transition(&mut app, CourseState::Completed);
+        assert_eq!(phase(&mut app), GamePhase::CourseComplete);
+
+        transition(&mut app, GameState::Completed);
+        assert_eq!(phase(&mut app), GamePhase::GameComplete);
+    }
+
+    #[test]
+    fn physics_debug_flag_toggles_gizmos() {
+        for (args, enabled) in [(vec![], false), (vec!["--physics-debug"], true)] {
+            let mut world = World::new();
+            let mut gizmos = GizmoConfigStore::default();
+            gizmos.register::<PhysicsGizmos>();
+            world.insert_resource(gizmos);
+
+            let args =
+                <Args as clap::Parser>::parse_from(["minigolf_server"].into_iter().chain(args));
+            world.insert_resource(args);
+
+            world.run_system_once(configure_physics_debug).unwrap();
+
+            let gizmos = world.resource::<GizmoConfigStore>();
+            assert_eq!(gizmos.config::<PhysicsGizmos>().0.enabled, enabled);
+        }
+    }
+
+    #[test]
+    fn balls_resting_within_gimme_radius_complete_the_hole() {
+        let mut world = World::new();
+        world.insert_resource(Configuration {
+            gimme_radius: Some(0.1),
+            gimme_strokes: 1,
+            ..default()
+        });
+        world.init_resource::<Events<ToClients<HoleInOne>>>();
+
+        let hole = Hole {
+            start_position: Vec3::ZERO,
+            time_limit: None,
+            gravity_scale: None,
+            deactivation_time: None,
+            sleeping_threshold: None,
+            camera_hint: None,
+            music: None,
+        };
+        let hole_entity = world.spawn_empty().id();
+        world.insert_resource(CurrentHole::new(hole, hole_entity));
+        world.spawn((
+            HoleSensor::new(hole_entity),
+            GlobalTransform::IDENTITY,
+            CollidingEntities::default(),
+        ));
+
+        let [near, far] = [0.05, 0.5].map(|distance| {
+            world
+                .spawn((
+                    Player::new(),
+                    Transform::from_xyz(distance, 0.0, 0.0),
+                    LastPlayerPosition {
+                        position: Vec3::ZERO,
+                        rotation: Quat::IDENTITY,
+                    },
+                    HoleStrokes(2),
+                    PlayerScore {
+                        score: 2,
+                        ..default()
+                    },
+                    Sleeping,
+                ))
+                .id()
+        });
+
+        world.run_system_once(player_can_move).unwrap();
+
+        let near_player = *world.get::<Player>(near).unwrap();
+        assert!(!near_player.can_move);
+        assert!(world.resource::<CurrentHole>().is_completed_by(&near_player));
+        assert_eq!(world.get::<HoleStrokes>(near).unwrap().0, 3);
+        assert_eq!(world.get::<PlayerScore>(near).unwrap().score, 3);
+
+        let far_player = *world.get::<Player>(far).unwrap();
+        assert!(far_player.can_move);
+        assert!(!world.resource::<CurrentHole>().is_completed_by(&far_player));
+    }
+}
@@ -23,12 +23,15 @@ use {
     bevy_replicon::prelude::*,
     core::time::Duration,
     minigolf::{
-        AuthenticatePlayer, Player, PlayerCredentials, RequestAuthentication,
+        AuthenticatePlayer, GAME_COMPLETED_REASON, PROTOCOL_VERSION, Player, PlayerCredentials,
+        PlayerScore, RequestAuthentication,
         lobby::{
-            game::{ClientPacket, ServerPacket},
+            game::{ClientPacket, ProtocolError, ServerPacket},
             user::LobbyMember,
         },
+        shutdown::ShutdownPlugin,
     },
+    rand::Rng,
 };
 
 /// Sets up minigolf server networking.
@@ -36,7 +39,7 @@ pub(crate) struct ServerNetworkPlugin;
 
 impl Plugin for ServerNetworkPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(ServerListenerPlugin);
+        app.add_plugins((ServerListenerPlugin, ShutdownPlugin));
         app.add_plugins(WebSocketClientPlugin);
         app.add_plugins((AeronetTransportPlugin, AeronetRepliconServerPlugin));
         app.add_plugins(RepliconPlugins.set(ServerPlugin {
@@ -67,7 +70,13 @@ impl Plugin for ServerNetworkPlugin {
         app.add_systems(OnEnter(GameState::Waiting), setup_waiting_for_players);
         app.add_systems(
             FixedUpdate,
-            (player_authentication_handler, all_players_joined).in_set(WaitingForPlayersSystems),
+            (
+                player_authentication_handler,
+                remove_missing_players,
+                all_players_joined,
+            )
+                .chain()
+                .in_set(WaitingForPlayersSystems),
         );
 
         app.add_systems(OnExit(ServerState::Playing), disconnect_players);
@@ -76,6 +85,26 @@ impl Plugin for ServerNetworkPlugin {
 
 // Client setup for lobby server
 
+/// Delay before the first reconnection attempt, doubled after each failed attempt.
+const LOBBY_RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+/// Maximum delay between reconnection attempts, before the jitter is added.
+const LOBBY_RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+/// Maximum fraction of the delay that is randomly added to it,
+/// so that game servers which lost the connection at the same time do not reconnect at the same time.
+const LOBBY_RETRY_JITTER: f64 = 0.25;
+
+/// Gets the delay before the reconnection attempt following `attempts` failed attempts.
+///
+/// `jitter` is a random value from 0 to 1.
+fn lobby_retry_delay(attempts: usize, jitter: f64) -> Duration {
+    let factor = 2_u32.saturating_pow(attempts.try_into().unwrap_or(u32::MAX));
+    let delay = LOBBY_RETRY_BASE_DELAY
+        .saturating_mul(factor)
+        .min(LOBBY_RETRY_MAX_DELAY);
+
+    delay.mul_f64(1.0 + LOBBY_RETRY_JITTER * jitter.clamp(0.0, 1.0))
+}
+
 #[derive(Resource, Reflect, Debug)]
 struct LobbyServerConnector {
     timer: Timer,
@@ -83,17 +112,20 @@ struct LobbyServerConnector {
 }
 
 impl LobbyServerConnector {
-    fn retry(&mut self) {
-        if self.attempts >= 5 {
-            panic!(
-                "retried {} times to connect to lobby server without success",
-                self.attempts
-            );
+    /// Schedules another connection attempt, unless there already were `max_attempts` attempts.
+    fn retry(&mut self, max_attempts: usize) -> Result<(), ()> {
+        if self.attempts >= max_attempts {
+            return Err(());
         }
 
+        let delay = lobby_retry_delay(self.attempts, rand::rng().random());
+        info!("Reconnecting to lobby server in {delay:?}");
+
         self.attempts += 1;
+        self.timer.set_duration(delay);
         self.timer.reset();
         self.timer.unpause();
+        Ok(())
     }
 }
 
@@ -125,29 +157,51 @@ fn lobby_connection_messages(
     };
 
     for message in session.recv.drain(..) {
-        let server_packet = ServerPacket::from(message.payload.as_ref());
-        info!("{server_packet:?}");
+        let result = ServerPacket::parse(message.payload.as_ref()).and_then(|server_packet| {
+            info!("{server_packet:?}");
+            lobby_connection_packet(server_packet, &mut server_state)
+        });
 
-        match server_packet {
-            ServerPacket::Hello => {
-                server_state.set(ServerState::WaitingForGame);
-            }
+        if let Err(err) = result {
+            warn!("Ignoring lobby server packet while connecting: {err}");
+        }
+    }
+}
 
-            _ => unimplemented!(),
+fn lobby_connection_packet(
+    server_packet: ServerPacket,
+    server_state: &mut NextState<ServerState>,
+) -> Result<(), ProtocolError> {
+    match server_packet {
+        ServerPacket::Hello => {
+            server_state.set(ServerState::WaitingForGame);
+            Ok(())
         }
+
+        packet => Err(ProtocolError::Unexpected(packet)),
     }
 }
 
 fn on_lobby_disconnected(
     trigger: Trigger<Disconnected>,
     mut connector: ResMut<LobbyServerConnector>,
+    args: Res<Args>,
+    mut exit: EventWriter<AppExit>,
 ) {
-    match trigger.event() {
+    let result = match trigger.event() {
         Disconnected::ByUser(reason) => {
             panic!("Disconnected from lobby server by user; {}", reason)
         }
-        Disconnected::ByPeer(_) => connector.retry(),
-        Disconnected::ByError(_) => connector.retry(),
+        Disconnected::ByPeer(_) => connector.retry(args.lobby_retries),
+        Disconnected::ByError(_) => connector.retry(args.lobby_retries),
+    };
+
+    if result.is_err() {
+        error!(
+            "Retried {} times to connect to lobby server without success, exiting",
+            connector.attempts
+        );
+        exit.write(AppExit::error());
     }
 }
 
@@ -177,6 +231,7 @@ fn connect_to_lobby(mut commands: Commands, args: Res<Args>) {
 
 fn inform_lobby_server(mut sessions: Query<&mut Session, With<WebSocketClient>>, args: Res<Args>) {
     let Ok(mut session) = sessions.single_mut() else {
+        warn!("Not connected to the lobby server, cannot inform it about availability");
         return;
     };
 
@@ -193,6 +248,7 @@ fn game_setup_messages(
     mut server_state: ResMut<NextState<ServerState>>,
     mut commands: Commands,
     config: Res<Configuration>,
+    args: Res<Args>,
 ) {
     let Ok(mut session) = sessions.single_mut() else {
         return;
@@ -201,40 +257,72 @@ fn game_setup_messages(
     let session = &mut *session;
 
     for message in session.recv.drain(..) {
-        let server_packet = ServerPacket::from(message.payload.as_ref());
-        info!("{server_packet:?}");
-
-        match server_packet {
-            ServerPacket::CreateGame(request) => {
-                for (player_id, player_credentials) in request.players.into_iter() {
-                    commands.spawn((
-                        Name::new("Player"),
-                        LobbyMember::from(request.lobby_id),
-                        Player::from(player_id),
-                        player_credentials,
-                    ));
-                }
-
-                let courses = request
-                    .courses
-                    .iter()
-                    .map(|id| {
-                        config
-                            .courses
-                            .iter()
-                            .find(|c| c.id == *id)
-                            .unwrap()
-                            .to_owned()
-                    })
-                    .collect::<Vec<_>>();
-
-                info!("Starting game with courses {:?}", courses);
-                commands.insert_resource(GameConfig::new(courses));
-                server_state.set(ServerState::Playing);
+        let result = ServerPacket::parse(message.payload.as_ref()).and_then(|server_packet| {
+            info!("{server_packet:?}");
+            game_setup_packet(server_packet, &mut server_state, &mut commands, &config)
+        });
+
+        if let Err(err) = result {
+            warn!("Ignoring lobby server packet while waiting for a game: {err}");
+
+            // the lobby server stops assigning games to the server after sending a request
+            if let ProtocolError::Invalid(_) = err {
+                let response: String = ClientPacket::Available(args.get_publish_address()).into();
+                session.send.push(Bytes::from_owner(response));
+            }
+        }
+    }
+}
+
+fn game_setup_packet(
+    server_packet: ServerPacket,
+    server_state: &mut NextState<ServerState>,
+    commands: &mut Commands,
+    config: &Configuration,
+) -> Result<(), ProtocolError> {
+    match server_packet {
+        ServerPacket::CreateGame(request) => {
+            request.validate()?;
+
+            let Some(courses) = request
+                .courses
+                .iter()
+                .map(|id| config.courses.iter().find(|c| c.id == *id).cloned())
+                .collect::<Option<Vec<_>>>()
+            else {
+                let reason = format!("courses {:?} are not available", request.courses);
+                return Err(ProtocolError::Invalid(reason));
+            };
+
+            for (player_id, player_credentials) in request.players.iter().cloned() {
+                commands.spawn((
+                    // the display name, replicated to the clients together with the player
+                    Name::new(request.name(player_id)),
+                    LobbyMember::from(request.lobby_id),
+                    Player::from(player_id),
+                    player_credentials,
+                    // kept when the player reconnects, unlike the components added on authentication
+                    PlayerScore::with_handicap(request.handicap(player_id)),
+                ));
             }
 
-            _ => unimplemented!(),
+            let enabled_power_ups = request
+                .enabled_power_ups
+                .clone()
+                .or_else(|| config.enabled_power_ups.clone());
+
+            let power_ups = config.power_up_weights_of(enabled_power_ups.as_deref());
+            if power_ups.is_none() {
+                info!("No power ups are enabled");
+            }
+
+            info!("Starting game with courses {:?}", courses);
+            commands.insert_resource(GameConfig::new(courses, enabled_power_ups, power_ups));
+            server_state.set(ServerState::Playing);
+            Ok(())
         }
+
+        packet => Err(ProtocolError::Unexpected(packet)),
     }
 }
 
@@ -247,17 +335,31 @@ fn setup_waiting_for_players(
     mut commands: Commands,
     mut sessions: Query<&mut Session, With<WebSocketClient>>,
     lobby_members: Query<&LobbyMember>,
+    config: Res<Configuration>,
 ) {
     info!("Waiting for players");
 
+    commands.insert_resource(JoinTimeout(Timer::from_seconds(
+        config.join_timeout,
+        TimerMode::Once,
+    )));
+
     commands.spawn((
         Name::new("Player session observer"),
         Observer::new(on_connected_while_waiting),
         StateScoped(GameState::Waiting),
     ));
 
-    let lobby_id = lobby_members.iter().next().unwrap().lobby_id;
-    let mut lobby_session = sessions.single_mut().unwrap();
+    let Some(lobby_id) = lobby_members.iter().next().map(|member| member.lobby_id) else {
+        warn!("No lobby members to wait for");
+        return;
+    };
+
+    let Ok(mut lobby_session) = sessions.single_mut() else {
+        warn!("Not connected to the lobby server, cannot inform it about the created game");
+        return;
+    };
+
     let message: String = ClientPacket::GameCreated(lobby_id).into();
     lobby_session.send.push(Bytes::from_owner(message));
 }
@@ -293,6 +395,8 @@ fn on_connected_while_waiting(
 fn player_authentication_handler(
     mut reader: EventReader<FromClient<AuthenticatePlayer>>,
     players: Query<(Entity, &Player, &PlayerCredentials)>,
+    sessions: Query<(Entity, &PlayerSession)>,
+    configuration: Res<Configuration>,
     mut commands: Commands,
     mut writer: EventWriter<PlayerAuthenticated>,
 ) {
@@ -305,6 +409,16 @@ fn player_authentication_handler(
     {
         info!("Received auth request from {:?}", session_entity);
 
+        if new_event.protocol_version != PROTOCOL_VERSION {
+            let reason = format!(
+                "Incompatible client version {}, the server requires version {PROTOCOL_VERSION}",
+                new_event.protocol_version
+            );
+            warn!("Rejecting {session_entity}: {reason}");
+            commands.trigger_targets(Disconnect::new(reason), session_entity);
+            continue;
+        }
+
         let x = players
             .iter()
             .filter(|(_, player, _)| player.id == new_event.id)
@@ -313,14 +427,35 @@ fn player_authentication_handler(
 
         let &[(player_entity, creds)] = x.as_slice() else {
             commands.trigger_targets(Disconnect::new("Player id not found"), session_entity);
-            warn!("player not found");
-            break;
+            warn!("Player {:?} of {session_entity} not found", new_event.id);
+            continue;
         };
 
         if *creds != new_event.credentials {
             commands.trigger_targets(Disconnect::new("Unauthorized"), session_entity);
-            warn!("credentials don't match");
-            break;
+            warn!("Credentials of {session_entity} for {player_entity} don't match");
+            continue;
+        }
+
+        let ttl = configuration.credentials_ttl.and_then(|ttl| {
+            Duration::try_from_secs_f32(ttl)
+                .inspect_err(|error| warn!("Invalid credentials TTL {ttl}: {error}"))
+                .ok()
+        });
+
+        if ttl.is_some_and(|ttl| creds.is_expired(ttl)) {
+            commands.trigger_targets(Disconnect::new("Credentials expired"), session_entity);
+            warn!("Credentials of {session_entity} for {player_entity} expired");
+            continue;
+        }
+
+        // the player reconnected before the previous session timed out
+        for (previous_session, _) in sessions.iter().filter(|(entity, session)| {
+            *entity != session_entity && session.player == player_entity
+        }) {
+            info!("Replacing session {previous_session} of {player_entity}");
+            commands.entity(previous_session).remove::<PlayerSession>();
+            commands.trigger_targets(Disconnect::new("Reconnected"), previous_session);
         }
 
         info!("User {:?} authenticated", player_entity);
@@ -332,13 +467,57 @@ fn player_authentication_handler(
     }
 }
 
+/// Time left for the players from the lobby to join, see [Configuration::join_timeout].
+#[derive(Resource, Debug)]
+struct JoinTimeout(Timer);
+
+/// Removes the players that did not join in time, so that the game can start without them.
+///
+/// Ends the game if no players joined, instead of waiting forever.
+fn remove_missing_players(
+    mut timeout: ResMut<JoinTimeout>,
+    players: Query<Entity, With<Player>>,
+    sessions: Query<&PlayerSession>,
+    time: Res<Time>,
+    mut server_state: ResMut<NextState<ServerState>>,
+    mut commands: Commands,
+) {
+    if !timeout.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let (connected, missing): (Vec<_>, Vec<_>) = players
+        .iter()
+        .partition(|player| sessions.iter().any(|session| session.player == *player));
+
+    for player in missing {
+        warn!("Player {player} did not join in time");
+        commands.entity(player).despawn();
+    }
+
+    if connected.is_empty() {
+        warn!("No players joined in time, ending game");
+        server_state.set(ServerState::WaitingForGame);
+    }
+}
+
+/// Starts the game once every player from the lobby has a connected session.
+///
+/// Sessions are matched to players, so that multiple sessions of the same player are counted once.
 fn all_players_joined(
-    players: Query<(), With<Player>>,
-    authenticated_players: Query<(), (With<Player>, With<Replicated>)>,
+    players: Query<Entity, With<Player>>,
+    sessions: Query<&PlayerSession>,
     mut state: ResMut<NextState<GameState>>,
 ) {
     let total_player_count = players.iter().count();
-    let connected_player_count = authenticated_players.iter().count();
+    let connected_player_count = players
+        .iter()
+        .filter(|player| sessions.iter().any(|session| session.player == *player))
+        .count();
+
+    if total_player_count == 0 {
+        return;
+    }
 
     if total_player_count == connected_player_count {
         info!("All {:?} players joined", total_player_count);
@@ -362,7 +541,11 @@ fn on_opened(trigger: Trigger<OnAdd, Server>, servers: Query<&LocalAddr>) {
     info!("{server} opened on {}", **local_addr);
 }
 
-fn on_session_request(mut request: Trigger<SessionRequest>, clients: Query<&ChildOf>) {
+fn on_session_request(
+    mut request: Trigger<SessionRequest>,
+    clients: Query<&ChildOf>,
+    args: Res<Args>,
+) {
     let client = request.target();
     let Ok(server) = clients.get(client).map(ChildOf::parent) else {
         return;
@@ -373,6 +556,18 @@ fn on_session_request(mut request: Trigger<SessionRequest>, clients: Query<&Chil
         info!("  {header_key}: {header_value}");
     }
 
+    let origin = request
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("origin"))
+        .map(|(_, value)| value.as_str());
+
+    if !args.is_origin_allowed(origin) {
+        warn!("Rejecting {client} connecting from disallowed origin {origin:?}");
+        request.respond(SessionResponse::Forbidden);
+        return;
+    }
+
     request.respond(SessionResponse::Accepted);
 }
 
@@ -381,6 +576,7 @@ fn on_connected(
     servers: Query<&ChildOf>,
     names: Query<&Name>,
     mut sessions: Query<&mut Session>,
+    mut connector: ResMut<LobbyServerConnector>,
 ) {
     let client = trigger.target();
 
@@ -388,6 +584,9 @@ fn on_connected(
         info!("{client} connected to {server}");
     } else if let Ok(name) = names.get(client) {
         info!("Connected to {name}");
+        // only consecutive failed attempts count towards the retry limit
+        connector.attempts = 0;
+
         let mut session = sessions.get_mut(client).unwrap();
 
         let message: String = ClientPacket::Hello.into();
@@ -471,6 +670,314 @@ fn on_player_disconnected(
 
 fn disconnect_players(players: Query<Entity, With<PlayerSession>>, mut commands: Commands) {
     for player in players.iter() {
-        commands.trigger_targets(Disconnect::new("Game completed"), player);
+        commands.trigger_targets(Disconnect::new(GAME_COMPLETED_REASON), player);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        bevy::ecs::system::RunSystemOnce,
+        minigolf::lobby::{PlayerId, game::CreateGameRequest},
+        std::time::Instant,
+    };
+
+    #[test]
+    fn lobby_retry_delay_doubles_up_to_maximum() {
+        assert_eq!(lobby_retry_delay(0, 0.0), LOBBY_RETRY_BASE_DELAY);
+        assert_eq!(lobby_retry_delay(1, 0.0), LOBBY_RETRY_BASE_DELAY * 2);
+        assert_eq!(lobby_retry_delay(2, 0.0), LOBBY_RETRY_BASE_DELAY * 4);
+        assert_eq!(lobby_retry_delay(10, 0.0), LOBBY_RETRY_MAX_DELAY);
+        assert_eq!(lobby_retry_delay(usize::MAX, 0.0), LOBBY_RETRY_MAX_DELAY);
+
+        assert_eq!(
+            lobby_retry_delay(10, 1.0),
+            LOBBY_RETRY_MAX_DELAY.mul_f64(1.0 + LOBBY_RETRY_JITTER)
+        );
+        assert_eq!(
+            lobby_retry_delay(10, 2.0),
+            LOBBY_RETRY_MAX_DELAY.mul_f64(1.0 + LOBBY_RETRY_JITTER)
+        );
+        assert_eq!(lobby_retry_delay(10, -1.0), LOBBY_RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn lobby_retries_are_reset_once_connected() {
+        let mut world = World::new();
+        world.init_resource::<LobbyServerConnector>();
+        world.add_observer(on_connected);
+
+        let mut connector = world.resource_mut::<LobbyServerConnector>();
+        assert_eq!(connector.retry(2), Ok(()));
+        assert_eq!(connector.retry(2), Ok(()));
+        assert_eq!(connector.retry(2), Err(()));
+
+        world.spawn((
+            Name::new("Lobby server connection"),
+            Session::new(Instant::now(), 1200),
+        ));
+        world.flush();
+
+        let mut connector = world.resource_mut::<LobbyServerConnector>();
+        assert_eq!(connector.attempts, 0);
+        assert_eq!(connector.retry(2), Ok(()));
+    }
+
+    #[test]
+    fn disconnect_during_authentication_does_not_block_joining() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<NextState<ServerState>>();
+        world.init_resource::<NextState<GameState>>();
+        world.insert_resource(JoinTimeout(Timer::from_seconds(1.0, TimerMode::Once)));
+
+        let joined = world.spawn(Player::new()).id();
+        world.spawn(PlayerSession { player: joined });
+        // the session of this player disconnected before it was authenticated
+        let disconnected = world.spawn(Player::new()).id();
+
+        world.run_system_once(all_players_joined).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<GameState>>(),
+            NextState::Unchanged
+        ));
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(2));
+        world.run_system_once(remove_missing_players).unwrap();
+        world.run_system_once(all_players_joined).unwrap();
+
+        assert!(world.get_entity(disconnected).is_err());
+        assert!(world.get_entity(joined).is_ok());
+        assert!(matches!(
+            *world.resource::<NextState<GameState>>(),
+            NextState::Pending(GameState::Playing)
+        ));
+        assert!(matches!(
+            *world.resource::<NextState<ServerState>>(),
+            NextState::Unchanged
+        ));
+    }
+
+    #[test]
+    fn unexpected_lobby_server_packets_are_rejected() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<NextState<ServerState>>();
+
+        let result = world
+            .run_system_once(|mut server_state: ResMut<NextState<ServerState>>| {
+                let game = CreateGameRequest {
+                    lobby_id: 1,
+                    players: vec![],
+                    courses: vec![],
+                    handicaps: vec![],
+                    names: vec![],
+                    enabled_power_ups: None,
+                };
+                lobby_connection_packet(ServerPacket::CreateGame(game), &mut server_state)
+            })
+            .unwrap();
+        assert!(matches!(result, Err(ProtocolError::Unexpected(_))));
+
+        let result = world
+            .run_system_once(
+                |mut server_state: ResMut<NextState<ServerState>>,
+                 mut commands: Commands,
+                 config: Res<Configuration>| {
+                    game_setup_packet(
+                        ServerPacket::Hello,
+                        &mut server_state,
+                        &mut commands,
+                        &config,
+                    )
+                },
+            )
+            .unwrap();
+        assert!(matches!(result, Err(ProtocolError::Unexpected(_))));
+
+        assert!(matches!(
+            *world.resource::<NextState<ServerState>>(),
+            NextState::Unchanged
+        ));
+    }
+
+    #[derive(Resource, Default)]
+    struct DisconnectedSessions(Vec<Entity>);
+
+    #[test]
+    fn valid_authentication_after_invalid_one_succeeds() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<Events<FromClient<AuthenticatePlayer>>>();
+        world.init_resource::<Events<PlayerAuthenticated>>();
+        world.init_resource::<DisconnectedSessions>();
+        world.add_observer(
+            |trigger: Trigger<Disconnect>, mut sessions: ResMut<DisconnectedSessions>| {
+                sessions.0.push(trigger.target());
+            },
+        );
+
+        let credentials = PlayerCredentials {
+            secret: "secret".to_owned(),
+            issued_at: 0,
+        };
+        let player = Player::new();
+        let player_entity = world.spawn((player, credentials.clone())).id();
+
+        let [invalid, valid] = [(); 2].map(|_| world.spawn_empty().id());
+        for (session, id) in [(invalid, PlayerId::new()), (valid, player.id)] {
+            world.send_event(FromClient {
+                client_entity: session,
+                event: AuthenticatePlayer {
+                    protocol_version: PROTOCOL_VERSION,
+                    id,
+                    credentials: credentials.clone(),
+                },
+            });
+        }
+
+        world
+            .run_system_once(player_authentication_handler)
+            .unwrap();
+
+        assert_eq!(world.resource::<DisconnectedSessions>().0, [invalid]);
+
+        let events = world.resource::<Events<PlayerAuthenticated>>();
+        let authenticated = events.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(authenticated.len(), 1);
+        assert_eq!(authenticated[0].player, player_entity);
+        assert_eq!(authenticated[0].session, valid);
+    }
+
+    #[test]
+    fn clients_with_other_protocol_version_are_rejected() {
+        #[derive(Resource, Default)]
+        struct DisconnectReasons(Vec<(Entity, String)>);
+
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<Events<FromClient<AuthenticatePlayer>>>();
+        world.init_resource::<Events<PlayerAuthenticated>>();
+        world.init_resource::<DisconnectReasons>();
+        world.add_observer(
+            |trigger: Trigger<Disconnect>, mut reasons: ResMut<DisconnectReasons>| {
+                let reason = trigger.event().reason.clone();
+                reasons.0.push((trigger.target(), reason));
+            },
+        );
+
+        let credentials = PlayerCredentials {
+            secret: "secret".to_owned(),
+            issued_at: 0,
+        };
+        let player = Player::new();
+        world.spawn((player, credentials.clone()));
+
+        let session = world.spawn_empty().id();
+        world.send_event(FromClient {
+            client_entity: session,
+            event: AuthenticatePlayer {
+                protocol_version: PROTOCOL_VERSION + 1,
+                id: player.id,
+                credentials,
+            },
+        });
+
+        world
+            .run_system_once(player_authentication_handler)
+            .unwrap();
+
+        let reasons = &world.resource::<DisconnectReasons>().0;
+        assert_eq!(reasons.len(), 1);
+        assert_eq!(reasons[0].0, session);
+        assert!(reasons[0].1.contains(&PROTOCOL_VERSION.to_string()));
+        assert!(reasons[0].1.contains(&(PROTOCOL_VERSION + 1).to_string()));
+        assert!(world.resource::<Events<PlayerAuthenticated>>().is_empty());
+    }
+
+    #[test]
+    fn reconnecting_sessions_are_not_counted_twice() {
+        let mut world = World::new();
+        world.init_resource::<NextState<GameState>>();
+
+        let reconnected = world.spawn(Player::new()).id();
+        let missing = world.spawn(Player::new()).id();
+
+        // the previous session of the player has not timed out yet
+        world.spawn(PlayerSession {
+            player: reconnected,
+        });
+        world.spawn(PlayerSession {
+            player: reconnected,
+        });
+
+        world.run_system_once(all_players_joined).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<GameState>>(),
+            NextState::Unchanged
+        ));
+
+        world.spawn(PlayerSession { player: missing });
+        world.run_system_once(all_players_joined).unwrap();
+        assert!(matches!(
+            *world.resource::<NextState<GameState>>(),
+            NextState::Pending(GameState::Playing)
+        ));
+    }
+
+    #[test]
+    fn create_game_requests_with_duplicate_players_are_rejected() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.init_resource::<NextState<ServerState>>();
+
+        let player_id = PlayerId::new();
+        let credentials = PlayerCredentials {
+            secret: "secret".to_owned(),
+            issued_at: 0,
+        };
+        let request = CreateGameRequest {
+            lobby_id: 1,
+            players: vec![(player_id, credentials.clone()), (player_id, credentials)],
+            courses: vec![],
+            handicaps: vec![],
+            names: vec![],
+            enabled_power_ups: None,
+        };
+
+        let result = world
+            .run_system_once(
+                move |mut server_state: ResMut<NextState<ServerState>>,
+                      mut commands: Commands,
+                      config: Res<Configuration>| {
+                    let packet = ServerPacket::CreateGame(request.clone());
+                    game_setup_packet(packet, &mut server_state, &mut commands, &config)
+                },
+            )
+            .unwrap();
+
+        assert!(matches!(result, Err(ProtocolError::Invalid(_))));
+        assert_eq!(world.query::<&Player>().iter(&world).count(), 0);
+        assert!(matches!(
+            *world.resource::<NextState<ServerState>>(),
+            NextState::Unchanged
+        ));
+    }
+
+    #[test]
+    fn lobby_messages_without_session_do_not_panic() {
+        let mut world = World::new();
+        world.init_resource::<Configuration>();
+        world.insert_resource(<Args as clap::Parser>::parse_from(["minigolf_server"]));
+        world.spawn((Player::new(), LobbyMember::from(1)));
+
+        world.run_system_once(inform_lobby_server).unwrap();
+        world.run_system_once(setup_waiting_for_players).unwrap();
+
+        // the game is still set up, only the lobby server is not informed
+        assert!(world.contains_resource::<JoinTimeout>());
     }
 }
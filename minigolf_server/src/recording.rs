@@ -0,0 +1,288 @@
+use {
+    crate::{Args, GameState, PlayingSystems, ValidPlayerInput, apply_pending_moves, recv_input},
+    bevy::prelude::*,
+    minigolf::{Player, PlayerInput, lobby::PlayerId},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::VecDeque,
+        fs::File,
+        io::{BufRead, BufReader, BufWriter, Write},
+        path::Path,
+    },
+};
+
+/// Records the valid inputs of a game to a file, or replays them from a file.
+///
+/// Replaying relies on [crate::GameRng] being seeded the same way as in the recorded game,
+/// and the same players joining the game.
+pub(crate) struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayingTick>();
+
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            (reset_tick, start_recording, load_replay),
+        );
+        app.add_systems(OnExit(GameState::Playing), stop_recording);
+
+        app.add_systems(FixedUpdate, increment_tick.in_set(PlayingSystems));
+        app.add_systems(
+            FixedUpdate,
+            (
+                record_inputs
+                    .after(recv_input)
+                    .after(apply_pending_moves)
+                    .before(increment_tick)
+                    .run_if(resource_exists::<Recorder>),
+                replay_inputs
+                    .before(increment_tick)
+                    .in_set(PlayingSystems)
+                    .run_if(resource_exists::<Replay>),
+            ),
+        );
+    }
+}
+
+/// Number of fixed updates while a hole was being played, used to order recorded inputs.
+#[derive(Resource, Default, Debug)]
+struct PlayingTick(u64);
+
+/// A single line of a recording.
+#[derive(Serialize, Deserialize, Debug)]
+struct RecordedInput {
+    tick: u64,
+    player: PlayerId,
+    input: PlayerInput,
+}
+
+#[derive(Resource)]
+struct Recorder {
+    writer: BufWriter<File>,
+}
+
+#[derive(Resource, Debug)]
+struct Replay {
+    inputs: VecDeque<RecordedInput>,
+}
+
+/// Run condition for ignoring inputs from clients while inputs are replayed from a recording.
+pub(crate) fn is_replaying(replay: Option<Res<Replay>>) -> bool {
+    replay.is_some()
+}
+
+fn reset_tick(mut tick: ResMut<PlayingTick>) {
+    tick.0 = 0;
+}
+
+fn increment_tick(mut tick: ResMut<PlayingTick>) {
+    tick.0 += 1;
+}
+
+fn start_recording(args: Res<Args>, mut commands: Commands) {
+    let Some(path) = &args.record else {
+        return;
+    };
+
+    match File::create(path) {
+        Ok(file) => {
+            info!("Recording inputs to {}", path.display());
+            commands.insert_resource(Recorder {
+                writer: BufWriter::new(file),
+            });
+        }
+        Err(err) => error!("Could not create recording {}: {err}", path.display()),
+    }
+}
+
+fn stop_recording(recorder: Option<ResMut<Recorder>>, mut commands: Commands) {
+    commands.remove_resource::<Replay>();
+
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+
+    if let Err(err) = recorder.writer.flush() {
+        error!("Could not write recording: {err}");
+    }
+
+    commands.remove_resource::<Recorder>();
+}
+
+fn record_inputs(
+    mut reader: EventReader<ValidPlayerInput>,
+    players: Query<&Player>,
+    tick: Res<PlayingTick>,
+    mut recorder: ResMut<Recorder>,
+) {
+    for input in reader.read() {
+        let Ok(player) = players.get(input.player) else {
+            continue;
+        };
+
+        let recorded = RecordedInput {
+            tick: tick.0,
+            player: player.id,
+            input: input.input,
+        };
+
+        let result = serde_json::to_writer(&mut recorder.writer, &recorded)
+            .map_err(std::io::Error::from)
+            .and_then(|_| recorder.writer.write_all(b"\n"));
+
+        if let Err(err) = result {
+            error!("Could not record input {:?}: {err}", recorded);
+        }
+    }
+}
+
+fn load_replay(args: Res<Args>, mut commands: Commands) {
+    let Some(path) = &args.replay else {
+        return;
+    };
+
+    match read_replay(path) {
+        Ok(inputs) => {
+            info!("Replaying {} inputs from {}", inputs.len(), path.display());
+            commands.insert_resource(Replay { inputs });
+        }
+        Err(err) => error!("Could not read recording {}: {err}", path.display()),
+    }
+}
+
+fn read_replay(path: &Path) -> std::io::Result<VecDeque<RecordedInput>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+fn replay_inputs(
+    mut replay: ResMut<Replay>,
+    tick: Res<PlayingTick>,
+    players: Query<(Entity, &Player)>,
+    mut writer: EventWriter<ValidPlayerInput>,
+) {
+    while let Some(recorded) = replay.inputs.front() {
+        if recorded.tick > tick.0 {
+            return;
+        }
+
+        let recorded = replay.inputs.pop_front().unwrap();
+        let Some((player, _)) = players.iter().find(|(_, p)| p.id == recorded.player) else {
+            warn!(
+                "Player {:?} from the recording is not in the game",
+                recorded.player
+            );
+            continue;
+        };
+
+        writer.write(ValidPlayerInput {
+            player,
+            input: recorded.input,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            PENDING_MOVE_TICKS, PendingMove,
+            course::{HoleStrokes, increment_score},
+        },
+        minigolf::PlayerScore,
+    };
+
+    fn spawn_players(world: &mut World, ids: [PlayerId; 2]) -> [Entity; 2] {
+        ids.map(|id| {
+            world
+                .spawn((
+                    Player::from(id),
+                    PlayerScore::default(),
+                    HoleStrokes::default(),
+                ))
+                .id()
+        })
+    }
+
+    fn scores(world: &World, players: [Entity; 2]) -> [u32; 2] {
+        players.map(|player| world.get::<PlayerScore>(player).unwrap().score)
+    }
+
+    #[test]
+    fn recorded_inputs_replay_to_same_scores() {
+        let path =
+            std::env::temp_dir().join("minigolf_recorded_inputs_replay_to_same_scores.jsonl");
+        let ids = [Player::new().id, Player::new().id];
+
+        let mut world = World::new();
+        world.init_resource::<PlayingTick>();
+        world.init_resource::<Events<ValidPlayerInput>>();
+        world.insert_resource(Recorder {
+            writer: BufWriter::new(File::create(&path).unwrap()),
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            apply_pending_moves,
+            record_inputs.after(apply_pending_moves),
+            increment_score.after(apply_pending_moves),
+            increment_tick.after(record_inputs),
+        ));
+
+        let [first, second] = spawn_players(&mut world, ids);
+        world.get_mut::<Player>(first).unwrap().can_move = true;
+        world.entity_mut(second).insert(PendingMove {
+            input: PlayerInput::Move(Vec2::X),
+            remaining_ticks: PENDING_MOVE_TICKS,
+        });
+
+        world.send_event(ValidPlayerInput {
+            player: first,
+            input: PlayerInput::Move(Vec2::Y),
+        });
+        schedule.run(&mut world);
+
+        // the pending move is applied once the player can move
+        world.get_mut::<Player>(second).unwrap().can_move = true;
+        schedule.run(&mut world);
+
+        world.send_event(ValidPlayerInput {
+            player: first,
+            input: PlayerInput::Move(Vec2::NEG_Y),
+        });
+        schedule.run(&mut world);
+
+        let mut recorder = world.remove_resource::<Recorder>().unwrap();
+        recorder.writer.flush().unwrap();
+        let recorded_scores = scores(&world, [first, second]);
+        assert_eq!(recorded_scores, [2, 1]);
+
+        let mut world = World::new();
+        world.init_resource::<PlayingTick>();
+        world.init_resource::<Events<ValidPlayerInput>>();
+        world.insert_resource(Replay {
+            inputs: read_replay(&path).unwrap(),
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            replay_inputs,
+            increment_score.after(replay_inputs),
+            increment_tick.after(replay_inputs),
+        ));
+
+        let players = spawn_players(&mut world, ids);
+        for _ in 0..3 {
+            schedule.run(&mut world);
+        }
+
+        assert!(world.resource::<Replay>().inputs.is_empty());
+        assert_eq!(scores(&world, players), recorded_scores);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
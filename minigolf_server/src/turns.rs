@@ -0,0 +1,178 @@
+use {
+    crate::{
+        Configuration, GameState, HoleState, PlayingSystems, ServerState, apply_pending_moves,
+        course::{CurrentHole, HoleStrokes},
+        player_can_move, recv_input,
+    },
+    avian3d::prelude::*,
+    bevy::prelude::*,
+    bevy_replicon::prelude::*,
+    minigolf::{CurrentTurn, Player},
+};
+
+/// Lets players take turns instead of shooting simultaneously, see [Configuration::turn_based].
+pub(crate) struct TurnsPlugin;
+
+impl Plugin for TurnsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Turn>();
+
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            spawn_current_turn.run_if(is_turn_based),
+        );
+        app.add_systems(OnEnter(HoleState::Playing), reset_turn);
+        // player_can_move lets every ball at rest move,
+        // so it must be restricted to the current turn before any inputs are accepted
+        app.add_systems(
+            FixedUpdate,
+            update_turns
+                .after(player_can_move)
+                .before(recv_input)
+                .before(apply_pending_moves)
+                .in_set(PlayingSystems)
+                .run_if(is_turn_based),
+        );
+    }
+}
+
+fn is_turn_based(configuration: Res<Configuration>) -> bool {
+    configuration.turn_based
+}
+
+/// The player whose turn it is, and their strokes when the turn started.
+#[derive(Resource, Default, Debug)]
+struct Turn {
+    player: Option<Entity>,
+    strokes: u32,
+    /// Whether the ball of the player started moving after the shot.
+    ///
+    /// The ball is still at rest right after the shot is taken, until the impulse is applied.
+    shot_moving: bool,
+}
+
+fn spawn_current_turn(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Current turn"),
+        CurrentTurn(None),
+        Replicated,
+        StateScoped(ServerState::Playing),
+    ));
+}
+
+fn reset_turn(mut turn: ResMut<Turn>) {
+    *turn = Turn::default();
+}
+
+/// Passes the turn to the next player once the ball of the current player comes to rest after a shot.
+///
+/// Players take turns ordered by their entity, skipping the ones that already completed the hole.
+fn update_turns(
+    mut players: Query<(Entity, &mut Player, &HoleStrokes, Has<Sleeping>)>,
+    mut current_turn: Query<&mut CurrentTurn>,
+    current_hole: Res<CurrentHole>,
+    mut turn: ResMut<Turn>,
+) {
+    let turn_finished = match turn.player.and_then(|entity| players.get(entity).ok()) {
+        Some((_, player, strokes, is_sleeping)) => {
+            if strokes.0 > turn.strokes && !is_sleeping {
+                turn.shot_moving = true;
+            }
+
+            current_hole.is_completed_by(player) || (turn.shot_moving && is_sleeping)
+        }
+        None => true,
+    };
+
+    if turn_finished {
+        let mut order = players
+            .iter()
+            .filter(|(_, player, ..)| !current_hole.is_completed_by(player))
+            .map(|(entity, ..)| entity)
+            .collect::<Vec<_>>();
+        order.sort();
+
+        let next = order
+            .iter()
+            .find(|entity| Some(**entity) > turn.player)
+            .or(order.first())
+            .copied();
+
+        turn.player = next;
+        turn.shot_moving = false;
+        turn.strokes = next
+            .and_then(|entity| players.get(entity).ok())
+            .map_or(0, |(_, _, strokes, _)| strokes.0);
+
+        let next_id = next
+            .and_then(|entity| players.get(entity).ok())
+            .map(|(_, player, ..)| player.id);
+        info!("Turn of player {next_id:?}");
+
+        for mut current_turn in &mut current_turn {
+            current_turn.0 = next_id;
+        }
+    }
+
+    for (entity, mut player, strokes, is_sleeping) in &mut players {
+        // only a single shot can be taken during a turn
+        let can_move = turn.player == Some(entity)
+            && strokes.0 == turn.strokes
+            && is_sleeping
+            && !current_hole.is_completed_by(&player);
+        if player.can_move != can_move {
+            player.can_move = can_move;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::course::Hole, bevy::ecs::system::RunSystemOnce};
+
+    fn players_that_can_move(world: &mut World) -> Vec<Entity> {
+        world
+            .query::<(Entity, &Player)>()
+            .iter(world)
+            .filter(|(_, player)| player.can_move)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    #[test]
+    fn only_one_player_can_move_at_a_time() {
+        let mut world = World::new();
+        world.init_resource::<Turn>();
+        world.insert_resource(CurrentHole::new(
+            Hole {
+                start_position: Vec3::ZERO,
+                time_limit: None,
+                gravity_scale: None,
+                deactivation_time: None,
+                sleeping_threshold: None,
+                camera_hint: None,
+                music: None,
+            },
+            Entity::PLACEHOLDER,
+        ));
+
+        let first = world.spawn((Player::new(), HoleStrokes(0), Sleeping)).id();
+        let second = world.spawn((Player::new(), HoleStrokes(0), Sleeping)).id();
+
+        world.run_system_once(update_turns).unwrap();
+        assert_eq!(players_that_can_move(&mut world), [first]);
+
+        // the shot was taken, but the ball did not start moving yet
+        world.get_mut::<HoleStrokes>(first).unwrap().0 = 1;
+        world.run_system_once(update_turns).unwrap();
+        assert!(players_that_can_move(&mut world).is_empty());
+
+        world.entity_mut(first).remove::<Sleeping>();
+        world.run_system_once(update_turns).unwrap();
+        assert!(players_that_can_move(&mut world).is_empty());
+
+        world.entity_mut(first).insert(Sleeping);
+        world.run_system_once(update_turns).unwrap();
+        assert_eq!(players_that_can_move(&mut world), [second]);
+    }
+}